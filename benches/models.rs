@@ -1,7 +1,7 @@
 use std::path::Path;
 
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
-use stl_thumb::RenderOptions;
+use stl_thumb::{Mesh, ObjectSelector, RenderOptions, StlMode};
 
 const MODELS_DIR: &str = "test/data";
 
@@ -15,6 +15,7 @@ async fn render(file_path: &Path) {
             cam_position: glam::Vec3::new(2.0, -4.0, 2.0),
             sample_count: 4,
             recalc_normals: false,
+            ..Default::default()
         },
     )
     .await
@@ -51,5 +52,67 @@ fn criterion_benchmark(c: &mut Criterion) {
     }
 }
 
-criterion_group!(benches, criterion_benchmark);
+// Compare the serial and parallel mesh loading with criterion baselines:
+//   cargo bench --bench models -- load --save-baseline serial
+//   cargo bench --bench models --features parallel -- load --baseline serial
+fn load_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load");
+
+    for model in &["shipwreck", "skull"] {
+        let stl_file = format!("{MODELS_DIR}/{model}.stl");
+
+        group.bench_with_input(BenchmarkId::new("stl", model), &stl_file, |b, path| {
+            b.iter(|| {
+                Mesh::load(path, None, StlMode::Auto, &ObjectSelector::All, false)
+                    .expect("Error in load function")
+            });
+        });
+        group.bench_with_input(
+            BenchmarkId::new("stl-recalc-normals", model),
+            &stl_file,
+            |b, path| {
+                b.iter(|| {
+                    Mesh::load(path, None, StlMode::Auto, &ObjectSelector::All, true)
+                        .expect("Error in load function")
+                });
+            },
+        );
+    }
+}
+
+// Many frames of the same model with one renderer, writing over the buffers of the previous
+// frame or allocating new ones for each
+fn buffer_pool_benchmark(c: &mut Criterion) {
+    const FRAMES: usize = 32;
+
+    let rt = tokio::runtime::Runtime::new().expect("Failed to create runtime");
+    let mut group = c.benchmark_group("buffer-pool");
+    let sizes = [(256, 256); FRAMES];
+
+    for model in &["3DBenchy", "skull"] {
+        let stl_file = format!("{MODELS_DIR}/{model}.stl");
+        let stl_path = Path::new(&stl_file);
+
+        for (name, reuse_buffers) in [("pooled", true), ("fresh", false)] {
+            let opts = RenderOptions {
+                reuse_buffers,
+                ..Default::default()
+            };
+            group.bench_with_input(BenchmarkId::new(name, model), &stl_path, |b, path| {
+                b.to_async(&rt).iter(|| async {
+                    stl_thumb::render_multi_size(path, &sizes, &opts)
+                        .await
+                        .expect("Error in render function")
+                });
+            });
+        }
+    }
+}
+
+criterion_group!(
+    benches,
+    criterion_benchmark,
+    load_benchmark,
+    buffer_pool_benchmark
+);
 criterion_main!(benches);
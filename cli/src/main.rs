@@ -7,37 +7,100 @@ extern crate tokio;
 extern crate tracing;
 extern crate tracing_subscriber;
 
-use std::path::Path;
+use std::{
+    io::IsTerminal,
+    path::{Path, PathBuf},
+};
 
 use clap::{Arg, ArgAction, Command};
-use stl_thumb::{render, render_to_file, Config};
+use stl_thumb::{
+    list_adapters, render, render_contact_sheet_to_file, render_mask_to_file,
+    render_multi_size_to_files, render_to_ansi, render_to_file, render_to_ico,
+    render_views_sheet_to_file, sized_filename, AntiAlias, Axis, BitDepth, CenterMode, Config,
+    CullMode, DebugOverlay, GridOptions, InputFormat, Mesh, ObjectSelector, RimLight, StlMode,
+    Topology,
+};
 
-use crate::utils::{html_to_rgba, match_format};
+use crate::utils::{
+    camera_preset, html_to_rgba, match_format, mesh_info_json, parse_fov, parse_rgb, parse_size,
+    parse_vec3, preset_args, print_mesh_info, CAMERA_PRESETS,
+};
 
-fn args() -> Result<(Config, bool), Box<dyn std::error::Error>> {
-    let matches = Command::new(env!("CARGO_PKG_NAME"))
+struct Args {
+    config: Config,
+    /// Print the MD5 hash of the render instead of writing an image
+    md5: bool,
+    /// Write a mask of the model's silhouette instead of the shaded render
+    mask: bool,
+    /// Render every model in the MODEL_FILE directory into a single labeled sheet image
+    contact_sheet: bool,
+    /// Print information about the model instead of rendering it
+    info: bool,
+    /// Print the available GPU adapters instead of rendering
+    list_gpus: bool,
+    /// Load and check the models without rendering them
+    dry_run: bool,
+    /// Print a preview of the model to the terminal instead of writing an image
+    preview_term: bool,
+    /// Print information about the model and render as JSON
+    json: bool,
+    /// Refuse to overwrite existing image files
+    no_clobber: bool,
+}
+
+/// Command line interface of the program.
+fn command() -> Command {
+    Command::new(env!("CARGO_PKG_NAME"))
         .version(env!("CARGO_PKG_VERSION"))
         .author(env!("CARGO_PKG_AUTHORS"))
         .about("Generate thumbnails for STL files")
         .arg(
             Arg::new("MODEL_FILE")
-                .help("STL file. Use - to read from stdin instead of a file.")
-                .required(true)
+                .help("STL file. Use - to read from stdin instead of a file, or a file:// URI, or an http(s):// URI with --allow-remote.")
+                .required_unless_present("list_gpus")
                 .index(1),
         )
         .arg(
             Arg::new("IMG_FILE")
                 .help("Thumbnail image file. Use - to write to stdout instead of a file.")
-                .required(true)
+                .required_unless_present_any(["info", "preview_term", "list_gpus", "dry_run"])
                 .index(2),
         )
         .arg(
             Arg::new("format")
-                .help("The format of the image file. Supported formats: PNG, JPEG, GIF, ICO, BMP")
+                .help("The format of the image file. Supported formats: PNG, JPEG, GIF, ICO, BMP, EXR")
                 .short('f')
                 .long("format")
                 .action(ArgAction::Set)
-                .value_parser(["png", "jpeg", "gif", "ico", "bmp"]),
+                .value_parser(["png", "jpeg", "gif", "ico", "bmp", "exr"]),
+        )
+        .arg(
+            Arg::new("bit_depth")
+                .help("Bits per channel of the image file. 16 is only supported by PNG, 32 writes linear floats and is the default for EXR")
+                .long("bit-depth")
+                .action(ArgAction::Set)
+                .value_parser(["8", "16", "32"]),
+        )
+        .arg(
+            Arg::new("input_format")
+                .help("The format of the model file. Required when reading a non-STL model from stdin. Supported formats: STL, OBJ, 3MF, and zip archives holding one of them")
+                .long("input-format")
+                .action(ArgAction::Set)
+                .value_parser(["stl", "obj", "3mf", "zip"]),
+        )
+        .arg(
+            Arg::new("stl_mode")
+                .help("Encoding of STL model files, use to override the detection for files with a misleading header")
+                .long("stl-mode")
+                .action(ArgAction::Set)
+                .value_parser(["auto", "ascii", "binary"]),
+        )
+        .arg(
+            Arg::new("object")
+                .help("Only render the object with this index (counted from 0) or name, for OBJ and 3MF models with several objects")
+                .long("object")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(String)),
         )
         .arg(
             Arg::new("size")
@@ -47,6 +110,15 @@ fn args() -> Result<(Config, bool), Box<dyn std::error::Error>> {
                 .action(ArgAction::Set)
                 .value_parser(clap::value_parser!(String))
         )
+        .arg(
+            Arg::new("sizes")
+                .help("Comma-separated list of sizes to render, each a number (square) or <width>x<height>. The size is appended to the name of each image file")
+                .long("sizes")
+                .alias("output-size-list")
+                .action(ArgAction::Set)
+                .value_delimiter(',')
+                .value_parser(clap::value_parser!(String))
+        )
         .arg(
             Arg::new("verbosity")
                 .help("Increase message verbosity")
@@ -61,18 +133,73 @@ fn args() -> Result<(Config, bool), Box<dyn std::error::Error>> {
                 .long("background")
                 .action(ArgAction::Set)
         )
+        .arg(
+            Arg::new("matte")
+                .help("Color (rgb) the transparent parts of the image are blended into, keeping the alpha channel. Use when the thumbnail is shown over a known background color")
+                .long("matte")
+                .action(ArgAction::Set)
+                .value_parser(parse_rgb)
+        )
+        .arg(
+            Arg::new("auto_levels")
+                .help("Stretch the brightness of the model to a consistent range, so dark and bright models get similar thumbnails")
+                .long("auto-levels")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("recalc_normals")
                 .help("Force recalculation of face normals. Use when dealing with malformed STL files.")
                 .long("recalc-normals")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("reject_non_finite")
+                .help("Fail on models with NaN or infinite vertex coordinates, instead of skipping the triangles using them")
+                .long("reject-non-finite")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("allow_remote")
+                .help("Download models given as http:// or https:// URIs. Only local files and file:// URIs are read otherwise")
+                .long("allow-remote")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("strict_normals")
+                .help("Fail on models with zero area triangles or zero normals, instead of shading them black")
+                .long("strict-normals")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("auto_fix_winding")
+                .help("Flip triangles wound inconsistently with their neighbours, so they aren't culled")
+                .long("fix-winding")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("convex_hull")
+                .help("Render the convex hull of the model instead of its triangles, for a simplified silhouette")
+                .long("convex-hull")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("auto_color_objects")
+                .help("Color each object of OBJ and 3MF files differently, replacing their colors")
+                .long("color-objects")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("embed_model_size")
+                .help("Store the size of the model in model units in PNG images")
+                .long("embed-size")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("cam_fov_deg")
-                .help("Camera field of view in degrees")
+                .help("Camera field of view in degrees, along the shorter side of the image")
                 .long("cam-fov-deg")
                 .action(ArgAction::Set)
-                .value_parser(clap::value_parser!(f32))
+                .value_parser(parse_fov)
         )
         .arg(
             Arg::new("cam_position")
@@ -81,6 +208,289 @@ fn args() -> Result<(Config, bool), Box<dyn std::error::Error>> {
                 .action(ArgAction::Set)
                 .value_parser(clap::value_parser!(String))
         )
+        .arg(
+            Arg::new("rotate")
+                .help("Rotation of the model around the x, y and z axes in degrees as a comma-separated list of three floats (x,y,z), applied before framing")
+                .long("rotate")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(String))
+        )
+        .arg(
+            Arg::new("mirror_x")
+                .help("Mirror the model along its x axis, e.g. for models authored with the other handedness")
+                .long("mirror-x")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["mirror_y", "mirror_z"]),
+        )
+        .arg(
+            Arg::new("mirror_y")
+                .help("Mirror the model along its y axis")
+                .long("mirror-y")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("mirror_z"),
+        )
+        .arg(
+            Arg::new("mirror_z")
+                .help("Mirror the model along its z axis")
+                .long("mirror-z")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("view")
+                .help("Look at the model from a standard view, at the distance of --cam-position")
+                .long("view")
+                .action(ArgAction::Set)
+                .value_parser(CAMERA_PRESETS),
+        )
+        .arg(
+            Arg::new("orthographic")
+                .help("Use an orthographic projection, where parallel lines stay parallel. --cam-fov-deg is ignored")
+                .long("orthographic")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("views")
+                .help("Comma-separated list of views rendered orthographically into a single labeled sheet image")
+                .long("views")
+                .action(ArgAction::Set)
+                .value_delimiter(',')
+                .value_parser(CAMERA_PRESETS)
+                .conflicts_with_all(["sizes", "view"]),
+        )
+        .arg(
+            Arg::new("sheet_columns")
+                .help("Number of cells in each row of the --views or --contact-sheet sheet. Default is 3")
+                .long("sheet-columns")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(usize))
+        )
+        .arg(
+            Arg::new("auto_frame")
+                .help("Move the camera along the direction of --cam-position so the model fills the frame regardless of the field of view")
+                .long("auto-frame")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("auto_up")
+                .help("Turn models whose format is Y-up by convention, like OBJ, so they stand upright like STL and 3MF models")
+                .long("auto-up")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("shininess")
+                .help("Specular exponent of the model material, higher values give smaller highlights")
+                .long("shininess")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(f32))
+        )
+        .arg(
+            Arg::new("specular_strength")
+                .help("Intensity of the specular highlights, 0 gives a matte look")
+                .long("specular")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(f32))
+        )
+        .arg(
+            Arg::new("rim_light")
+                .help("Color (rgb) of a light along the silhouette of the model, setting it apart from the background")
+                .long("rim-light")
+                .action(ArgAction::Set)
+                .value_parser(parse_rgb)
+        )
+        .arg(
+            Arg::new("rim_power")
+                .help("Falloff exponent of the rim light, higher values give thinner rims. Default is 3")
+                .long("rim-power")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(f32))
+                .requires("rim_light"),
+        )
+        .arg(
+            Arg::new("points")
+                .help("Draw a point at each vertex instead of the faces. Models without faces are always drawn as points")
+                .long("points")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("edges")
+                .help("Draw lines along the creases and silhouette of the model, where they aren't hidden by it")
+                .long("edges")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("toon")
+                .help("Shade the model with this many flat light levels for a cartoon look. Combine with --edges for outlines")
+                .long("toon")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(u8).range(2..)),
+        )
+        .arg(
+            Arg::new("xray")
+                .help("Draw the faces see-through with this opacity, from 0 to 1, so their light adds up where they overlap. Shows shells and internal cavities")
+                .long("xray")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(f32))
+                .conflicts_with_all(["toon", "edges"]),
+        )
+        .arg(
+            Arg::new("edge_color")
+                .help("Color (rgb) of the edge lines. Default is 000000")
+                .long("edge-color")
+                .action(ArgAction::Set)
+                .value_parser(parse_rgb)
+                .requires("edges"),
+        )
+        .arg(
+            Arg::new("edge_thickness")
+                .help("Width of the edge lines in pixels. Default is 1.5")
+                .long("edge-thickness")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(f32))
+                .requires("edges"),
+        )
+        .arg(
+            Arg::new("ambient_occlusion")
+                .help("Darken creases and corners with ambient occlusion. Slower to render")
+                .long("ambient-occlusion")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("ao_strength")
+                .help("How much fully occluded areas are darkened by ambient occlusion, from 0 to 1")
+                .long("ao-strength")
+                .requires("ambient_occlusion")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(f32))
+        )
+        .arg(
+            Arg::new("ao_radius")
+                .help("Distance searched for occluders by ambient occlusion, the model is scaled to fit a 2x2x2 box")
+                .long("ao-radius")
+                .requires("ambient_occlusion")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(f32))
+        )
+        .arg(
+            Arg::new("focal_distance")
+                .help("Blur the model away from this distance from the camera (depth of field), the model is scaled to fit a 2x2x2 box")
+                .long("focal-distance")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(f32))
+        )
+        .arg(
+            Arg::new("aperture")
+                .help("Size of the depth of field blur, as a fraction of the image height")
+                .long("aperture")
+                .requires("focal_distance")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(f32))
+        )
+        .arg(
+            Arg::new("cull")
+                .help("Faces skipped when drawing the model. Use none for open surfaces and scans with inconsistent winding. Default is back")
+                .long("cull")
+                .action(ArgAction::Set)
+                .value_parser(["back", "front", "none"]),
+        )
+        .arg(
+            Arg::new("center")
+                .help("Point of the model placed at the center of the image: the center of its bounding box, its area weighted centroid or the center of a sphere around it. Default is bbox")
+                .long("center")
+                .action(ArgAction::Set)
+                .value_parser(["bbox", "centroid", "sphere"]),
+        )
+        .arg(
+            Arg::new("two_sided")
+                .help("Light the back of faces like their front, for open and thin surfaces drawn with --cull none")
+                .long("two-sided")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("highlight_backfaces")
+                .help("Draw the back of faces in a flat color (rgb), so faces with inverted normals stand out from shadows. Default is ff00ff")
+                .long("highlight-backfaces")
+                .action(ArgAction::Set)
+                .value_parser(parse_rgb)
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("ff00ff"),
+        )
+        .arg(
+            Arg::new("point_size")
+                .help("Size in pixels of the points drawn for point clouds")
+                .long("point-size")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(f32))
+        )
+        .arg(
+            Arg::new("debug_overlay")
+                .help("Draw the bounding box and/or the axes of the model over it, to debug framing issues")
+                .long("debug-overlay")
+                .action(ArgAction::Set)
+                .value_parser(["none", "bbox", "axes", "both"]),
+        )
+        .arg(
+            Arg::new("wireframe")
+                .help("Draw the edges of the triangles in black over the model")
+                .long("wireframe")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("line_width")
+                .help("Thickness in pixels of the --wireframe, --grid and --debug-overlay lines. Default is 1")
+                .long("line-width")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(f32))
+        )
+        .arg(
+            Arg::new("grid")
+                .help("Draw a grid with lines this many model units apart on the floor beneath the model, as a reference for its scale")
+                .long("grid")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(f32))
+        )
+        .arg(
+            Arg::new("grid_color")
+                .help("Color (rgb) of the grid lines. Default is 808080")
+                .long("grid-color")
+                .action(ArgAction::Set)
+                .value_parser(parse_rgb)
+                .requires("grid"),
+        )
+        .arg(
+            Arg::new("grid_extent")
+                .help("How far the grid reaches past the model on each side, as a fraction of its longest side. Default is 0.25")
+                .long("grid-extent")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(f32))
+                .requires("grid"),
+        )
+        .arg(
+            Arg::new("depth_bias")
+                .help("Push the faces of the model back by this many depth units, so the wireframe isn't hidden by them")
+                .long("depth-bias")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(i32))
+        )
+        .arg(
+            Arg::new("depth_bias_slope")
+                .help("Push the faces of the model back in proportion to their slope, for faces seen at a grazing angle")
+                .long("depth-bias-slope")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(f32))
+        )
+        .arg(
+            Arg::new("no_depth_test")
+                .help("Draw the faces of the model over each other in file order instead of hiding those behind nearer ones")
+                .long("no-depth-test")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no_depth_write")
+                .help("Don't record the depth of the faces of the model, so faces drawn later are never hidden by them")
+                .long("no-depth-write")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("sample_count")
                 .help("Number of samples for anti-aliasing")
@@ -88,45 +498,191 @@ fn args() -> Result<(Config, bool), Box<dyn std::error::Error>> {
                 .action(ArgAction::Set)
                 .value_parser(clap::value_parser!(u32))
         )
+        .arg(
+            Arg::new("antialiasing")
+                .help("Anti-aliasing quality. Default is msaa4")
+                .short('a')
+                .long("antialiasing")
+                .action(ArgAction::Set)
+                .value_parser(["off", "msaa2", "msaa4", "msaa8"])
+                .conflicts_with("sample_count"),
+        )
+        .arg(
+            Arg::new("fxaa")
+                .help("Smooth jagged edges with a post-processing filter (FXAA). Works on GPUs without multisampling support")
+                .long("antialias-edges")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("shader")
+                .help("WGSL shader file replacing the one the model is drawn with, for custom shading. It needs the vert_main and frag_main entry points of the built-in shader")
+                .long("shader")
+                .action(ArgAction::Set),
+        )
+        .arg(
+            Arg::new("taa_samples")
+                .help("Average this many frames, each sampling the pixels at another point inside them. Smooths edges without the memory of multisampling")
+                .long("taa-samples")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(u8).range(1..))
+        )
+        .arg(
+            Arg::new("no_tiling")
+                .help("Fail on sizes larger than the GPU texture limit instead of rendering them in tiles")
+                .long("no-tiling")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("allow_software")
+                .help("Fall back to software rendering if no GPU is available")
+                .long("allow-software")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("list_gpus")
+                .help("Print the available GPU adapters and exit")
+                .long("list-gpus")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("dry_run")
+                .help("Load and check MODEL_FILE without rendering it, or every model in it and its subdirectories if it is a directory. Prints a line for each model and fails if any can't be loaded")
+                .long("dry-run")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("md5")
                 .help("Calculate MD5 hash of the rendered model")
                 .long("md5")
                 .action(ArgAction::SetTrue),
         )
-        .get_matches();
+        .arg(
+            Arg::new("mask")
+                .help("Write a grayscale mask of the model's silhouette instead of the shaded render")
+                .long("mask")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("contact_sheet")
+                .help("Render every model in MODEL_FILE, a directory, and its subdirectories into a single sheet image, each in a cell the size given by --size and labeled with its file name")
+                .long("contact-sheet")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["views", "sizes", "mask"]),
+        )
+        .arg(
+            Arg::new("info")
+                .help("Print information about the model instead of rendering it")
+                .long("info")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("preview_term")
+                .help("Print a preview of the model to the terminal as text instead of writing an image. It is as wide as the COLUMNS environment variable, or 80 characters")
+                .long("preview-term")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("json")
+                .help("Print information about the model and render as JSON to stdout. Only for a single image or with --info")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "md5",
+                    "sizes",
+                    "views",
+                    "mask",
+                    "contact_sheet",
+                    "preview_term",
+                    "dry_run",
+                    "list_gpus",
+                ]),
+        )
+        .arg(
+            Arg::new("overwrite")
+                .help("Overwrite IMG_FILE if it already exists. This is the default")
+                .long("overwrite")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no_clobber")
+                .help("Fail instead of overwriting IMG_FILE if it already exists")
+                .long("no-clobber")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("overwrite"),
+        )
+        .arg(
+            Arg::new("config")
+                .help("TOML file with default values for the options, e.g. `size = 256`. Options given on the command line take precedence")
+                .long("config")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(String))
+        )
+}
+
+fn args() -> Result<Args, Box<dyn std::error::Error>> {
+    let cli_args = std::env::args_os().collect::<Vec<_>>();
+    let command = command();
+    let mut matches = command.clone().get_matches_from(&cli_args);
+
+    // Options from a preset file are inserted before the ones given on the command line, unless
+    // the command line already sets them or an option they conflict with
+    if let Some(preset) = matches.get_one::<String>("config") {
+        let preset_args = preset_args(&command, &matches, Path::new(preset))?;
+        if !preset_args.is_empty() {
+            let mut args = cli_args[..1].to_vec();
+            args.extend(preset_args.into_iter().map(Into::into));
+            args.extend_from_slice(&cli_args[1..]);
+            matches = command.get_matches_from(args);
+        }
+    }
 
     let mut c = Config {
         model_filename: matches
             .get_one::<String>("MODEL_FILE")
-            .ok_or("MODEL_FILE not provided")?
-            .to_string(),
+            .cloned()
+            .unwrap_or_default(),
         img_filename: matches
             .get_one::<String>("IMG_FILE")
-            .ok_or("IMG_FILE not provided")?
-            .to_string(),
+            .cloned()
+            .unwrap_or_default(),
         verbosity: matches.get_count("verbosity").into(),
         recalc_normals: matches.get_flag("recalc_normals"),
+        reject_non_finite: matches.get_flag("reject_non_finite"),
+        allow_remote: matches.get_flag("allow_remote"),
+        strict_normals: matches.get_flag("strict_normals"),
+        auto_color_objects: matches.get_flag("auto_color_objects"),
+        auto_fix_winding: matches.get_flag("auto_fix_winding"),
+        convex_hull: matches.get_flag("convex_hull"),
+        embed_model_size: matches.get_flag("embed_model_size"),
+        auto_frame: matches.get_flag("auto_frame"),
+        auto_levels: matches.get_flag("auto_levels"),
+        auto_up: matches.get_flag("auto_up"),
+        wireframe: matches.get_flag("wireframe"),
+        edges: matches.get_flag("edges"),
+        orthographic: matches.get_flag("orthographic"),
+        allow_software: matches.get_flag("allow_software"),
+        ambient_occlusion: matches.get_flag("ambient_occlusion"),
+        two_sided: matches.get_flag("two_sided"),
+        fxaa: matches.get_flag("fxaa"),
+        tiled: !matches.get_flag("no_tiling"),
+        depth_test: !matches.get_flag("no_depth_test"),
+        depth_write: !matches.get_flag("no_depth_write"),
         ..Default::default()
     };
 
     if let Some(size) = matches.get_one::<String>("size") {
-        if let Ok(size_num) = size.parse::<u16>() {
-            c.width = size_num;
-            c.height = size_num;
-        } else if let Some((width, height)) = size.split_once('x') {
-            c.width = width
-                .trim()
-                .parse::<u16>()
-                .map_err(|_| "Invalid width in size")?;
-            c.height = height
-                .trim()
-                .parse::<u16>()
-                .map_err(|_| "Invalid height in size")?;
-        } else {
+        (c.width, c.height) = parse_size(size)?;
+    }
+
+    if let Some(sizes) = matches.get_many::<String>("sizes") {
+        c.sizes = sizes
+            .map(|size| parse_size(size))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if c.img_filename == "-" {
             return Err(Box::new(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
-                "Invalid size format. Use a number or <width>x<height>",
+                "IMG_FILE can't be '-' when using --sizes",
             )));
         }
     }
@@ -135,45 +691,206 @@ fn args() -> Result<(Config, bool), Box<dyn std::error::Error>> {
         c.format = match_format(format);
     }
 
+    c.bit_depth = match matches.get_one::<String>("bit_depth").map(String::as_str) {
+        Some("16") => BitDepth::Sixteen,
+        Some("32") => BitDepth::Float,
+        Some(_) => BitDepth::Eight,
+        None if c.format == image::ImageFormat::OpenExr => BitDepth::Float,
+        None => c.bit_depth,
+    };
+
+    if let Some(input_format) = matches.get_one::<String>("input_format") {
+        c.input_format = InputFormat::from_extension(input_format);
+    }
+
+    if let Some(stl_mode) = matches.get_one::<String>("stl_mode") {
+        c.stl_mode = match stl_mode.as_str() {
+            "ascii" => StlMode::Ascii,
+            "binary" => StlMode::Binary,
+            _ => StlMode::Auto,
+        };
+    }
+
+    if let Some(object) = matches.get_one::<String>("object") {
+        c.object_selector = object.parse().map_or_else(
+            |_| ObjectSelector::ByName(object.clone()),
+            ObjectSelector::ByIndex,
+        );
+    }
+
     if let Some(background) = matches.get_one::<String>("background") {
         c.background = html_to_rgba(background);
     }
 
+    if let Some(&matte) = matches.get_one::<(f32, f32, f32)>("matte") {
+        c.matte_background = Some(matte);
+    }
+
     if let Some(cam_fov_deg) = matches.get_one::<f32>("cam_fov_deg") {
         c.cam_fov_deg = *cam_fov_deg;
     }
 
+    if let Some(shininess) = matches.get_one::<f32>("shininess") {
+        c.shininess = *shininess;
+    }
+
+    if let Some(specular_strength) = matches.get_one::<f32>("specular_strength") {
+        c.specular_strength = *specular_strength;
+    }
+    if let Some(&backface_color) = matches.get_one::<(f32, f32, f32)>("highlight_backfaces") {
+        c.highlight_backfaces = Some(backface_color);
+    }
+    if let Some(&rim_color) = matches.get_one::<(f32, f32, f32)>("rim_light") {
+        c.rim_light = Some(RimLight {
+            color: rim_color.into(),
+            power: matches
+                .get_one::<f32>("rim_power")
+                .copied()
+                .unwrap_or(RimLight::default().power),
+        });
+    }
+
+    if let Some(ao_strength) = matches.get_one::<f32>("ao_strength") {
+        c.ao_strength = *ao_strength;
+    }
+
+    if let Some(ao_radius) = matches.get_one::<f32>("ao_radius") {
+        c.ao_radius = *ao_radius;
+    }
+
+    if let Some(focal_distance) = matches.get_one::<f32>("focal_distance") {
+        c.focal_distance = Some(*focal_distance);
+    }
+    if let Some(aperture) = matches.get_one::<f32>("aperture") {
+        c.aperture = *aperture;
+    }
+
+    if matches.get_flag("points") {
+        c.topology = Topology::Points;
+    }
+
+    if let Some(toon_bands) = matches.get_one::<u8>("toon") {
+        c.toon_bands = *toon_bands;
+    }
+    if let Some(xray_opacity) = matches.get_one::<f32>("xray") {
+        c.xray_opacity = Some(*xray_opacity);
+    }
+    if let Some(&edge_color) = matches.get_one::<(f32, f32, f32)>("edge_color") {
+        c.edge_color = edge_color;
+    }
+    if let Some(edge_thickness) = matches.get_one::<f32>("edge_thickness") {
+        c.edge_thickness = *edge_thickness;
+    }
+
+    if let Some(line_width) = matches.get_one::<f32>("line_width") {
+        c.line_width = *line_width;
+    }
+    if let Some(spacing) = matches.get_one::<f32>("grid") {
+        let default = GridOptions::default();
+        c.grid = Some(GridOptions {
+            spacing: *spacing,
+            color: matches
+                .get_one::<(f32, f32, f32)>("grid_color")
+                .map_or(default.color, |&color| color.into()),
+            extent: matches
+                .get_one::<f32>("grid_extent")
+                .copied()
+                .unwrap_or(default.extent),
+        });
+    }
+
+    if let Some(cull) = matches.get_one::<String>("cull") {
+        c.cull_mode = match cull.as_str() {
+            "front" => CullMode::Front,
+            "none" => CullMode::None,
+            _ => CullMode::Back,
+        };
+    }
+
+    if let Some(center) = matches.get_one::<String>("center") {
+        c.center_mode = match center.as_str() {
+            "centroid" => CenterMode::Centroid,
+            "sphere" => CenterMode::BoundingSphere,
+            _ => CenterMode::BoundingBox,
+        };
+    }
+
+    if let Some(point_size) = matches.get_one::<f32>("point_size") {
+        c.point_size = *point_size;
+    }
+
+    if let Some(debug_overlay) = matches.get_one::<String>("debug_overlay") {
+        c.debug_overlay = match debug_overlay.as_str() {
+            "bbox" => DebugOverlay::BoundingBox,
+            "axes" => DebugOverlay::Axes,
+            "both" => DebugOverlay::Both,
+            _ => DebugOverlay::None,
+        };
+    }
+
+    if let Some(depth_bias) = matches.get_one::<i32>("depth_bias") {
+        c.depth_bias.constant = *depth_bias;
+    }
+
+    if let Some(slope_scale) = matches.get_one::<f32>("depth_bias_slope") {
+        c.depth_bias.slope_scale = *slope_scale;
+    }
+
     if let Some(sample_count) = matches.get_one::<u32>("sample_count") {
         c.sample_count = *sample_count;
     }
 
-    if let Some(cam_position) = matches.get_one::<String>("cam_position") {
-        let cam_position_vec = cam_position
-            .split(',')
-            .map(|s| {
-                s.trim()
-                    .parse::<f32>()
-                    .map_err(|_| "Invalid float in cam_position")
-            })
-            .collect::<Result<Vec<f32>, _>>()?;
-
-        if cam_position_vec.len() != 3 {
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                format!(
-                    "cam_position must have exactly three elements, but got {}",
-                    cam_position_vec.len()
-                ),
-            )));
+    if let Some(antialiasing) = matches.get_one::<String>("antialiasing") {
+        c.sample_count = match antialiasing.as_str() {
+            "off" => AntiAlias::Off,
+            "msaa2" => AntiAlias::Msaa2,
+            "msaa8" => AntiAlias::Msaa8,
+            _ => AntiAlias::Msaa4,
         }
+        .sample_count();
+    }
+
+    if let Some(taa_samples) = matches.get_one::<u8>("taa_samples") {
+        c.taa_samples = *taa_samples;
+    }
 
-        c.cam_position = (
-            cam_position_vec[0],
-            cam_position_vec[1],
-            cam_position_vec[2],
+    if let Some(shader) = matches.get_one::<String>("shader") {
+        c.custom_shader = Some(
+            std::fs::read_to_string(shader)
+                .map_err(|e| format!("Failed to read shader {shader}: {e}"))?,
         );
     }
 
+    if let Some(cam_position) = matches.get_one::<String>("cam_position") {
+        c.cam_position = parse_vec3(cam_position, "cam_position")?;
+    }
+
+    if let Some(view) = matches.get_one::<String>("view") {
+        let view = camera_preset(view);
+        c.cam_position = view.position(c.cam_position.into()).into();
+        c.orthographic |= view.orthographic();
+    }
+
+    if let Some(views) = matches.get_many::<String>("views") {
+        c.views = views.map(|view| camera_preset(view)).collect();
+    }
+
+    if let Some(sheet_columns) = matches.get_one::<usize>("sheet_columns") {
+        c.sheet_columns = *sheet_columns;
+    }
+
+    if let Some(rotation) = matches.get_one::<String>("rotate") {
+        c.model_rotation = parse_vec3(rotation, "rotate")?;
+    }
+
+    c.mirror = [
+        ("mirror_x", Axis::X),
+        ("mirror_y", Axis::Y),
+        ("mirror_z", Axis::Z),
+    ]
+    .into_iter()
+    .find_map(|(flag, axis)| matches.get_flag(flag).then_some(axis));
+
     if matches.get_flag("md5") && c.img_filename != "-" {
         return Err(Box::new(std::io::Error::new(
             std::io::ErrorKind::InvalidInput,
@@ -181,12 +898,41 @@ fn args() -> Result<(Config, bool), Box<dyn std::error::Error>> {
         )));
     };
 
-    Ok((c, matches.get_flag("md5")))
+    if matches.get_flag("json") && !matches.get_flag("info") && c.img_filename == "-" {
+        return Err(Box::new(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "IMG_FILE can't be '-' when using --json without --info",
+        )));
+    }
+
+    Ok(Args {
+        config: c,
+        md5: matches.get_flag("md5"),
+        mask: matches.get_flag("mask"),
+        contact_sheet: matches.get_flag("contact_sheet"),
+        info: matches.get_flag("info"),
+        list_gpus: matches.get_flag("list_gpus"),
+        dry_run: matches.get_flag("dry_run"),
+        preview_term: matches.get_flag("preview_term"),
+        json: matches.get_flag("json"),
+        no_clobber: matches.get_flag("no_clobber"),
+    })
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let (config, md5) = args()?;
+    let Args {
+        config,
+        md5,
+        mask,
+        contact_sheet,
+        info,
+        list_gpus,
+        dry_run,
+        preview_term,
+        json,
+        no_clobber,
+    } = args()?;
 
     tracing_subscriber::fmt()
         .with_max_level(match config.verbosity {
@@ -199,28 +945,273 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with_writer(std::io::stderr)
         .init();
 
-    if md5 {
+    let mut claimed = ClaimedOutputs(Vec::new());
+    if list_gpus {
+        for adapter in list_adapters() {
+            println!(
+                "{} ({:?}, {:?})",
+                adapter.name, adapter.backend, adapter.device_type
+            );
+        }
+    } else if dry_run {
+        check_models(&config).await?;
+    } else if info {
+        let mesh = load_checked(&config.model_filename, &config).await?;
+        if json {
+            let mut output = mesh_info_json(&mesh.info());
+            output["width"] = config.width.into();
+            output["height"] = config.height.into();
+            println!("{output}");
+        } else {
+            print_mesh_info(&mesh.info());
+        }
+    } else if preview_term {
+        let cols = std::env::var("COLUMNS")
+            .ok()
+            .and_then(|cols| cols.parse::<u16>().ok())
+            .filter(|&cols| cols > 0)
+            .unwrap_or(80);
+        // Terminal cells are about twice as high as they are wide
+        let rows = u16::try_from(
+            u32::from(cols) * u32::from(config.height) / u32::from(config.width) / 2,
+        )?
+        .max(1);
+        let color = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+        print!(
+            "{}",
+            render_to_ansi(
+                Path::new(&config.model_filename),
+                cols,
+                rows,
+                color,
+                &(&config).into(),
+            )
+            .await?
+        );
+    } else if md5 {
         let digest =
             md5::compute(&render(Path::new(&config.model_filename), &(&config).into()).await?);
         println!("MD5: {:x}", digest);
+    } else if mask {
+        if no_clobber && config.img_filename != "-" {
+            claimed.claim(Path::new(&config.img_filename))?;
+        }
+        render_mask_to_file(
+            Path::new(&config.model_filename),
+            Path::new(&config.img_filename),
+            config.format,
+            &(&config).into(),
+        )
+        .await?;
+    } else if contact_sheet {
+        if no_clobber && config.img_filename != "-" {
+            claimed.claim(Path::new(&config.img_filename))?;
+        }
+        let mut models = Vec::new();
+        find_models(Path::new(&config.model_filename), &mut models)?;
+        models.sort();
+        let failures = render_contact_sheet_to_file(
+            &models,
+            Path::new(&config.img_filename),
+            config.sheet_columns,
+            (config.width, config.height),
+            true,
+            config.format,
+            &(&config).into(),
+        )
+        .await?;
+        // The sheet is written anyway, with the cells of these models left empty
+        for (model, error) in &failures {
+            eprintln!("{}: {error}", model.display());
+        }
+        if !failures.is_empty() {
+            return Err(format!(
+                "{} of {} models failed to load",
+                failures.len(),
+                models.len()
+            )
+            .into());
+        }
+    } else if !config.views.is_empty() {
+        if no_clobber && config.img_filename != "-" {
+            claimed.claim(Path::new(&config.img_filename))?;
+        }
+        render_views_sheet_to_file(
+            Path::new(&config.model_filename),
+            Path::new(&config.img_filename),
+            &config.views,
+            config.sheet_columns,
+            config.format,
+            &(&config).into(),
+        )
+        .await?;
+    } else if !config.sizes.is_empty() && config.format == image::ImageFormat::Ico {
+        // Icons hold every size in a single file
+        if no_clobber {
+            claimed.claim(Path::new(&config.img_filename))?;
+        }
+        render_to_ico(
+            Path::new(&config.model_filename),
+            Path::new(&config.img_filename),
+            &config.sizes,
+            &(&config).into(),
+        )
+        .await?;
+    } else if !config.sizes.is_empty() {
+        if no_clobber {
+            for &(width, height) in &config.sizes {
+                claimed.claim(&sized_filename(
+                    Path::new(&config.img_filename),
+                    width,
+                    height,
+                ))?;
+            }
+        }
+        render_multi_size_to_files(
+            Path::new(&config.model_filename),
+            Path::new(&config.img_filename),
+            &config.sizes,
+            config.format,
+            &(&config).into(),
+        )
+        .await?;
     } else {
-        render_to_file(
+        if no_clobber && config.img_filename != "-" {
+            claimed.claim(Path::new(&config.img_filename))?;
+        }
+        let result = render_to_file(
             Path::new(&config.model_filename),
             Path::new(&config.img_filename),
             config.format,
             &(&config).into(),
         )
         .await?;
+
+        if json {
+            let mut output = mesh_info_json(&result.mesh);
+            output["width"] = result.width.into();
+            output["height"] = result.height.into();
+            output["render_time_ms"] = (result.render_time.as_secs_f64() * 1000.0).into();
+            output["coverage"] = result.coverage.into();
+            println!("{output}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads a model and checks it like before rendering, without initializing the GPU.
+async fn load_checked(
+    model_filename: &str,
+    config: &Config,
+) -> Result<Mesh, Box<dyn std::error::Error>> {
+    stl_thumb::load_mesh(Path::new(model_filename), &config.into()).await
+}
+
+/// Adds the files with the extension of a supported format in `dir` and its subdirectories to
+/// `models`.
+fn find_models(dir: &Path, models: &mut Vec<PathBuf>) -> Result<(), std::io::Error> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_models(&path, models)?;
+        } else if path
+            .extension()
+            .and_then(|ext| InputFormat::from_extension(&ext.to_string_lossy()))
+            .is_some()
+        {
+            models.push(path);
+        }
     }
+    Ok(())
+}
+
+/// Loads and checks MODEL_FILE, or every model in it if it is a directory, printing a line for
+/// each, for `--dry-run`. Fails if any of them can't be loaded.
+async fn check_models(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let path = Path::new(&config.model_filename);
+    let models = if path.is_dir() {
+        let mut models = Vec::new();
+        find_models(path, &mut models)?;
+        models.sort();
+        models
+    } else {
+        vec![path.to_path_buf()]
+    };
 
+    let mut failed = 0;
+    for model in &models {
+        match load_checked(&model.to_string_lossy(), config).await {
+            Ok(mesh) => {
+                let info = mesh.info();
+                let format = info.format.map_or_else(String::new, |f| format!("{f}, "));
+                println!(
+                    "{}: OK ({format}{} triangles, {} vertices)",
+                    model.display(),
+                    info.triangle_count,
+                    info.vertex_count
+                );
+            }
+            Err(error) => {
+                println!("{}: {error}", model.display());
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{failed} of {} models failed to load", models.len()).into());
+    }
     Ok(())
 }
 
+/// Output files created empty before rendering for `--no-clobber`, so another process can't
+/// write them in the meantime. Those still empty when this is dropped weren't rendered, and are
+/// removed again.
+struct ClaimedOutputs(Vec<PathBuf>);
+
+impl ClaimedOutputs {
+    /// Creates an image file, failing if it already exists.
+    fn claim(&mut self, img_filename: &Path) -> Result<(), std::io::Error> {
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(img_filename)
+        {
+            Ok(_) => {
+                self.0.push(img_filename.to_path_buf());
+                Ok(())
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!(
+                        "{} already exists, remove it or drop --no-clobber",
+                        img_filename.display()
+                    ),
+                ))
+            }
+            Err(error) => Err(error),
+        }
+    }
+}
+
+impl Drop for ClaimedOutputs {
+    fn drop(&mut self) {
+        for img_filename in &self.0 {
+            if std::fs::metadata(img_filename).is_ok_and(|metadata| metadata.len() == 0) {
+                let _ = std::fs::remove_file(img_filename);
+            }
+        }
+    }
+}
+
 // Notes
 // =====
 //
 // Linux Thumbnails
 // ----------------
+// https://specifications.freedesktop.org/thumbnail-spec/latest/ (see `thumbnail_for_uri`)
 // https://tecnocode.co.uk/2013/10/21/writing-a-gnome-thumbnailer/
 // https://wiki.archlinux.org/index.php/XDG_MIME_Applications#Shared_MIME_database
 // https://developer.gnome.org/integration-guide/stable/thumbnailer.html.en (outdated)
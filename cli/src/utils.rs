@@ -1,4 +1,10 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use clap::{parser::ValueSource, Arg, ArgAction, ArgMatches, Command};
 use image::ImageFormat;
+use serde::Deserialize;
+use serde_json::json;
+use stl_thumb::{CameraPreset, MeshInfo};
 
 /// Matches a file extension to an `ImageFormat`.
 ///
@@ -15,10 +21,45 @@ pub fn match_format(ext: &str) -> ImageFormat {
         "gif" => ImageFormat::Gif,
         "ico" => ImageFormat::Ico,
         "bmp" => ImageFormat::Bmp,
+        "exr" => ImageFormat::OpenExr,
         _ => ImageFormat::Png,
     }
 }
 
+/// Names of the views accepted by `--view` and `--views`.
+pub const CAMERA_PRESETS: [&str; 8] = [
+    "front",
+    "back",
+    "left",
+    "right",
+    "top",
+    "bottom",
+    "isometric",
+    "game-iso",
+];
+
+/// Matches a view name from [`CAMERA_PRESETS`] to a `CameraPreset`.
+///
+/// # Arguments
+///
+/// * `name` - A string slice that holds the view name.
+///
+/// # Returns
+///
+/// * `CameraPreset` - The corresponding view, isometric for unknown names.
+pub fn camera_preset(name: &str) -> CameraPreset {
+    match name {
+        "front" => CameraPreset::Front,
+        "back" => CameraPreset::Back,
+        "left" => CameraPreset::Left,
+        "right" => CameraPreset::Right,
+        "top" => CameraPreset::Top,
+        "bottom" => CameraPreset::Bottom,
+        "game-iso" => CameraPreset::GameIsometric,
+        _ => CameraPreset::Isometric,
+    }
+}
+
 /// Converts an HTML color code to an RGBA tuple.
 ///
 /// # Arguments
@@ -49,3 +90,252 @@ pub fn html_to_rgba(color: &str) -> (f32, f32, f32, f32) {
 
     (red, green, blue, alpha)
 }
+
+/// Parses an HTML color code without transparency, e.g. `ff8000`.
+///
+/// # Arguments
+///
+/// * `color` - A string slice that holds the HTML color code.
+///
+/// # Returns
+///
+/// * `Result<(f32, f32, f32), String>` - The RGB values, or an error message if it isn't six
+///   hexadecimal digits.
+pub fn parse_rgb(color: &str) -> Result<(f32, f32, f32), String> {
+    if color.len() != 6 || !color.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err("Invalid color, expected six hexadecimal digits like ff8000".to_owned());
+    }
+
+    let (red, green, blue, _) = html_to_rgba(&format!("{color}ff"));
+    Ok((red, green, blue))
+}
+
+/// Parses an image size, either a single number for a square or `<width>x<height>`.
+///
+/// # Arguments
+///
+/// * `size` - A string slice that holds the size.
+///
+/// # Returns
+///
+/// * `Result<(u16, u16), &str>` - The width and height, or an error message if they aren't
+///   numbers or are 0.
+pub fn parse_size(size: &str) -> Result<(u16, u16), &'static str> {
+    let (width, height) = if let Ok(size_num) = size.trim().parse::<u16>() {
+        (size_num, size_num)
+    } else if let Some((width, height)) = size.split_once('x') {
+        (
+            width
+                .trim()
+                .parse::<u16>()
+                .map_err(|_| "Invalid width in size")?,
+            height
+                .trim()
+                .parse::<u16>()
+                .map_err(|_| "Invalid height in size")?,
+        )
+    } else {
+        return Err("Invalid size format. Use a number or <width>x<height>");
+    };
+
+    if width == 0 || height == 0 {
+        return Err("The width and height of the size must be at least 1");
+    }
+    Ok((width, height))
+}
+
+/// Parses a comma-separated list of three floats.
+///
+/// # Arguments
+///
+/// * `value` - A string slice that holds the list, e.g. `2,-4,2`.
+/// * `name` - The name of the option, used in error messages.
+///
+/// # Returns
+///
+/// * `Result<(f32, f32, f32), String>` - The three values or an error message.
+pub fn parse_vec3(value: &str, name: &str) -> Result<(f32, f32, f32), String> {
+    let values = value
+        .split(',')
+        .map(|s| {
+            s.trim()
+                .parse::<f32>()
+                .map_err(|_| format!("Invalid float in {name}"))
+        })
+        .collect::<Result<Vec<f32>, _>>()?;
+
+    match values[..] {
+        [x, y, z] => Ok((x, y, z)),
+        _ => Err(format!(
+            "{name} must have exactly three elements, but got {}",
+            values.len()
+        )),
+    }
+}
+
+/// Parses a camera field of view in degrees.
+///
+/// # Arguments
+///
+/// * `value` - A string slice that holds the angle.
+///
+/// # Returns
+///
+/// * `Result<f32, String>` - The angle, or an error message if it isn't between 0 and 180.
+pub fn parse_fov(value: &str) -> Result<f32, String> {
+    let fov = value
+        .trim()
+        .parse::<f32>()
+        .map_err(|_| "Invalid float".to_owned())?;
+    if fov > 0.0 && fov < 180.0 {
+        Ok(fov)
+    } else {
+        Err(format!("{fov} is not between 0 and 180 degrees"))
+    }
+}
+
+/// Converts mesh statistics to a JSON object.
+///
+/// # Arguments
+///
+/// * `info` - The statistics of the loaded mesh.
+///
+/// # Returns
+///
+/// * `serde_json::Value` - A JSON object with the statistics.
+pub fn mesh_info_json(info: &MeshInfo) -> serde_json::Value {
+    json!({
+        "format": info.format.map(|format| format.to_string()),
+        "triangle_count": info.triangle_count,
+        "vertex_count": info.vertex_count,
+        "unique_vertex_count": info.unique_vertex_count,
+        "bounding_box": {
+            "min": info.bounds.min.to_array(),
+            "max": info.bounds.max.to_array(),
+            "size": info.bounds.size().to_array(),
+        },
+        "normals_recomputed": info.normals_recomputed,
+        "stl_mode": info.stl_mode.map(|mode| mode.to_string()),
+        "stl_colors": info.stl_mode.map(|_| info.stl_colors),
+    })
+}
+
+/// Prints mesh statistics in a human readable form.
+///
+/// # Arguments
+///
+/// * `info` - The statistics of the loaded mesh.
+pub fn print_mesh_info(info: &MeshInfo) {
+    let size = info.bounds.size();
+    if let Some(format) = info.format {
+        println!("Format: {format}");
+    }
+    if let Some(stl_mode) = info.stl_mode {
+        println!("STL mode: {stl_mode}");
+        println!("STL colors: {}", if info.stl_colors { "yes" } else { "no" });
+    }
+    println!("Triangles: {}", info.triangle_count);
+    println!(
+        "Vertices: {} ({} unique)",
+        info.vertex_count, info.unique_vertex_count
+    );
+    println!("Bounding box min: {}", info.bounds.min);
+    println!("Bounding box max: {}", info.bounds.max);
+    println!("Size: {} x {} x {}", size.x, size.y, size.z);
+    println!("Normals recomputed: {}", info.normals_recomputed);
+}
+
+/// A value of a preset file.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PresetValue {
+    Flag(bool),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    List(Vec<PresetValue>),
+}
+
+impl PresetValue {
+    /// The value like it is written on the command line, arrays are joined with commas.
+    fn text(&self) -> String {
+        match self {
+            Self::Flag(flag) => flag.to_string(),
+            Self::Integer(integer) => integer.to_string(),
+            Self::Float(float) => float.to_string(),
+            Self::Text(text) => text.clone(),
+            Self::List(items) => items.iter().map(Self::text).collect::<Vec<_>>().join(","),
+        }
+    }
+}
+
+/// Reads a TOML preset file into the command line options it sets.
+///
+/// Each key is the long name of an option, e.g. `size = 256` or `auto-frame = true`. Underscores
+/// in keys are treated as dashes, arrays are joined with commas, flags are set by `true` and
+/// `verbosity` takes a count. Options given on the command line, or conflicting with one given on
+/// it, are left out so the command line takes precedence.
+///
+/// # Arguments
+///
+/// * `command` - The command line interface the options belong to.
+/// * `matches` - The options given on the command line.
+/// * `path` - The path of the preset file.
+///
+/// # Returns
+///
+/// * `Result<Vec<String>, String>` - The options or an error message.
+pub fn preset_args(
+    command: &Command,
+    matches: &ArgMatches,
+    path: &Path,
+) -> Result<Vec<String>, String> {
+    let invalid = |reason: &dyn std::fmt::Display| {
+        format!("Invalid config file {}: {reason}", path.display())
+    };
+    let preset = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read config file {}: {e}", path.display()))?;
+    let preset =
+        toml::from_str::<BTreeMap<String, PresetValue>>(&preset).map_err(|e| invalid(&e))?;
+
+    let mut command = command.clone();
+    command.build();
+    let given =
+        |arg: &Arg| matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine);
+
+    let mut args = Vec::new();
+    for (key, value) in preset {
+        let name = key.replace('_', "-");
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(name.as_str()) && name != "config")
+            .ok_or_else(|| invalid(&format!("unknown option `{key}`")))?;
+        if given(arg) || command.get_arg_conflicts_with(arg).into_iter().any(given) {
+            continue;
+        }
+
+        match (arg.get_action(), value) {
+            (ArgAction::SetTrue, PresetValue::Flag(flag)) => {
+                if flag {
+                    args.push(format!("--{name}"));
+                }
+            }
+            (ArgAction::Count, PresetValue::Integer(count)) => {
+                let count = usize::try_from(count)
+                    .map_err(|_| invalid(&format!("`{key}` must be a non-negative integer")))?;
+                args.extend(std::iter::repeat_n(format!("--{name}"), count));
+            }
+            (ArgAction::SetTrue, _) => {
+                return Err(invalid(&format!("`{key}` must be true or false")));
+            }
+            (ArgAction::Count, _) => {
+                return Err(invalid(&format!("`{key}` must be a non-negative integer")));
+            }
+            (_, PresetValue::Flag(_)) => {
+                return Err(invalid(&format!("`{key}` takes a value")));
+            }
+            (_, value) => args.push(format!("--{name}={}", value.text())),
+        }
+    }
+    Ok(args)
+}
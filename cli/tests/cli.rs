@@ -0,0 +1,300 @@
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+const MODELS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/data");
+
+#[test]
+fn obj_from_stdin() {
+    let img_filename = Path::new("cube-obj-stdin.png");
+    match fs::remove_file(img_filename) {
+        Err(error) if error.kind() == ErrorKind::NotFound => (),
+        r => r.expect("Couldn't clean files before testing"),
+    }
+
+    let model = fs::read(format!("{MODELS_DIR}/cube.obj")).expect("Couldn't read test model");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_stl-thumb"))
+        .args(["-", "cube-obj-stdin.png", "--input-format", "obj"])
+        .stdin(Stdio::piped())
+        .spawn()
+        .expect("Couldn't run stl-thumb");
+    child
+        .stdin
+        .take()
+        .expect("No stdin")
+        .write_all(&model)
+        .expect("Couldn't write model to stdin");
+    let status = child.wait().expect("stl-thumb didn't run");
+
+    assert!(status.success());
+
+    let size = fs::metadata(img_filename).expect("No file created").len();
+
+    assert_ne!(0, size);
+}
+
+#[test]
+fn json_info() {
+    let output = Command::new(env!("CARGO_BIN_EXE_stl-thumb"))
+        .args([&format!("{MODELS_DIR}/cube.stl"), "--json", "--info"])
+        .output()
+        .expect("Couldn't run stl-thumb");
+
+    assert!(output.status.success());
+
+    let info: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Output is not valid JSON");
+
+    assert_eq!(info["triangle_count"], 12);
+    assert_eq!(info["unique_vertex_count"], 8);
+    assert_eq!(info["format"], "stl");
+
+    // Modes without JSON output refuse it instead of ignoring it
+    let output = Command::new(env!("CARGO_BIN_EXE_stl-thumb"))
+        .args([&format!("{MODELS_DIR}/cube.stl"), "-", "--json", "--md5"])
+        .output()
+        .expect("Couldn't run stl-thumb");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be used with"));
+}
+
+#[test]
+fn config_file() {
+    let img_filename = Path::new("cube-config.png");
+    let config_filename = Path::new("cube-config.toml");
+    fs::write(
+        config_filename,
+        "# Preset\nsize = 128\nbackground = \"ff0000ff\"\n",
+    )
+    .expect("Couldn't write config file");
+
+    let status = Command::new(env!("CARGO_BIN_EXE_stl-thumb"))
+        .args([&format!("{MODELS_DIR}/cube.stl"), "cube-config.png"])
+        .args(["--config", "cube-config.toml", "--size", "64"])
+        .status()
+        .expect("Couldn't run stl-thumb");
+
+    assert!(status.success());
+
+    let img = image::open(img_filename)
+        .expect("No file created")
+        .into_rgba8();
+    fs::remove_file(config_filename).expect("Couldn't clean up config file");
+
+    assert_eq!((64, 64), img.dimensions());
+    assert_eq!([255, 0, 0, 255], img.get_pixel(0, 0).0);
+}
+
+#[test]
+fn config_file_conflicts() {
+    let config_filename = Path::new("cube-config-conflicts.toml");
+    fs::write(config_filename, "mirror_x = true\nsize = 64\n").expect("Couldn't write config file");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_stl-thumb"))
+        .args([&format!("{MODELS_DIR}/cube.stl"), "--info", "--json"])
+        .args(["--config", "cube-config-conflicts.toml", "--mirror-y"])
+        .output()
+        .expect("Couldn't run stl-thumb");
+
+    fs::write(config_filename, "not_an_option = 64\n").expect("Couldn't write config file");
+    let unknown = Command::new(env!("CARGO_BIN_EXE_stl-thumb"))
+        .args([&format!("{MODELS_DIR}/cube.stl"), "--info"])
+        .args(["--config", "cube-config-conflicts.toml"])
+        .output()
+        .expect("Couldn't run stl-thumb");
+
+    fs::write(config_filename, "verbosity = \"high\"\n").expect("Couldn't write config file");
+    let count = Command::new(env!("CARGO_BIN_EXE_stl-thumb"))
+        .args([&format!("{MODELS_DIR}/cube.stl"), "--info"])
+        .args(["--config", "cube-config-conflicts.toml"])
+        .output()
+        .expect("Couldn't run stl-thumb");
+    fs::remove_file(config_filename).expect("Couldn't clean up config file");
+
+    assert!(output.status.success());
+    assert!(!unknown.status.success());
+    assert!(String::from_utf8_lossy(&unknown.stderr).contains("unknown option `not_an_option`"));
+    assert!(!count.status.success());
+    assert!(String::from_utf8_lossy(&count.stderr)
+        .contains("`verbosity` must be a non-negative integer"));
+}
+
+#[test]
+fn no_clobber() {
+    let img_filename = Path::new("cube-no-clobber.png");
+    fs::write(img_filename, "existing image").expect("Couldn't write image file");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_stl-thumb"))
+        .args([&format!("{MODELS_DIR}/cube.stl"), "cube-no-clobber.png"])
+        .args(["--no-clobber", "--size", "64"])
+        .output()
+        .expect("Couldn't run stl-thumb");
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("already exists"));
+    assert_eq!(
+        "existing image",
+        fs::read_to_string(img_filename).expect("Image file was removed")
+    );
+
+    // Sizes claimed before the existing one aren't left behind empty
+    let sized_filename = Path::new("cube-no-clobber-32.png");
+    fs::write(sized_filename, "existing image").expect("Couldn't write image file");
+    let output = Command::new(env!("CARGO_BIN_EXE_stl-thumb"))
+        .args([&format!("{MODELS_DIR}/cube.stl"), "cube-no-clobber.png"])
+        .args(["--no-clobber", "--sizes", "16,32"])
+        .output()
+        .expect("Couldn't run stl-thumb");
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("already exists"));
+    assert!(!Path::new("cube-no-clobber-16.png").exists());
+    assert_eq!(
+        "existing image",
+        fs::read_to_string(sized_filename).expect("Image file was removed")
+    );
+
+    // Neither are outputs of failed renders
+    let output = Command::new(env!("CARGO_BIN_EXE_stl-thumb"))
+        .args(["missing.stl", "missing-no-clobber.png", "--no-clobber"])
+        .output()
+        .expect("Couldn't run stl-thumb");
+
+    assert!(!output.status.success());
+    assert!(!Path::new("missing-no-clobber.png").exists());
+}
+
+#[test]
+fn dry_run() {
+    let dir = Path::new("dry-run-models");
+    match fs::remove_dir_all(dir) {
+        Err(error) if error.kind() == ErrorKind::NotFound => (),
+        r => r.expect("Couldn't clean files before testing"),
+    }
+    fs::create_dir_all(dir.join("nested")).expect("Couldn't create model directory");
+    for model in ["cube.stl", "cube.3mf", "README.md"] {
+        fs::copy(format!("{MODELS_DIR}/{model}"), dir.join(model)).expect("Couldn't copy model");
+    }
+    fs::copy(
+        format!("{MODELS_DIR}/cube.obj"),
+        dir.join("nested/cube.obj"),
+    )
+    .expect("Couldn't copy model");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_stl-thumb"))
+        .args(["dry-run-models", "--dry-run"])
+        .output()
+        .expect("Couldn't run stl-thumb");
+    fs::remove_dir_all(dir).expect("Couldn't clean up model directory");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3, "{stdout}");
+    assert!(lines.contains(&"dry-run-models/cube.stl: OK (stl, 12 triangles, 36 vertices)"));
+    assert!(lines.contains(&"dry-run-models/cube.3mf: OK (3mf, 12 triangles, 36 vertices)"));
+    assert!(lines.contains(&"dry-run-models/nested/cube.obj: OK (obj, 12 triangles, 36 vertices)"));
+}
+
+#[test]
+fn dry_run_failure() {
+    let dir = std::env::temp_dir().join("stl-thumb-dry-run-failure");
+    match fs::remove_dir_all(&dir) {
+        Err(error) if error.kind() == ErrorKind::NotFound => (),
+        r => r.expect("Couldn't clean files before testing"),
+    }
+    fs::create_dir_all(&dir).expect("Couldn't create model directory");
+    // The header of this binary file starts with `solid `, which fools the STL detection
+    for model in ["cube.stl", "cube_solid_header.stl"] {
+        fs::copy(format!("{MODELS_DIR}/{model}"), dir.join(model)).expect("Couldn't copy model");
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_stl-thumb"))
+        .arg(&dir)
+        .arg("--dry-run")
+        .output()
+        .expect("Couldn't run stl-thumb");
+    fs::remove_dir_all(&dir).expect("Couldn't clean up model directory");
+
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2, "{stdout}");
+    assert!(lines[0].ends_with("cube.stl: OK (stl, 12 triangles, 36 vertices)"));
+    assert!(lines[1].contains("cube_solid_header.stl"));
+    assert!(!lines[1].contains(": OK ("));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 of 2 models failed to load"));
+}
+
+#[test]
+fn dry_run_convex_hull() {
+    let dir = std::env::temp_dir().join("stl-thumb-dry-run-convex-hull");
+    match fs::remove_dir_all(&dir) {
+        Err(error) if error.kind() == ErrorKind::NotFound => (),
+        r => r.expect("Couldn't clean files before testing"),
+    }
+    fs::create_dir_all(&dir).expect("Couldn't create model directory");
+    // A single triangle loads fine, but has no convex hull to render
+    fs::write(dir.join("flat.obj"), "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n")
+        .expect("Couldn't write model");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_stl-thumb"))
+        .arg(dir.join("flat.obj"))
+        .args(["--dry-run", "--convex-hull"])
+        .output()
+        .expect("Couldn't run stl-thumb");
+    fs::remove_dir_all(&dir).expect("Couldn't clean up model directory");
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("no convex hull"));
+}
+
+#[test]
+fn invalid_color() {
+    for args in [
+        ["--matte=zz0000"].as_slice(),
+        &["--rim-light=12345"],
+        &["--highlight-backfaces=red"],
+        &["--edges", "--edge-color=ff00ff00"],
+        &["--grid=1", "--grid-color=80808g"],
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_stl-thumb"))
+            .arg(format!("{MODELS_DIR}/cube.stl"))
+            .arg("invalid-color.png")
+            .args(args)
+            .output()
+            .expect("Couldn't run stl-thumb");
+
+        // A usage error, not a panic
+        assert_eq!(output.status.code(), Some(2), "{args:?}");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Invalid color"), "{stderr}");
+        assert!(!Path::new("invalid-color.png").exists());
+    }
+}
+
+#[test]
+fn zero_size() {
+    for args in [
+        ["-s", "0"].as_slice(),
+        &["--preview-term", "-s", "0x10"],
+        &["--sizes", "64,10x0"],
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_stl-thumb"))
+            .arg(format!("{MODELS_DIR}/cube.stl"))
+            .arg("zero-size.png")
+            .args(args)
+            .output()
+            .expect("Couldn't run stl-thumb");
+
+        // An error, not a panic
+        assert_eq!(output.status.code(), Some(1), "{args:?}");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("must be at least 1"), "{stderr}");
+        assert!(!Path::new("zero-size.png").exists());
+    }
+}
@@ -0,0 +1,485 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use glam::{Quat, Vec2, Vec3, Vec4};
+use image::ImageFormat;
+
+use crate::{
+    mesh::{InputFormat, ObjectSelector, StlMode},
+    render::{
+        Axis, Background, BitDepth, CenterMode, ColorSpace, CullMode, DebugOverlay, DepthBias,
+        DirectionalLight, DofOptions, GridOptions, PixelFormat, RimLight, Shading, Topology,
+    },
+    RenderOptions,
+};
+
+/// A directory of encoded thumbnails, keyed by the model contents and the render options.
+#[derive(Debug)]
+pub struct ThumbnailCache {
+    dir: PathBuf,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl ThumbnailCache {
+    /// Opens a cache in `dir`, creating the directory if needed.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the directory cannot be created.
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        })
+    }
+
+    /// Path of the cached thumbnail for a model rendered with the given format and options.
+    #[must_use]
+    pub fn path(&self, model: &[u8], format: ImageFormat, opts: &RenderOptions) -> PathBuf {
+        self.path_with_companions(model, &[], format, opts)
+    }
+
+    /// Path of the cached thumbnail like [`ThumbnailCache::path`], also keyed by the files the
+    /// model references, like OBJ material libraries, `None` for the ones that can't be read.
+    pub(crate) fn path_with_companions(
+        &self,
+        model: &[u8],
+        companions: &[Option<Vec<u8>>],
+        format: ImageFormat,
+        opts: &RenderOptions,
+    ) -> PathBuf {
+        let mut key = Vec::new();
+        // Renders of other versions may differ for the same options
+        env!("CARGO_PKG_VERSION").write_key(&mut key);
+        format.to_mime_type().write_key(&mut key);
+        opts.write_key(&mut key);
+        companions.len().write_key(&mut key);
+        for companion in companions {
+            companion.as_deref().write_key(&mut key);
+        }
+
+        let mut context = md5::Context::new();
+        context.consume((model.len() as u64).to_le_bytes());
+        context.consume(model);
+        context.consume(key);
+
+        self.dir.join(format!(
+            "{:x}.{}",
+            context.compute(),
+            format.extensions_str().first().unwrap_or(&"img")
+        ))
+    }
+
+    /// Reads a cached thumbnail, returning `None` on a cache miss.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the cached file exists but cannot be read.
+    pub fn get(&self, path: &Path) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(path) {
+            Ok(buff) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Ok(Some(buff))
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                Ok(None)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Stores an encoded thumbnail in the cache.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file cannot be written.
+    pub fn insert(&self, path: &Path, buff: &[u8]) -> io::Result<()> {
+        // Write to a temporary file of its own first, so a concurrent reader never sees a partial
+        // thumbnail and concurrent writers don't write to the same file
+        let mut tmp = tempfile::NamedTempFile::new_in(path.parent().unwrap_or(&self.dir))?;
+        tmp.write_all(buff)?;
+        tmp.persist(path)?;
+        Ok(())
+    }
+
+    /// Number of thumbnails served from the cache.
+    #[must_use]
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of thumbnails that had to be rendered.
+    #[must_use]
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+/// A value written to a cache key, in a fixed encoding that only changes with the value, unlike
+/// the `Debug` representation.
+trait KeyPart {
+    fn write_key(&self, key: &mut Vec<u8>);
+}
+
+macro_rules! key_part_bytes {
+    ($($t:ty),*) => {
+        $(impl KeyPart for $t {
+            fn write_key(&self, key: &mut Vec<u8>) {
+                key.extend(self.to_le_bytes());
+            }
+        })*
+    };
+}
+
+key_part_bytes!(u8, u16, u32, u64, i32, f32);
+
+macro_rules! key_part_fieldless {
+    ($($t:ty),*) => {
+        $(impl KeyPart for $t {
+            fn write_key(&self, key: &mut Vec<u8>) {
+                (*self as u8).write_key(key);
+            }
+        })*
+    };
+}
+
+key_part_fieldless!(
+    Axis,
+    BitDepth,
+    CenterMode,
+    ColorSpace,
+    CullMode,
+    DebugOverlay,
+    InputFormat,
+    PixelFormat,
+    StlMode,
+    Topology
+);
+
+impl KeyPart for bool {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        u8::from(*self).write_key(key);
+    }
+}
+
+impl KeyPart for usize {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        (*self as u64).write_key(key);
+    }
+}
+
+impl KeyPart for [f32] {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        for value in self {
+            value.write_key(key);
+        }
+    }
+}
+
+impl KeyPart for Vec2 {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        self.to_array()[..].write_key(key);
+    }
+}
+
+impl KeyPart for Vec3 {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        self.to_array()[..].write_key(key);
+    }
+}
+
+impl KeyPart for Vec4 {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        self.to_array()[..].write_key(key);
+    }
+}
+
+impl KeyPart for Quat {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        self.to_array()[..].write_key(key);
+    }
+}
+
+impl KeyPart for [u8] {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        // Prefixed with the length, so neighbouring parts can't run into each other
+        self.len().write_key(key);
+        key.extend(self);
+    }
+}
+
+impl KeyPart for str {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        self.as_bytes().write_key(key);
+    }
+}
+
+impl KeyPart for PathBuf {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        self.to_string_lossy().write_key(key);
+    }
+}
+
+impl<T: KeyPart + ?Sized> KeyPart for &T {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        (**self).write_key(key);
+    }
+}
+
+impl<T: KeyPart> KeyPart for Option<T> {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        self.is_some().write_key(key);
+        if let Some(value) = self {
+            value.write_key(key);
+        }
+    }
+}
+
+impl<T: KeyPart> KeyPart for Vec<T> {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        self.len().write_key(key);
+        for value in self {
+            value.write_key(key);
+        }
+    }
+}
+
+impl KeyPart for String {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        self.as_str().write_key(key);
+    }
+}
+
+/// Writes each of the parts to the key.
+fn write_parts(key: &mut Vec<u8>, parts: &[&dyn KeyPart]) {
+    for part in parts {
+        part.write_key(key);
+    }
+}
+
+impl KeyPart for ObjectSelector {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        match self {
+            Self::All => 0u8.write_key(key),
+            Self::ByIndex(index) => write_parts(key, &[&1u8, index]),
+            Self::ByName(name) => write_parts(key, &[&2u8, name]),
+        }
+    }
+}
+
+impl KeyPart for Background {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        match self {
+            Self::Solid(color) => write_parts(key, &[&0u8, color]),
+            Self::VerticalGradient { top, bottom } => write_parts(key, &[&1u8, top, bottom]),
+            Self::Image(image) => write_parts(key, &[&2u8, &image.as_slice()]),
+        }
+    }
+}
+
+impl KeyPart for Shading {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        match self {
+            Self::Solid => 0u8.write_key(key),
+            Self::SolidWithEdges {
+                edge_color,
+                thickness,
+            } => write_parts(key, &[&1u8, edge_color, thickness]),
+            Self::Toon {
+                bands,
+                edge_color,
+                thickness,
+            } => write_parts(key, &[&2u8, bands, edge_color, thickness]),
+            Self::XRay { opacity } => write_parts(key, &[&3u8, opacity]),
+        }
+    }
+}
+
+impl KeyPart for DirectionalLight {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        let Self { direction, color } = self;
+        write_parts(key, &[direction, color]);
+    }
+}
+
+impl KeyPart for RimLight {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        let Self { color, power } = self;
+        write_parts(key, &[color, power]);
+    }
+}
+
+impl KeyPart for GridOptions {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        let Self {
+            spacing,
+            color,
+            extent,
+        } = self;
+        write_parts(key, &[spacing, color, extent]);
+    }
+}
+
+impl KeyPart for DepthBias {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        let Self {
+            constant,
+            slope_scale,
+            clamp,
+        } = self;
+        write_parts(key, &[constant, slope_scale, clamp]);
+    }
+}
+
+impl KeyPart for DofOptions {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        let Self {
+            focal_distance,
+            aperture,
+        } = self;
+        write_parts(key, &[focal_distance, aperture]);
+    }
+}
+
+impl KeyPart for RenderOptions {
+    fn write_key(&self, key: &mut Vec<u8>) {
+        // Destructured without `..`, so options added later can't be left out of the key. Only
+        // the options that can't change the image are skipped
+        let Self {
+            width,
+            height,
+            cam_fov_deg,
+            cam_position,
+            model_rotation,
+            mirror,
+            auto_frame,
+            auto_up,
+            orthographic,
+            near,
+            far,
+            sample_count,
+            recalc_normals,
+            reject_non_finite,
+            allow_remote: _,
+            strict_normals,
+            auto_color_objects,
+            auto_fix_winding,
+            convex_hull,
+            embed_model_size,
+            input_format,
+            stl_mode,
+            object_selector,
+            obj_base_dir,
+            allow_software,
+            lights,
+            rim_light,
+            shininess,
+            specular_strength,
+            color_space,
+            background,
+            premultiply_alpha,
+            matte_background,
+            flatten_background,
+            auto_levels,
+            output_format,
+            bit_depth,
+            topology,
+            shading,
+            cull_mode,
+            center_mode,
+            two_sided,
+            highlight_backfaces,
+            point_size,
+            ambient_occlusion,
+            ao_strength,
+            ao_radius,
+            debug_overlay,
+            wireframe,
+            line_width,
+            grid,
+            depth_bias,
+            depth_test,
+            depth_write,
+            fxaa,
+            taa_samples,
+            jitter,
+            depth_of_field,
+            tiled,
+            custom_shader,
+            reuse_buffers: _,
+        } = self;
+        write_parts(
+            key,
+            &[
+                width,
+                height,
+                cam_fov_deg,
+                cam_position,
+                model_rotation,
+                mirror,
+                auto_frame,
+                auto_up,
+                orthographic,
+                near,
+                far,
+                sample_count,
+                recalc_normals,
+                reject_non_finite,
+                strict_normals,
+                auto_color_objects,
+                auto_fix_winding,
+                convex_hull,
+                embed_model_size,
+                input_format,
+                stl_mode,
+                object_selector,
+                obj_base_dir,
+                allow_software,
+                lights,
+                rim_light,
+                shininess,
+                specular_strength,
+                color_space,
+                background,
+                premultiply_alpha,
+                matte_background,
+                flatten_background,
+                auto_levels,
+                output_format,
+                bit_depth,
+                topology,
+                shading,
+                cull_mode,
+                center_mode,
+                two_sided,
+                highlight_backfaces,
+                point_size,
+                ambient_occlusion,
+                ao_strength,
+                ao_radius,
+                debug_overlay,
+                wireframe,
+                line_width,
+                grid,
+                depth_bias,
+                depth_test,
+                depth_write,
+                fxaa,
+                taa_samples,
+                jitter,
+                depth_of_field,
+                tiled,
+                custom_shader,
+            ],
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll, Waker},
+};
+
+/// Completes with the value passed to its callback, for APIs reporting their result through one.
+pub struct CallbackFuture<T>(Arc<Mutex<CallbackState<T>>>);
+
+struct CallbackState<T> {
+    result: Option<T>,
+    waker: Option<Waker>,
+}
+
+impl<T> Default for CallbackFuture<T> {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(CallbackState {
+            result: None,
+            waker: None,
+        })))
+    }
+}
+
+impl<T: Send + 'static> CallbackFuture<T> {
+    pub fn callback(&self) -> impl FnOnce(T) + Send + 'static {
+        let state = Arc::clone(&self.0);
+        move |result| {
+            let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl<T> Future for CallbackFuture<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        state.result.take().map_or_else(
+            || {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            },
+            Poll::Ready,
+        )
+    }
+}
@@ -47,7 +47,7 @@ pub unsafe extern "C" fn render_to_buffer(
 
     use std::path::Path;
 
-    use crate::{render, RenderOptions};
+    use crate::{render_blocking, RenderOptions};
     if output_buf.is_null() {
         error!("Image buffer pointer is null");
         return false;
@@ -74,15 +74,6 @@ pub unsafe extern "C" fn render_to_buffer(
     }
     let cam_position = unsafe { from_raw_parts(cam_position, 3) };
 
-    // Create a runtime to block on the async function
-    let runtime = match tokio::runtime::Runtime::new() {
-        Ok(rt) => rt,
-        Err(e) => {
-            error!("Failed to create Tokio runtime: {:?}", e);
-            return false;
-        }
-    };
-
     // Render the image
     let render_opts = RenderOptions {
         width,
@@ -91,9 +82,10 @@ pub unsafe extern "C" fn render_to_buffer(
         cam_position: Vec3::new(cam_position[0], cam_position[1], cam_position[2]),
         sample_count,
         recalc_normals,
+        ..Default::default()
     };
 
-    let buffer = match runtime.block_on(render(Path::new(filename), &render_opts)) {
+    let buffer = match render_blocking(Path::new(filename), &render_opts) {
         Ok(buf) => buf,
         Err(e) => {
             error!("Rendering error: {:?}", e);
@@ -106,3 +98,121 @@ pub unsafe extern "C" fn render_to_buffer(
 
     true
 }
+
+/// Suggests a camera that frames the model in the file `filename`, for use with
+/// [`render_to_buffer`]
+///
+/// The model is seen from the default direction, at a distance where the sphere around it fits in
+/// the default field of view. The field of view is stored into `out_fov` and the camera position
+/// into the 3 floats at `out_position`.
+///
+/// Returns `true` if successful and `false` if unsuccessful.
+///
+/// # Example in C
+/// ```c
+/// float cam_fov_deg;
+/// float cam_position[3];
+///
+/// if (stl_thumb_suggest_camera(filename, &cam_fov_deg, cam_position)) {
+///     render_to_buffer(filename, width, height, cam_fov_deg, cam_position, 4, false, output_buf);
+/// }
+/// ```
+///
+/// # Safety
+///
+/// * `filename` must point to a valid null-terminated string.
+/// * `out_fov` must point to a valid float.
+/// * `out_position` must point to an array of 3 floats.
+#[no_mangle]
+pub unsafe extern "C" fn stl_thumb_suggest_camera(
+    filename: *const libc::c_char,
+    out_fov: *mut f32,
+    out_position: *mut f32,
+) -> bool {
+    use std::path::Path;
+
+    use crate::{load_mesh, RenderOptions};
+
+    if out_fov.is_null() || out_position.is_null() {
+        error!("Camera output pointer is null");
+        return false;
+    }
+
+    // Check validity of provided file path string
+    if filename.is_null() {
+        error!("model file path pointer is null");
+        return false;
+    }
+    let filename = unsafe { std::ffi::CStr::from_ptr(filename) };
+    let Ok(filename) = filename.to_str() else {
+        error!("Invalid model file path {:?}", filename);
+        return false;
+    };
+
+    let opts = RenderOptions::default();
+    let mesh = match pollster::block_on(load_mesh(Path::new(filename), &opts)) {
+        Ok(mesh) => mesh,
+        Err(e) => {
+            error!("Loading error: {:?}", e);
+            return false;
+        }
+    };
+
+    // Radius of the sphere around the point the camera looks at holding the placed model
+    let model_matrix = opts.model_matrix(&mesh);
+    let radius = mesh
+        .vertices
+        .iter()
+        .map(|&vertex| model_matrix.transform_point3(vertex).length())
+        .fold(0.0, f32::max);
+    // Fit the sphere in the field of view, with a small margin
+    let distance = radius / (opts.cam_fov_deg.to_radians() / 2.0).sin() * 1.05;
+    let position = opts.cam_position.normalize_or(Vec3::NEG_Y) * distance;
+
+    unsafe {
+        *out_fov = opts.cam_fov_deg;
+        from_raw_parts_mut(out_position, 3).copy_from_slice(&position.to_array());
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use super::*;
+
+    #[test]
+    fn suggested_camera() {
+        let filename = CString::new("test/data/cube.stl").expect("Invalid file name");
+        let mut fov = 0.0;
+        let mut position = [0.0; 3];
+        assert!(unsafe {
+            stl_thumb_suggest_camera(filename.as_ptr(), &raw mut fov, position.as_mut_ptr())
+        });
+        assert!(fov > 0.0 && fov < 180.0);
+        assert!(Vec3::from_array(position).length() > 0.0);
+
+        let mut buffer = vec![0; 64 * 64 * 4];
+        assert!(unsafe {
+            render_to_buffer(
+                filename.as_ptr(),
+                64,
+                64,
+                fov,
+                position.as_ptr(),
+                4,
+                false,
+                buffer.as_mut_ptr(),
+            )
+        });
+
+        // The model is drawn whole, without touching the edges of the image
+        let opaque = |x: usize, y: usize| buffer[(y * 64 + x) * 4 + 3] > 0;
+        assert!(opaque(32, 32));
+        for i in 0..64 {
+            assert!(!opaque(0, i) && !opaque(63, i) && !opaque(i, 0) && !opaque(i, 63));
+        }
+    }
+}
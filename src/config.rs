@@ -1,17 +1,148 @@
+use crate::{
+    mesh::{InputFormat, ObjectSelector, StlMode},
+    render::{
+        AntiAlias, Axis, BitDepth, CameraPreset, CenterMode, ColorSpace, CullMode, DebugOverlay,
+        DepthBias, DirectionalLight, GridOptions, PixelFormat, RimLight, Topology,
+    },
+};
+
+#[allow(clippy::struct_excessive_bools)]
 pub struct Config {
     pub model_filename: String,
     pub img_filename: String,
+    /// Format of the model file, detected from the extension or contents if not set
+    pub input_format: Option<InputFormat>,
+    /// Encoding of STL models, detected from the file if `Auto`
+    pub stl_mode: StlMode,
+    /// Objects to load from model files with several, like OBJ and 3MF
+    pub object_selector: ObjectSelector,
     #[cfg(feature = "image")]
     pub format: image::ImageFormat,
     pub width: u16,
     pub height: u16,
+    /// Additional `(width, height)` sizes to render, each to its own image file
+    pub sizes: Vec<(u16, u16)>,
+    /// Views rendered side by side into a single sheet image instead of the usual thumbnail
+    pub views: Vec<CameraPreset>,
+    /// Number of cells in each row of a views or contact sheet
+    pub sheet_columns: usize,
     pub verbosity: usize,
     pub background: (f32, f32, f32, f32),
     pub recalc_normals: bool,
+    /// Fail to load models with NaN or infinite vertex coordinates instead of skipping them
+    pub reject_non_finite: bool,
+    /// Download models given as `http://` or `https://` URIs
+    pub allow_remote: bool,
+    /// Fail to load models with triangles that have no normal instead of shading them black
+    pub strict_normals: bool,
+    /// Color each object of an assembly differently, replacing the colors from the file
+    pub auto_color_objects: bool,
+    /// Flip triangles wound inconsistently with their neighbours
+    pub auto_fix_winding: bool,
+    /// Render the convex hull of the model instead of its triangles
+    pub convex_hull: bool,
+    /// Store the size of the model in PNG images, see `RenderOptions::embed_model_size`
+    pub embed_model_size: bool,
     pub cam_fov_deg: f32,
     pub cam_position: (f32, f32, f32),
+    /// Rotation of the model around the x, y and z axes in degrees, applied in that order
+    pub model_rotation: (f32, f32, f32),
+    /// Mirror the model along one of its axes
+    pub mirror: Option<Axis>,
+    /// Place the camera at a distance where the model fills the frame
+    pub auto_frame: bool,
+    /// Turn models whose format is Y-up by convention, like OBJ, so Y points up
+    pub auto_up: bool,
+    /// Use an orthographic projection instead of a perspective one
+    pub orthographic: bool,
+    /// Distance from the camera to the near clip plane
+    pub near: f32,
+    /// Distance from the camera to the far clip plane
+    pub far: f32,
+    /// Fall back to software rendering if no GPU adapter is available
+    pub allow_software: bool,
+    /// Lights illuminating the model, up to 4 are used
+    pub lights: Vec<DirectionalLight>,
+    /// Light along the silhouette of the model
+    pub rim_light: Option<RimLight>,
+    /// Specular exponent, higher values give smaller highlights
+    pub shininess: f32,
+    /// Intensity of the specular highlights
+    pub specular_strength: f32,
     /// Number of samples for anti-aliasing
     pub sample_count: u32,
+    /// Color space of the output image
+    pub color_space: ColorSpace,
+    /// Multiply the color channels by alpha in the output image
+    pub premultiply_alpha: bool,
+    /// Color the transparent parts of the image are composited over, keeping the alpha channel
+    pub matte_background: Option<(f32, f32, f32)>,
+    /// Color RGB output is composited over, instead of the background color
+    pub flatten_background: Option<(f32, f32, f32)>,
+    /// Stretch the luminance of the model to a consistent range
+    pub auto_levels: bool,
+    /// Pixel layout of the output image
+    pub output_format: PixelFormat,
+    /// Bits per channel of the output image
+    pub bit_depth: BitDepth,
+    /// Whether the model is drawn as triangles or points
+    pub topology: Topology,
+    /// Draw lines along the creases and silhouette of the model
+    pub edges: bool,
+    /// Shade the model with this many flat light levels for a cartoon look, 0 for smooth
+    /// lighting
+    pub toon_bands: u8,
+    /// Draw the faces see-through with this opacity, their light adding up where they overlap
+    pub xray_opacity: Option<f32>,
+    /// Color of the edge lines (rgb)
+    pub edge_color: (f32, f32, f32),
+    /// Width of the edge lines in pixels
+    pub edge_thickness: f32,
+    /// Faces skipped when drawing triangles
+    pub cull_mode: CullMode,
+    /// Point of the model placed at the center of the image
+    pub center_mode: CenterMode,
+    /// Light the back of faces like their front
+    pub two_sided: bool,
+    /// Color the back of faces are drawn with, to find inverted faces
+    pub highlight_backfaces: Option<(f32, f32, f32)>,
+    /// Size in pixels of the points drawn for point clouds
+    pub point_size: f32,
+    /// Darken creases and corners with a screen-space ambient occlusion pass
+    pub ambient_occlusion: bool,
+    /// How much fully occluded areas are darkened, from 0 to 1
+    pub ao_strength: f32,
+    /// Distance around each point searched for occluders, relative to the 2 x 2 x 2 box the
+    /// model is scaled to fit
+    pub ao_radius: f32,
+    /// Lines drawn over the model for debugging
+    pub debug_overlay: DebugOverlay,
+    /// Draw the edges of the triangles over the model
+    pub wireframe: bool,
+    /// Thickness in pixels of the wireframe, grid and debug overlay lines
+    pub line_width: f32,
+    /// Grid drawn on the floor beneath the model
+    pub grid: Option<GridOptions>,
+    /// Offset of the depth of the faces of the model, keeping the wireframe visible
+    pub depth_bias: DepthBias,
+    /// Hide the faces of the model behind nearer ones
+    pub depth_test: bool,
+    /// Record the depth of the faces of the model
+    pub depth_write: bool,
+    /// Smooth jagged edges with a post-processing pass (FXAA)
+    pub fxaa: bool,
+    /// Number of frames averaged into the image, each sampling the pixels at another point
+    pub taa_samples: u8,
+    /// Distance from the camera to the plane in focus, enables depth of field when set
+    pub focal_distance: Option<f32>,
+    /// Size of the depth of field blur, as a fraction of the image height
+    pub aperture: f32,
+    /// Render images larger than the device texture limit in several tiles
+    pub tiled: bool,
+    /// Reuse the GPU buffers of a render for the next ones
+    pub reuse_buffers: bool,
+    /// WGSL source replacing the shader the model is drawn with
+    pub custom_shader: Option<String>,
 }
 
 impl Default for Config {
@@ -20,16 +151,76 @@ impl Default for Config {
         Self {
             model_filename: String::new(),
             img_filename: String::new(),
+            input_format: None,
+            stl_mode: StlMode::Auto,
+            object_selector: ObjectSelector::All,
             #[cfg(feature = "image")]
             format: image::ImageFormat::Png,
             width: 1024,
             height: 1024,
+            sizes: Vec::new(),
+            views: Vec::new(),
+            sheet_columns: 3,
             verbosity: 0,
             background: (0.0, 0.0, 0.0, 0.0),
             recalc_normals: false,
+            reject_non_finite: false,
+            allow_remote: false,
+            strict_normals: false,
+            auto_color_objects: false,
+            auto_fix_winding: false,
+            convex_hull: false,
+            embed_model_size: false,
             cam_fov_deg: 45.0,
             cam_position: (2.0, -4.0, 2.0),
-            sample_count: 4, // MSAA 4x
+            model_rotation: (0.0, 0.0, 0.0),
+            mirror: None,
+            auto_frame: false,
+            auto_up: false,
+            orthographic: false,
+            near: 0.1,
+            far: 1024.0,
+            allow_software: false,
+            lights: vec![DirectionalLight::default()],
+            rim_light: None,
+            shininess: 128.0 * 32.0,
+            specular_strength: 1.0,
+            sample_count: AntiAlias::Msaa4.sample_count(),
+            color_space: ColorSpace::Srgb,
+            premultiply_alpha: false,
+            matte_background: None,
+            flatten_background: None,
+            auto_levels: false,
+            output_format: PixelFormat::Rgba8,
+            bit_depth: BitDepth::Eight,
+            topology: Topology::Auto,
+            edges: false,
+            toon_bands: 0,
+            xray_opacity: None,
+            edge_color: (0.0, 0.0, 0.0),
+            edge_thickness: 1.5,
+            cull_mode: CullMode::Back,
+            center_mode: CenterMode::BoundingBox,
+            two_sided: false,
+            highlight_backfaces: None,
+            point_size: 2.0,
+            ambient_occlusion: false,
+            ao_strength: 1.0,
+            ao_radius: 0.5,
+            debug_overlay: DebugOverlay::None,
+            wireframe: false,
+            line_width: 1.0,
+            grid: None,
+            depth_bias: DepthBias::default(),
+            depth_test: true,
+            depth_write: true,
+            fxaa: false,
+            taa_samples: 1,
+            focal_distance: None,
+            aperture: 0.02,
+            tiled: true,
+            reuse_buffers: true,
+            custom_shader: None,
         }
     }
 }
@@ -1,21 +1,23 @@
-use std::io;
+use std::{io, path::PathBuf};
 
 use thiserror::Error;
 
 #[non_exhaustive]
 #[derive(Error, Debug)]
 pub enum MeshError {
-    #[error("IO error")]
+    #[error("IO error: {0}")]
     Io(#[from] io::Error),
-    #[error("STL error")]
+    #[error("Failed to read {}: {source}", path.display())]
+    File { path: PathBuf, source: io::Error },
+    #[error("OBJ error: {0}")]
     Obj(#[from] tobj::LoadError),
-    #[error("3MF error")]
+    #[error("3MF error: {0}")]
     Threemf(#[from] threemf::Error),
     #[error("Unsupported format")]
     UnsupportedFormat,
     #[error("Invalid STL: {0}")]
     InvalidStl(String),
-    #[error("Invalid STL: {0}")]
+    #[error("Invalid OBJ: {0}")]
     InvalidObj(String),
     #[error("Invalid 3MF: {0}")]
     InvalidThreemf(String),
@@ -23,6 +25,24 @@ pub enum MeshError {
     EmptyMesh,
     #[error("No mesh data found in 3MF file")]
     NoMeshData,
+    #[error("The 3MF file is encrypted, which isn't supported")]
+    EncryptedThreemf,
+    #[error("Invalid mesh: {0}")]
+    InvalidMesh(String),
+    #[error("No object matches the {0}")]
+    ObjectNotFound(String),
+    #[error("Vertex {0} has a NaN or infinite coordinate")]
+    NonFiniteVertex(usize),
+    #[error("Triangle {0} has no normal, it has zero area or a zero normal in the file")]
+    DegenerateNormal(usize),
+    #[error("Zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("No supported model file found in the archive")]
+    NoModelInArchive,
+    #[error("Invalid URI: {0}")]
+    InvalidUri(String),
+    #[error("Failed to download model: {0}")]
+    Download(String),
 }
 
 #[non_exhaustive]
@@ -32,6 +52,35 @@ pub enum RenderError {
     /// Error related to WGPU requests.
     #[error("WGPU Error {0}")]
     Wgpu(#[from] wgpu::RequestDeviceError),
+    /// No GPU adapter is available.
+    #[error("Failed to find a suitable GPU adapter")]
+    NoAdapter,
+    /// No GPU adapter is available, and neither is the software fallback.
+    #[error("Failed to find a suitable GPU or software adapter")]
+    NoFallback,
+    /// The clip planes don't satisfy `0 < near < far`.
+    #[error("Invalid clip planes: near {near}, far {far}")]
+    InvalidClipPlanes { near: f32, far: f32 },
+    /// The field of view isn't between 0 and 180 degrees.
+    #[error("Invalid field of view {0} degrees, it must be between 0 and 180")]
+    InvalidFov(f32),
+    /// The adapter can't render with the requested number of samples.
+    #[error("Unsupported sample count {requested}, the adapter supports {supported:?}")]
+    UnsupportedSampleCount { requested: u32, supported: Vec<u32> },
+    /// The requested image is larger than the textures the device can render to.
+    #[error(
+        "Image size {requested} exceeds the largest texture size {max} supported by the device"
+    )]
+    DimensionsTooLarge { requested: u32, max: u32 },
+    /// The GPU device was lost, e.g. after a driver reset, and recreating it didn't help.
+    #[error("GPU device lost: {0}")]
+    DeviceLost(String),
+    /// The custom shader doesn't compile or doesn't match the inputs of the built-in one.
+    #[error("Invalid custom shader: {0}")]
+    ShaderCompile(String),
+    /// The render was cancelled by the caller.
+    #[error("Render cancelled")]
+    Cancelled,
     /// General render operation failure.
     #[error("Render operation failed: {0}")]
     RenderError(String),
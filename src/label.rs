@@ -0,0 +1,83 @@
+/// Height in pixels of the glyphs, before scaling.
+pub const GLYPH_HEIGHT: usize = 5;
+/// Width in pixels of the glyphs, before scaling.
+const GLYPH_WIDTH: usize = 3;
+
+/// Rows of a 3 x 5 pixel glyph, the highest of the 3 bits is the leftmost pixel. Letters are
+/// drawn in upper case, characters other than letters, digits and `.`, `-` and `_` are left
+/// blank.
+const fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        '.' => [0, 0, 0, 0, 0b010],
+        '-' => [0, 0, 0b111, 0, 0],
+        '_' => [0, 0, 0, 0, 0b111],
+        _ => [0; GLYPH_HEIGHT],
+    }
+}
+
+/// Draws `text` in opaque `color` into an 8-bit RGBA image `width` pixels wide, with its top left
+/// corner at `(x, y)` and every glyph pixel drawn as a `scale` x `scale` square. Pixels outside
+/// the image are skipped.
+pub fn draw_label(
+    pixels: &mut [u8],
+    width: usize,
+    (x, y): (usize, usize),
+    text: &str,
+    scale: usize,
+    color: [u8; 4],
+) {
+    let height = pixels.len() / 4 / width;
+    for (i, c) in text.chars().enumerate() {
+        // One blank column between glyphs
+        let left = x + i * (GLYPH_WIDTH + 1) * scale;
+        for (row, bits) in glyph(c).into_iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+                for py in y + row * scale..(y + (row + 1) * scale).min(height) {
+                    for px in left + column * scale..(left + (column + 1) * scale).min(width) {
+                        let start = (py * width + px) * 4;
+                        pixels[start..start + 4].copy_from_slice(&color);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,27 +1,65 @@
 #![cfg_attr(not(feature = "capi"), deny(unsafe_code))]
 
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+mod cache;
+mod callback;
 #[cfg(feature = "capi")]
 mod capi;
 mod config;
 mod error;
+#[cfg(not(target_arch = "wasm32"))]
+mod label;
 mod mesh;
+mod pool;
 mod render;
 mod shader;
+#[cfg(all(feature = "image", feature = "uri", not(target_arch = "wasm32")))]
+mod thumbnailer;
 
-use std::{error::Error, path::Path};
+use std::{error::Error, io::Cursor};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    fmt::Write,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    sync::atomic::AtomicBool,
+};
 
+use glam::Vec3;
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+use image::{
+    codecs::ico::{IcoEncoder, IcoFrame},
+    ExtendedColorType, ImageEncoder, ImageFormat, RgbaImage,
+};
 #[cfg(feature = "image")]
-use image::{ImageBuffer, ImageEncoder, ImageFormat, Rgba};
-use mesh::Mesh;
+use image::{ImageBuffer, Rgb, Rgba};
 
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+pub use crate::cache::ThumbnailCache;
 #[cfg(feature = "capi")]
 pub use crate::capi::*;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::label::{draw_label, GLYPH_HEIGHT};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::render::check_cancelled;
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::render::list_adapters;
 use crate::render::ThumbRenderer;
+#[cfg(all(feature = "image", feature = "uri", not(target_arch = "wasm32")))]
+pub use crate::thumbnailer::{
+    save_thumbnail_for_uri, thumbnail_for_uri, thumbnail_path, ThumbnailSize,
+};
 pub use crate::{
     config::Config,
     error::{MeshError, RenderError},
-    render::RenderOptions,
+    mesh::{BoundingBox, Edge, InputFormat, Mesh, MeshInfo, ObjectSelector, StlMode},
+    render::{
+        AntiAlias, Axis, Background, BitDepth, CameraPreset, CenterMode, ColorSpace, CullMode,
+        DebugOverlay, DepthBias, DirectionalLight, DofOptions, GridOptions, PixelFormat, Progress,
+        RenderOptions, RenderResult, RimLight, Shading, Topology,
+    },
 };
+pub use wgpu::{AdapterInfo, Backend, DeviceType};
 
 /// Renders a 3D model to a buffer.
 ///
@@ -29,173 +67,3836 @@ pub use crate::{
 ///
 /// This function will return an error if the model file cannot be loaded,
 /// or if the rendering process fails.
+#[cfg(not(target_arch = "wasm32"))]
 pub async fn render(
     model_filename: &Path,
     opts: &RenderOptions,
 ) -> Result<Vec<u8>, Box<dyn Error>> {
-    Ok(ThumbRenderer::new(opts.sample_count).await?.render(
-        &Mesh::load(
-            model_filename.to_str().ok_or("Invalid path")?,
-            opts.recalc_normals,
-        )?,
-        opts,
-    )?)
+    let mesh = load_mesh(model_filename, opts).await?;
+    render_mesh(&mesh, opts).await
 }
 
-/// Renders a 3D model to an image.
+/// Like [`render`], blocking the current thread until the image is ready.
+///
+/// No async runtime is needed, which suits synchronous callers like the C API.
 ///
 /// # Errors
 ///
 /// This function will return an error if the model file cannot be loaded,
 /// or if the rendering process fails.
-#[cfg(feature = "image")]
-pub async fn render_to_image(
-    filename: &Path,
+#[cfg(not(target_arch = "wasm32"))]
+pub fn render_blocking(
+    model_filename: &Path,
     opts: &RenderOptions,
-) -> Result<image::DynamicImage, Box<dyn Error>> {
-    let buffer = render(filename, opts).await?;
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    pollster::block_on(render(model_filename, opts))
+}
 
-    // Create image from the raw pixel data
-    Ok(image::DynamicImage::ImageRgba8(
-        ImageBuffer::<Rgba<u8>, _>::from_raw(u32::from(opts.width), u32::from(opts.height), buffer)
-            .ok_or("Failed to create image buffer")?,
-    ))
+/// Like [`render`], failing with [`RenderError::Cancelled`] once `cancel` is set, e.g. when the
+/// client waiting for the image disconnects.
+///
+/// Cancellation is checked before and after loading the model, before the commands of the render
+/// are submitted to the GPU and once the GPU is done with them. Submitted commands can't be
+/// interrupted, but their result is dropped without being read back or post-processed.
+///
+/// # Errors
+///
+/// This function will return an error if the render was cancelled, if the model file cannot be
+/// loaded, or if the rendering process fails.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn render_cancellable(
+    model_filename: &Path,
+    opts: &RenderOptions,
+    cancel: &AtomicBool,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    check_cancelled(cancel)?;
+    let mesh = load_mesh(model_filename, opts).await?;
+    check_cancelled(cancel)?;
+    Ok(ThumbRenderer::new(opts)
+        .await?
+        .render_or_recreate_cancellable(&mesh, opts, cancel)
+        .await?)
 }
 
-/// Renders a 3D model to an image file.
+/// Renders a mesh that is already loaded, or was built by the caller, to a buffer.
+///
+/// The loading options of `opts`, like `input_format`, are ignored. If the GPU device is lost,
+/// e.g. after a driver reset, the GPU is initialized again and the render tried once more before
+/// failing with [`RenderError::DeviceLost`].
+///
+/// # Errors
+///
+/// This function will return an error if the rendering process fails.
+pub async fn render_mesh(mesh: &Mesh, opts: &RenderOptions) -> Result<Vec<u8>, Box<dyn Error>> {
+    Ok(ThumbRenderer::new(opts)
+        .await?
+        .render_or_recreate(mesh, opts)
+        .await?)
+}
+
+/// Initializes the GPU and compiles the pipelines for `opts` ahead of time, then renders a
+/// throwaway 1x1 image so drivers that set up lazily do so as well.
+///
+/// The first render in a process pays for loading the drivers and compiling the shaders, servers
+/// can call this at startup so that cost isn't added to the first request. The device and
+/// pipelines are dropped again, this only warms the driver and its shader caches, which are kept
+/// for the whole process, so later renderers are created faster. It also checks that `opts` can
+/// be rendered with, e.g. that a custom shader compiles.
+///
+/// # Errors
+///
+/// This function will return an error if no GPU can be initialized with `opts`, or if the
+/// pipelines for `opts` can't be created.
+pub async fn prewarm_gpu(opts: &RenderOptions) -> Result<(), Box<dyn Error>> {
+    let mesh = Mesh::from_raw(vec![Vec3::ZERO, Vec3::X, Vec3::Y], vec![0, 1, 2], None)?;
+    let opts = RenderOptions {
+        width: 1,
+        height: 1,
+        ..opts.clone()
+    };
+    ThumbRenderer::new(&opts)
+        .await?
+        .render(&mesh, &opts)
+        .await?;
+    Ok(())
+}
+
+/// Renders a 3D model held in memory to a buffer.
+///
+/// The format is taken from `opts.input_format` if set, otherwise it is guessed from the data.
+/// Unlike the functions taking a file name this works in the browser. OBJ material libraries are
+/// only read from `opts.obj_base_dir`, when it is set.
+///
+/// # Errors
+///
+/// This function will return an error if the model cannot be loaded,
+/// or if the rendering process fails.
+pub async fn render_bytes(model: &[u8], opts: &RenderOptions) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mesh = mesh_from_bytes(model, opts)?;
+    render_mesh(&mesh, opts).await
+}
+
+/// Loads a model held in memory with the format and normals options.
+fn mesh_from_bytes(model: &[u8], opts: &RenderOptions) -> Result<Mesh, MeshError> {
+    let format = opts
+        .input_format
+        .or_else(|| InputFormat::sniff(&model[..model.len().min(512)], model.len() as u64))
+        .ok_or(MeshError::UnsupportedFormat)?;
+    let mesh = if format == InputFormat::Obj {
+        Mesh::from_obj_with_materials(
+            model,
+            opts.obj_base_dir.as_deref(),
+            &opts.object_selector,
+            opts.recalc_normals,
+        )?
+    } else {
+        Mesh::from_reader(
+            Cursor::new(model),
+            format,
+            opts.stl_mode,
+            &opts.object_selector,
+            opts.recalc_normals,
+        )?
+    };
+    prepare_mesh(mesh, opts)
+}
+
+/// Loads a model file with the loading options of `opts`, like the format, normals and
+/// clean-up options, the way the render functions taking a file name do.
+///
+/// The mesh can then be rendered any number of times with [`render_mesh`] and the other
+/// functions taking a mesh, without reading and parsing the file again.
+///
+/// # Errors
+///
+/// This function will return an error if the model file cannot be loaded.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn load_mesh(
+    model_filename: &Path,
+    opts: &RenderOptions,
+) -> Result<Mesh, Box<dyn Error>> {
+    let model_filename = model_filename.to_str().ok_or("Invalid path")?;
+    let load = Mesh::load_async(
+        model_filename,
+        opts.input_format,
+        opts.stl_mode,
+        &opts.object_selector,
+        opts.recalc_normals,
+    );
+    #[cfg(feature = "uri")]
+    let mesh = if opts.allow_remote && model_filename.contains("://") {
+        Mesh::from_uri_async(
+            model_filename,
+            opts.input_format,
+            opts.stl_mode,
+            &opts.object_selector,
+            opts.recalc_normals,
+        )
+        .await?
+    } else {
+        load.await?
+    };
+    #[cfg(not(feature = "uri"))]
+    let mesh = load.await?;
+    Ok(prepare_mesh(mesh, opts)?)
+}
+
+/// Runs the clean-up and checks of `opts` on a freshly loaded mesh.
+fn prepare_mesh(mut mesh: Mesh, opts: &RenderOptions) -> Result<Mesh, MeshError> {
+    mesh.sanitize(opts.reject_non_finite)?;
+    if opts.strict_normals {
+        mesh.check_normals()?;
+    }
+    if opts.convex_hull {
+        mesh = mesh.convex_hull()?;
+    }
+    if opts.auto_fix_winding {
+        mesh.fix_winding();
+    }
+    if opts.auto_color_objects {
+        mesh.color_objects();
+    }
+    Ok(mesh)
+}
+
+/// Renders a 3D model to a buffer for each of the given `(width, height)` sizes.
+///
+/// The model is loaded and the renderer initialized only once, then reused for every size.
 ///
 /// # Errors
 ///
 /// This function will return an error if the model file cannot be loaded,
-/// if the rendering process fails, or if the image cannot be written to the file.
-#[cfg(feature = "image")]
-pub async fn render_to_file(
+/// or if the rendering process fails.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn render_multi_size(
     model_filename: &Path,
-    img_filename: &Path,
-    format: ImageFormat,
+    sizes: &[(u16, u16)],
     opts: &RenderOptions,
-) -> Result<(), Box<dyn Error>> {
-    use std::{ffi, fs, io};
+) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+    render_multi_size_with_progress(model_filename, sizes, opts, |_| ControlFlow::Continue(()))
+        .await
+}
 
-    let img = render_to_image(model_filename, opts).await?;
+/// Renders a 3D model to a buffer for each of the given `(width, height)` sizes, calling
+/// `progress` after each one.
+///
+/// Returning [`ControlFlow::Break`] from `progress` stops before the next size is rendered, and
+/// only the buffers rendered so far are returned.
+///
+/// # Errors
+///
+/// This function will return an error if the model file cannot be loaded,
+/// or if the rendering process fails.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn render_multi_size_with_progress(
+    model_filename: &Path,
+    sizes: &[(u16, u16)],
+    opts: &RenderOptions,
+    progress: impl FnMut(Progress) -> ControlFlow<()>,
+) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+    let mesh = load_mesh(model_filename, opts).await?;
+    render_mesh_multi_size_with_progress(&mesh, sizes, opts, progress).await
+}
 
-    // Choose output
-    // Write to stdout if user did not specify a file
-    let mut output: Box<dyn io::Write> = match &img_filename {
-        os if os == ffi::OsStr::new("-") => Box::new(io::stdout()),
-        out => Box::new(fs::File::create(out)?),
-    };
+/// Renders a mesh that is already loaded to a buffer for each of the given `(width, height)`
+/// sizes.
+///
+/// # Errors
+///
+/// This function will return an error if the rendering process fails.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn render_mesh_multi_size(
+    mesh: &Mesh,
+    sizes: &[(u16, u16)],
+    opts: &RenderOptions,
+) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+    render_mesh_multi_size_with_progress(mesh, sizes, opts, |_| ControlFlow::Continue(())).await
+}
 
-    // write_to() requires a seekable writer for performance reasons.
-    // So we create an in-memory buffer and then dump that to the output.
-    // I wonder if it would be better to use std::io::BufWriter for writing files instead.
-    let mut buff: Vec<u8> = Vec::new();
-    let mut cursor = io::Cursor::new(&mut buff);
+/// Renders a mesh that is already loaded like [`render_multi_size_with_progress`].
+///
+/// # Errors
+///
+/// This function will return an error if the rendering process fails.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn render_mesh_multi_size_with_progress(
+    mesh: &Mesh,
+    sizes: &[(u16, u16)],
+    opts: &RenderOptions,
+    mut progress: impl FnMut(Progress) -> ControlFlow<()>,
+) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+    let mut renderer = ThumbRenderer::new(opts).await?;
 
-    // Encode image with specified format
-    // If encoding a PNG image, use fastest compression method
-    // Not sure if this is really necessary. Fast is the default anyways.
-    match format {
-        ImageFormat::Png => {
-            let encoder = image::codecs::png::PngEncoder::new_with_quality(
-                &mut cursor,
-                image::codecs::png::CompressionType::Fast,
-                //image::codecs::png::CompressionType::Default,
-                image::codecs::png::FilterType::Adaptive,
-            );
-            encoder.write_image(
-                img.as_bytes(),
-                u32::from(opts.width),
-                u32::from(opts.height),
-                img.color().into(),
-            )?;
+    let mut buffers = Vec::with_capacity(sizes.len());
+    for &(width, height) in sizes {
+        buffers.push(
+            renderer
+                .render_or_recreate(
+                    mesh,
+                    &RenderOptions {
+                        width,
+                        height,
+                        ..opts.clone()
+                    },
+                )
+                .await?,
+        );
+
+        let status = progress(Progress {
+            completed: buffers.len(),
+            total: sizes.len(),
+        });
+        if status.is_break() {
+            break;
         }
-        format => img.write_to(&mut cursor, format)?,
     }
 
-    output.write_all(&buff)?;
-    output.flush()?;
+    Ok(buffers)
+}
 
-    Ok(())
+/// Renders orthographic views of a 3D model from each of `views`, laid out in a grid `columns`
+/// views wide and labeled with their names.
+///
+/// Every view is `opts.width` x `opts.height` pixels, so the sheet is `columns * opts.width`
+/// pixels wide and as many views tall as needed. The returned buffer holds 8-bit RGBA pixels,
+/// cells without a view are left transparent.
+///
+/// # Errors
+///
+/// This function will return an error if `columns` is 0, if the model file cannot be loaded,
+/// or if the rendering process fails.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn render_views_sheet(
+    model_filename: &Path,
+    views: &[CameraPreset],
+    columns: usize,
+    opts: &RenderOptions,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    if columns == 0 {
+        return Err("A view sheet needs at least one column".into());
+    }
+    let mesh = load_mesh(model_filename, opts).await?;
+    render_mesh_views_sheet(&mesh, views, columns, opts).await
 }
 
-#[cfg(feature = "image")]
-#[cfg(test)]
-mod tests {
-    #![allow(clippy::borrow_interior_mutable_const)]
+/// Renders views of a mesh that is already loaded like [`render_views_sheet`].
+///
+/// # Errors
+///
+/// This function will return an error if `columns` is 0, or if the rendering process fails.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn render_mesh_views_sheet(
+    mesh: &Mesh,
+    views: &[CameraPreset],
+    columns: usize,
+    opts: &RenderOptions,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    if columns == 0 {
+        return Err("A view sheet needs at least one column".into());
+    }
 
-    use std::{cell::LazyCell, fs, io::ErrorKind};
+    let opts = RenderOptions {
+        bit_depth: BitDepth::Eight,
+        output_format: PixelFormat::Rgba8,
+        orthographic: true,
+        ..opts.clone()
+    };
+    let mut renderer = ThumbRenderer::new(&opts).await?;
 
-    use config::Config;
+    let mut sheet = Sheet::new(columns, (opts.width, opts.height), views.len());
+    for (i, &view) in views.iter().enumerate() {
+        let opts = RenderOptions {
+            cam_position: view.position(opts.cam_position),
+            ..opts.clone()
+        };
+        sheet.blit(i, &renderer.render_or_recreate(mesh, &opts).await?);
+        sheet.label(i, view.name(), false, LABEL_COLOR);
+    }
 
-    use super::*;
+    Ok(sheet.pixels)
+}
 
-    #[allow(clippy::declare_interior_mutable_const)]
-    const CONFIG: LazyCell<Config> = LazyCell::new(Config::default);
+/// Color of the labels of view and contact sheets.
+const LABEL_COLOR: [u8; 4] = [64, 64, 64, u8::MAX];
 
-    #[tokio::test]
-    async fn cube_stl() {
-        let img_filename = Path::new("cube-stl.png");
-        match fs::remove_file(img_filename) {
-            Err(ref error) if error.kind() == ErrorKind::NotFound => (),
-            r => r.expect("Couldn't clean files before testing"),
+/// Color of the labels of contact sheet cells whose model failed to load.
+const FAILED_LABEL_COLOR: [u8; 4] = [192, 32, 32, u8::MAX];
+
+/// Grid of equally sized cells in an 8-bit RGBA image, for view and contact sheets.
+struct Sheet {
+    pixels: Vec<u8>,
+    columns: usize,
+    cell: (usize, usize),
+}
+
+impl Sheet {
+    /// Transparent sheet `columns` cells wide with enough rows for `count` cells.
+    fn new(columns: usize, (width, height): (u16, u16), count: usize) -> Self {
+        let cell = (usize::from(width), usize::from(height));
+        Self {
+            pixels: vec![0; columns * cell.0 * count.div_ceil(columns) * cell.1 * 4],
+            columns,
+            cell,
         }
+    }
 
-        render_to_file(
-            Path::new("test/data/cube.stl"),
-            img_filename,
-            ImageFormat::Png,
-            &(&*CONFIG).into(),
+    /// Top left corner of cell `index`.
+    const fn origin(&self, index: usize) -> (usize, usize) {
+        (
+            index % self.columns * self.cell.0,
+            index / self.columns * self.cell.1,
         )
-        .await
-        .expect("Error in render function");
+    }
 
-        let size = fs::metadata(img_filename).expect("No file created").len();
+    /// Copies the pixels of a render the size of a cell into cell `index`.
+    fn blit(&mut self, index: usize, buffer: &[u8]) {
+        let width = self.cell.0;
+        let sheet_width = self.columns * width;
+        let (x, y) = self.origin(index);
+        for (row, data) in buffer.chunks_exact(width * 4).enumerate() {
+            let start = ((y + row) * sheet_width + x) * 4;
+            self.pixels[start..start + width * 4].copy_from_slice(data);
+        }
+    }
 
-        assert_ne!(0, size);
+    /// Draws `text` at the top or `bottom` of cell `index`, cut short to fit the cell. Cells too
+    /// small for a line of text get no label.
+    fn label(&mut self, index: usize, text: &str, bottom: bool, color: [u8; 4]) {
+        let (width, height) = self.cell;
+        // Labels are about a twentieth of the cell height
+        let scale = (height / (GLYPH_HEIGHT * 20)).max(1);
+        let line_height = (GLYPH_HEIGHT + 2) * scale;
+        if height < line_height {
+            return;
+        }
+        // Glyphs are 4 scaled pixels apart, keep a margin on both sides of the cell
+        let fits = width.saturating_sub(4 * scale) / (4 * scale);
+        let text: String = text.chars().take(fits).collect();
+        let (x, y) = self.origin(index);
+        let top = if bottom {
+            y + height - line_height
+        } else {
+            y + 2 * scale
+        };
+        draw_label(
+            &mut self.pixels,
+            self.columns * width,
+            (x + 2 * scale, top),
+            &text,
+            scale,
+            color,
+        );
+    }
+}
+
+/// A contact sheet, see [`render_contact_sheet`].
+#[derive(Debug)]
+pub struct ContactSheet {
+    /// 8-bit RGBA pixels of the sheet
+    pub pixels: Vec<u8>,
+    /// Model files that failed to load, with the error message, in the order of the files. Their
+    /// cells are left transparent, with a red label if the cells are labelled
+    pub failures: Vec<(PathBuf, String)>,
+}
+
+/// Renders each of `files` into a `(width, height)` sized cell of a grid `columns` cells wide.
+///
+/// This gives an overview of a folder of models at a glance. With `labels`, every cell is
+/// captioned with the file name, cut short to fit the cell.
+/// The sheet is `columns * cell.0` pixels wide and as many cells tall as needed, cells without
+/// a model are left transparent. Files that fail to load don't stop the sheet, they are listed
+/// in [`ContactSheet::failures`]. The size of `opts` is ignored in favor of `cell`.
+///
+/// # Errors
+///
+/// This function will return an error if `columns` is 0, if `files` is empty, or if the
+/// rendering process fails.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn render_contact_sheet(
+    files: &[PathBuf],
+    columns: usize,
+    (cell_width, cell_height): (u16, u16),
+    labels: bool,
+    opts: &RenderOptions,
+) -> Result<ContactSheet, Box<dyn Error>> {
+    if columns == 0 {
+        return Err("A contact sheet needs at least one column".into());
+    }
+    if files.is_empty() {
+        return Err("A contact sheet needs at least one model file".into());
     }
 
-    #[tokio::test]
-    async fn cube_obj() {
-        let img_filename = Path::new("cube-obj.png");
-        match fs::remove_file(img_filename) {
-            Err(error) if error.kind() == ErrorKind::NotFound => (),
-            r => r.expect("Couldn't clean files before testing"),
+    let opts = RenderOptions {
+        width: cell_width,
+        height: cell_height,
+        bit_depth: BitDepth::Eight,
+        output_format: PixelFormat::Rgba8,
+        ..opts.clone()
+    };
+    let mut renderer = ThumbRenderer::new(&opts).await?;
+
+    let mut sheet = Sheet::new(columns, (cell_width, cell_height), files.len());
+    let mut failures = Vec::new();
+    for (i, file) in files.iter().enumerate() {
+        // The error is kept as a message, boxed errors would keep the future from being `Send`
+        let color = match load_mesh(file, &opts).await.map_err(|e| e.to_string()) {
+            Ok(mesh) => {
+                sheet.blit(i, &renderer.render_or_recreate(&mesh, &opts).await?);
+                LABEL_COLOR
+            }
+            Err(error) => {
+                failures.push((file.clone(), error));
+                FAILED_LABEL_COLOR
+            }
+        };
+        if labels {
+            let name = file.file_name().unwrap_or_default().to_string_lossy();
+            sheet.label(i, &name, true, color);
         }
+    }
 
-        render_to_file(
-            Path::new("test/data/cube.obj"),
-            img_filename,
-            ImageFormat::Png,
-            &(&*CONFIG).into(),
-        )
-        .await
-        .expect("Error in render function");
+    Ok(ContactSheet {
+        pixels: sheet.pixels,
+        failures,
+    })
+}
 
-        let size = fs::metadata(img_filename).expect("No file created").len();
+/// Renders the silhouette of a 3D model as a coverage mask, with one byte per pixel that is 0
+/// where the background shows and 255 where the model covers the pixel completely.
+///
+/// The mask matches the alpha channel of a render with a transparent background, anti-aliased
+/// edges included. The shading, background and output format options of `opts` are ignored.
+///
+/// # Errors
+///
+/// This function will return an error if the model file cannot be loaded,
+/// or if the rendering process fails.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn render_mask(
+    model_filename: &Path,
+    opts: &RenderOptions,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let opts = RenderOptions {
+        background: Background::default(),
+        matte_background: None,
+        output_format: PixelFormat::Rgba8,
+        bit_depth: BitDepth::Eight,
+        ambient_occlusion: false,
+        debug_overlay: DebugOverlay::None,
+        wireframe: false,
+        grid: None,
+        ..opts.clone()
+    };
+    let buffer = render(model_filename, &opts).await?;
 
-        assert_ne!(0, size);
+    Ok(buffer.chunks_exact(4).map(|pixel| pixel[3]).collect())
+}
+
+/// Characters of the cells covered by the model in terminal previews, from dark to bright.
+#[cfg(not(target_arch = "wasm32"))]
+const ANSI_RAMP: &[u8] = b".:-=+*#%@";
+
+/// Renders a 3D model to text for previewing it in a terminal, `cols` characters wide and `rows`
+/// lines high.
+///
+/// Cells the model covers at least half of are drawn with characters from `.` to `@` by
+/// brightness, the others are spaces. With `color` set the characters are also colored like the
+/// model with 24-bit ANSI escape codes. Terminal cells are assumed to be twice as high as they are
+/// wide. The size, background and output format options of `opts` are ignored.
+///
+/// # Errors
+///
+/// This function will return an error if `cols` or `rows` is 0, if the model file cannot be
+/// loaded, or if the rendering process fails.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn render_to_ansi(
+    model_filename: &Path,
+    cols: u16,
+    rows: u16,
+    color: bool,
+    opts: &RenderOptions,
+) -> Result<String, Box<dyn Error>> {
+    if cols == 0 || rows == 0 {
+        return Err("The preview must be at least 1 x 1 characters".into());
     }
+    // Rendered at around 4 x 8 pixels per cell and averaged
+    let width = u32::from(cols) * 4;
+    let height = (width * u32::from(rows) * 2 / u32::from(cols)).max(1);
+    let opts = RenderOptions {
+        width: u16::try_from(width)?,
+        height: u16::try_from(height)?,
+        background: Background::default(),
+        matte_background: None,
+        premultiply_alpha: false,
+        output_format: PixelFormat::Rgba8,
+        bit_depth: BitDepth::Eight,
+        ..opts.clone()
+    };
+    let buffer = render(model_filename, &opts).await?;
 
-    #[tokio::test]
-    async fn cube_3mf() {
-        let img_filename = Path::new("cube-3mf.png");
-        match fs::remove_file(img_filename) {
-            Err(error) if error.kind() == ErrorKind::NotFound => (),
-            r => r.expect("Couldn't clean files before testing"),
+    // Alpha weighted color and alpha summed over each cell, and the number of pixels summed
+    let (cols, rows) = (usize::from(cols), usize::from(rows));
+    let mut cells = vec![([0_u64; 4], 0_u64); cols * rows];
+    let (width, height) = (width as usize, height as usize);
+    for (y, row) in buffer.chunks_exact(width * 4).enumerate() {
+        for (x, pixel) in row.chunks_exact(4).enumerate() {
+            let (sum, count) = &mut cells[y * rows / height * cols + x * cols / width];
+            let alpha = u64::from(pixel[3]);
+            for (sum, &channel) in sum.iter_mut().zip(&pixel[..3]) {
+                *sum += u64::from(channel) * alpha;
+            }
+            sum[3] += alpha;
+            *count += 1;
         }
+    }
 
-        render_to_file(
-            Path::new("test/data/cube.3mf"),
-            img_filename,
-            ImageFormat::Png,
-            &(&*CONFIG).into(),
-        )
-        .await
-        .expect("Error in render function");
+    let mut text = String::with_capacity(cols * rows);
+    for line in cells.chunks_exact(cols) {
+        for &([r, g, b, alpha], count) in line {
+            if alpha * 2 < count * 255 {
+                text.push(' ');
+                continue;
+            }
+            // Averages of 8-bit values
+            #[allow(clippy::cast_possible_truncation)]
+            let [r, g, b] = [r, g, b].map(|c| (c / alpha) as u8);
+            let luma = 0.0722f32.mul_add(
+                f32::from(b),
+                0.2126f32.mul_add(f32::from(r), 0.7152 * f32::from(g)),
+            ) / 255.0;
+            #[allow(
+                clippy::cast_possible_truncation,
+                clippy::cast_sign_loss,
+                clippy::cast_precision_loss
+            )]
+            let character = ANSI_RAMP[(luma * (ANSI_RAMP.len() - 1) as f32).round() as usize];
+            if color {
+                write!(text, "\x1b[38;2;{r};{g};{b}m")?;
+            }
+            text.push(char::from(character));
+        }
+        if color {
+            text.push_str("\x1b[0m");
+        }
+        text.push('\n');
+    }
 
-        let size = fs::metadata(img_filename).expect("No file created").len();
+    Ok(text)
+}
 
-        assert_ne!(0, size);
+/// Renders a 3D model to an image.
+///
+/// # Errors
+///
+/// This function will return an error if the model file cannot be loaded,
+/// or if the rendering process fails.
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+pub async fn render_to_image(
+    filename: &Path,
+    opts: &RenderOptions,
+) -> Result<image::DynamicImage, Box<dyn Error>> {
+    buffer_to_image(render(filename, opts).await?, opts)
+}
+
+/// Renders a mesh that is already loaded, or was built by the caller, to an image.
+///
+/// # Errors
+///
+/// This function will return an error if the rendering process fails.
+#[cfg(feature = "image")]
+pub async fn render_mesh_to_image(
+    mesh: &Mesh,
+    opts: &RenderOptions,
+) -> Result<image::DynamicImage, Box<dyn Error>> {
+    buffer_to_image(render_mesh(mesh, opts).await?, opts)
+}
+
+/// Renders a 3D model held in memory to an image, see [`render_bytes`].
+///
+/// # Errors
+///
+/// This function will return an error if the model cannot be loaded,
+/// or if the rendering process fails.
+#[cfg(feature = "image")]
+pub async fn render_bytes_to_image(
+    model: &[u8],
+    opts: &RenderOptions,
+) -> Result<image::DynamicImage, Box<dyn Error>> {
+    buffer_to_image(render_bytes(model, opts).await?, opts)
+}
+
+/// Creates an image from the raw pixel data of a render.
+#[cfg(feature = "image")]
+fn buffer_to_image(
+    buffer: Vec<u8>,
+    opts: &RenderOptions,
+) -> Result<image::DynamicImage, Box<dyn Error>> {
+    let width = u32::from(opts.width);
+    let height = u32::from(opts.height);
+
+    if opts.bit_depth == BitDepth::Sixteen {
+        let buffer = buffer
+            .chunks_exact(2)
+            .map(|bytes| u16::from_ne_bytes([bytes[0], bytes[1]]))
+            .collect();
+        return Ok(match opts.output_format {
+            PixelFormat::Rgba8 => image::DynamicImage::ImageRgba16(
+                ImageBuffer::<Rgba<u16>, _>::from_raw(width, height, buffer)
+                    .ok_or("Failed to create image buffer")?,
+            ),
+            PixelFormat::Rgb8 => image::DynamicImage::ImageRgb16(
+                ImageBuffer::<Rgb<u16>, _>::from_raw(width, height, buffer)
+                    .ok_or("Failed to create image buffer")?,
+            ),
+        });
+    }
+
+    if opts.bit_depth == BitDepth::Float {
+        let buffer = buffer
+            .chunks_exact(4)
+            .map(|bytes| f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        return Ok(match opts.output_format {
+            PixelFormat::Rgba8 => image::DynamicImage::ImageRgba32F(
+                ImageBuffer::<Rgba<f32>, _>::from_raw(width, height, buffer)
+                    .ok_or("Failed to create image buffer")?,
+            ),
+            PixelFormat::Rgb8 => image::DynamicImage::ImageRgb32F(
+                ImageBuffer::<Rgb<f32>, _>::from_raw(width, height, buffer)
+                    .ok_or("Failed to create image buffer")?,
+            ),
+        });
+    }
+
+    // Create image from the raw pixel data
+    Ok(match opts.output_format {
+        PixelFormat::Rgba8 => image::DynamicImage::ImageRgba8(
+            ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, buffer)
+                .ok_or("Failed to create image buffer")?,
+        ),
+        PixelFormat::Rgb8 => image::DynamicImage::ImageRgb8(
+            ImageBuffer::<Rgb<u8>, _>::from_raw(width, height, buffer)
+                .ok_or("Failed to create image buffer")?,
+        ),
+    })
+}
+
+/// Renders a 3D model to an image file.
+///
+/// Returns statistics about the model and the render. Before version 0.6 this returned `()`,
+/// callers that don't need the statistics can ignore them.
+///
+/// # Errors
+///
+/// This function will return an error if the model file cannot be loaded,
+/// if the rendering process fails, or if the image cannot be written to the file.
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+pub async fn render_to_file(
+    model_filename: &Path,
+    img_filename: &Path,
+    format: ImageFormat,
+    opts: &RenderOptions,
+) -> Result<RenderResult, Box<dyn Error>> {
+    let start = std::time::Instant::now();
+
+    let mesh = load_mesh(model_filename, opts).await?;
+    let (buffer, coverage) = ThumbRenderer::new(opts)
+        .await?
+        .render_with_coverage(&mesh, opts)
+        .await?;
+    let img = buffer_to_image(buffer, opts)?;
+    let buff = encode_image_with_size(&img, format, &mesh, opts)?;
+    write_output(img_filename, &buff)?;
+
+    Ok(RenderResult {
+        mesh: mesh.info(),
+        width: opts.width,
+        height: opts.height,
+        render_time: start.elapsed(),
+        coverage,
+    })
+}
+
+/// Renders a 3D model to an image file for each of the given `(width, height)` sizes.
+///
+/// The size is appended to the file name of each image, e.g. `thumb.png` becomes `thumb-128.png`
+/// for square sizes and `thumb-128x64.png` otherwise.
+///
+/// # Errors
+///
+/// This function will return an error if the model file cannot be loaded,
+/// if the rendering process fails, or if the images cannot be written to the files.
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+pub async fn render_multi_size_to_files(
+    model_filename: &Path,
+    img_filename: &Path,
+    sizes: &[(u16, u16)],
+    format: ImageFormat,
+    opts: &RenderOptions,
+) -> Result<(), Box<dyn Error>> {
+    render_multi_size_to_files_with_progress(
+        model_filename,
+        img_filename,
+        sizes,
+        format,
+        opts,
+        |_| ControlFlow::Continue(()),
+    )
+    .await
+}
+
+/// Renders a 3D model to an image file for each of the given `(width, height)` sizes, calling
+/// `progress` after each file is written.
+///
+/// Returning [`ControlFlow::Break`] from `progress` stops before the next size is rendered, the
+/// files already written are kept. See [`render_multi_size_to_files`] for the file names.
+///
+/// # Errors
+///
+/// This function will return an error if the model file cannot be loaded,
+/// if the rendering process fails, or if the images cannot be written to the files.
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+pub async fn render_multi_size_to_files_with_progress(
+    model_filename: &Path,
+    img_filename: &Path,
+    sizes: &[(u16, u16)],
+    format: ImageFormat,
+    opts: &RenderOptions,
+    mut progress: impl FnMut(Progress) -> ControlFlow<()>,
+) -> Result<(), Box<dyn Error>> {
+    let mut renderer = ThumbRenderer::new(opts).await?;
+    let mesh = load_mesh(model_filename, opts).await?;
+
+    for (i, &(width, height)) in sizes.iter().enumerate() {
+        let opts = RenderOptions {
+            width,
+            height,
+            ..opts.clone()
+        };
+        let img = buffer_to_image(renderer.render_or_recreate(&mesh, &opts).await?, &opts)?;
+
+        let buff = encode_image_with_size(&img, format, &mesh, &opts)?;
+        write_output(&sized_filename(img_filename, width, height), &buff)?;
+
+        let status = progress(Progress {
+            completed: i + 1,
+            total: sizes.len(),
+        });
+        if status.is_break() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Icon layers smaller than this are rendered larger and downsampled, as tiny renders alias badly.
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+const ICO_SUPERSAMPLE_SIZE: u16 = 64;
+
+/// Renders a 3D model to a single ICO file, with a layer for each of the given `(width, height)`
+/// sizes.
+///
+/// Layers smaller than 64 pixels are rendered at a multiple of their size and downsampled, which
+/// keeps tiny icons like 16x16 favicons sharp. Layers are always 8-bit RGBA.
+///
+/// # Errors
+///
+/// This function will return an error if a size is not between 1 and 256 pixels, if the model
+/// file cannot be loaded, if the rendering process fails, or if the image cannot be written to
+/// the file.
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+pub async fn render_to_ico(
+    model_filename: &Path,
+    img_filename: &Path,
+    sizes: &[(u16, u16)],
+    opts: &RenderOptions,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(&(width, height)) = sizes
+        .iter()
+        .find(|&&(width, height)| !(1..=256).contains(&width) || !(1..=256).contains(&height))
+    {
+        return Err(
+            format!("ICO layers must be 1 to 256 pixels wide, not {width}x{height}").into(),
+        );
+    }
+
+    let opts = RenderOptions {
+        bit_depth: BitDepth::Eight,
+        output_format: PixelFormat::Rgba8,
+        // Downsampling averages straight colors
+        premultiply_alpha: false,
+        ..opts.clone()
+    };
+    let mut renderer = ThumbRenderer::new(&opts).await?;
+    let mesh = load_mesh(model_filename, &opts).await?;
+
+    let mut frames = Vec::with_capacity(sizes.len());
+    for &(width, height) in sizes {
+        let factor = ICO_SUPERSAMPLE_SIZE.div_ceil(width.max(height));
+        let opts = RenderOptions {
+            width: width * factor,
+            height: height * factor,
+            point_size: opts.point_size * f32::from(factor),
+            ..opts.clone()
+        };
+        let img =
+            buffer_to_image(renderer.render_or_recreate(&mesh, &opts).await?, &opts)?.into_rgba8();
+        let img = downsample(&img, u32::from(factor));
+        frames.push(IcoFrame::as_png(
+            img.as_raw(),
+            img.width(),
+            img.height(),
+            ExtendedColorType::Rgba8,
+        )?);
+    }
+
+    let mut buff = Vec::new();
+    IcoEncoder::new(&mut buff).encode_images(&frames)?;
+    write_output(img_filename, &buff)
+}
+
+/// Renders a sheet of orthographic views of a 3D model, like [`render_views_sheet`], to an image
+/// file.
+///
+/// # Errors
+///
+/// This function will return an error if `columns` is 0, if the model file cannot be loaded,
+/// if the rendering process fails, or if the image cannot be written to the file.
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+pub async fn render_views_sheet_to_file(
+    model_filename: &Path,
+    img_filename: &Path,
+    views: &[CameraPreset],
+    columns: usize,
+    format: ImageFormat,
+    opts: &RenderOptions,
+) -> Result<(), Box<dyn Error>> {
+    let sheet = render_views_sheet(model_filename, views, columns, opts).await?;
+    let width = u32::try_from(columns)? * u32::from(opts.width);
+    let height = u32::try_from(views.len().div_ceil(columns))? * u32::from(opts.height);
+    let img = RgbaImage::from_raw(width, height, sheet).ok_or("Failed to create image buffer")?;
+    write_output(
+        img_filename,
+        &encode_image(&image::DynamicImage::ImageRgba8(img), format)?,
+    )
+}
+
+/// Renders a contact sheet of 3D models, like [`render_contact_sheet`], to an image file.
+///
+/// Returns the model files that failed to load, with the error message.
+///
+/// # Errors
+///
+/// This function will return an error if `columns` is 0, if `files` is empty, if the rendering
+/// process fails, or if the image cannot be written to the file.
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+pub async fn render_contact_sheet_to_file(
+    files: &[PathBuf],
+    img_filename: &Path,
+    columns: usize,
+    cell: (u16, u16),
+    labels: bool,
+    format: ImageFormat,
+    opts: &RenderOptions,
+) -> Result<Vec<(PathBuf, String)>, Box<dyn Error>> {
+    let sheet = render_contact_sheet(files, columns, cell, labels, opts).await?;
+    let width = u32::try_from(columns)? * u32::from(cell.0);
+    let height = u32::try_from(files.len().div_ceil(columns))? * u32::from(cell.1);
+    let img =
+        RgbaImage::from_raw(width, height, sheet.pixels).ok_or("Failed to create image buffer")?;
+    write_output(
+        img_filename,
+        &encode_image(&image::DynamicImage::ImageRgba8(img), format)?,
+    )?;
+    Ok(sheet.failures)
+}
+
+/// Renders the silhouette of a 3D model to a grayscale image file, see [`render_mask`].
+///
+/// # Errors
+///
+/// This function will return an error if the model file cannot be loaded,
+/// if the rendering process fails, or if the image cannot be written to the file.
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+pub async fn render_mask_to_file(
+    model_filename: &Path,
+    img_filename: &Path,
+    format: ImageFormat,
+    opts: &RenderOptions,
+) -> Result<(), Box<dyn Error>> {
+    let mask = render_mask(model_filename, opts).await?;
+    let img = image::GrayImage::from_raw(u32::from(opts.width), u32::from(opts.height), mask)
+        .ok_or("Failed to create image buffer")?;
+    write_output(
+        img_filename,
+        &encode_image(&image::DynamicImage::ImageLuma8(img), format)?,
+    )
+}
+
+/// Shrinks an image by an integer factor, averaging the colors weighted by their alpha so the
+/// transparent background doesn't darken the edges of the model.
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+fn downsample(img: &RgbaImage, factor: u32) -> RgbaImage {
+    let count = factor * factor;
+    RgbaImage::from_fn(img.width() / factor, img.height() / factor, |x, y| {
+        let mut sum = [0u32; 4];
+        for dy in 0..factor {
+            for dx in 0..factor {
+                let pixel = img.get_pixel(x * factor + dx, y * factor + dy).0;
+                let alpha = u32::from(pixel[3]);
+                for (sum, &channel) in sum.iter_mut().zip(&pixel[..3]) {
+                    *sum += u32::from(channel) * alpha;
+                }
+                sum[3] += alpha;
+            }
+        }
+
+        // Rounded averages never exceed the channel range
+        let average = |sum: u32, count: u32| {
+            u8::try_from((sum + count / 2) / count.max(1)).unwrap_or(u8::MAX)
+        };
+        Rgba([
+            average(sum[0], sum[3]),
+            average(sum[1], sum[3]),
+            average(sum[2], sum[3]),
+            average(sum[3], count),
+        ])
+    })
+}
+
+/// Renders a 3D model to an image file, reusing a previous render from `cache` when available.
+///
+/// Thumbnails are keyed by the contents of the model and of the material libraries of OBJ
+/// models. Reading the model from stdin bypasses the cache, as its contents can't be read twice,
+/// and so do remote models, which would have to be downloaded to be looked up.
+///
+/// # Errors
+///
+/// This function will return an error if the model file cannot be loaded,
+/// if the rendering process fails, or if the image cannot be written to the file or the cache.
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+pub async fn render_to_file_cached(
+    cache: &ThumbnailCache,
+    model_filename: &Path,
+    img_filename: &Path,
+    format: ImageFormat,
+    opts: &RenderOptions,
+) -> Result<(), Box<dyn Error>> {
+    use std::fs;
+
+    if model_filename == Path::new("-") {
+        render_to_file(model_filename, img_filename, format, opts).await?;
+        return Ok(());
+    }
+    #[cfg(feature = "uri")]
+    let local_path;
+    #[cfg(feature = "uri")]
+    let model_filename = match model_filename.to_str() {
+        Some(uri) if uri.contains("://") => match mesh::file_uri_path(uri) {
+            Ok(path) => {
+                local_path = PathBuf::from(path);
+                &local_path
+            }
+            Err(_) if opts.allow_remote => {
+                render_to_file(model_filename, img_filename, format, opts).await?;
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        },
+        _ => model_filename,
+    };
+
+    // The model is read once, so the thumbnail stored is always the one of the contents it is
+    // keyed by
+    let model = fs::read(model_filename)?;
+    let input_format = opts
+        .input_format
+        .or_else(|| {
+            model_filename
+                .extension()
+                .and_then(|s| s.to_str())
+                .and_then(InputFormat::from_extension)
+        })
+        .or_else(|| InputFormat::sniff(&model[..model.len().min(512)], model.len() as u64));
+    // Material libraries are looked up next to the file, like `load_mesh` does
+    let material_dir = model_filename.parent();
+    let libraries = match (input_format, material_dir) {
+        (Some(InputFormat::Obj), Some(dir)) => mesh::obj_material_libraries(&model, dir),
+        _ => Vec::new(),
+    };
+
+    let cached = cache.path_with_companions(&model, &libraries, format, opts);
+    let buff = if let Some(buff) = cache.get(&cached)? {
+        buff
+    } else {
+        let mesh = mesh_from_bytes(
+            &model,
+            &RenderOptions {
+                input_format,
+                obj_base_dir: material_dir.map(Path::to_path_buf),
+                ..opts.clone()
+            },
+        )?;
+        let img = render_mesh_to_image(&mesh, opts).await?;
+        let buff = encode_image_with_size(&img, format, &mesh, opts)?;
+        cache.insert(&cached, &buff)?;
+        buff
+    };
+
+    write_output(img_filename, &buff)
+}
+
+/// Appends the image size to a file name, keeping its extension.
+///
+/// This is the name of the file written for each size by [`render_multi_size_to_files`].
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+#[must_use]
+pub fn sized_filename(img_filename: &Path, width: u16, height: u16) -> std::path::PathBuf {
+    let mut sized_filename = img_filename.with_extension("").into_os_string();
+    if width == height {
+        sized_filename.push(format!("-{width}"));
+    } else {
+        sized_filename.push(format!("-{width}x{height}"));
+    }
+
+    let mut sized_filename = std::path::PathBuf::from(sized_filename);
+    if let Some(extension) = img_filename.extension() {
+        sized_filename.set_extension(extension);
+    }
+    sized_filename
+}
+
+/// Encodes an image with the given format.
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+fn encode_image(img: &image::DynamicImage, format: ImageFormat) -> Result<Vec<u8>, Box<dyn Error>> {
+    use std::io;
+
+    // write_to() requires a seekable writer for performance reasons.
+    // So we create an in-memory buffer and then dump that to the output.
+    // I wonder if it would be better to use std::io::BufWriter for writing files instead.
+    let mut buff: Vec<u8> = Vec::new();
+    let mut cursor = io::Cursor::new(&mut buff);
+
+    // Encode image with specified format
+    // If encoding a PNG image, use fastest compression method
+    // Not sure if this is really necessary. Fast is the default anyways.
+    match format {
+        ImageFormat::Png => {
+            let encoder = image::codecs::png::PngEncoder::new_with_quality(
+                &mut cursor,
+                image::codecs::png::CompressionType::Fast,
+                //image::codecs::png::CompressionType::Default,
+                image::codecs::png::FilterType::Adaptive,
+            );
+            encoder.write_image(
+                img.as_bytes(),
+                img.width(),
+                img.height(),
+                img.color().into(),
+            )?;
+        }
+        // OpenEXR only stores floats, other bit depths are converted without linearizing them
+        ImageFormat::OpenExr
+            if !matches!(
+                img,
+                image::DynamicImage::ImageRgb32F(_) | image::DynamicImage::ImageRgba32F(_)
+            ) =>
+        {
+            image::DynamicImage::ImageRgba32F(img.to_rgba32f()).write_to(&mut cursor, format)?;
+        }
+        format => img.write_to(&mut cursor, format)?,
+    }
+
+    Ok(buff)
+}
+
+/// Encodes an image like [`encode_image`], adding the size of the model before it is scaled to
+/// fit to PNG images if `opts.embed_model_size` is set.
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+fn encode_image_with_size(
+    img: &image::DynamicImage,
+    format: ImageFormat,
+    mesh: &Mesh,
+    opts: &RenderOptions,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    if !opts.embed_model_size || format != ImageFormat::Png {
+        return encode_image(img, format);
+    }
+    // The image crate can't write text chunks, so those PNG images are encoded with png directly
+    let (color, depth) = match img.color() {
+        image::ColorType::Rgb8 => (png::ColorType::Rgb, png::BitDepth::Eight),
+        image::ColorType::Rgba8 => (png::ColorType::Rgba, png::BitDepth::Eight),
+        image::ColorType::Rgb16 => (png::ColorType::Rgb, png::BitDepth::Sixteen),
+        image::ColorType::Rgba16 => (png::ColorType::Rgba, png::BitDepth::Sixteen),
+        _ => return encode_image(img, format),
+    };
+    let size = mesh.bounds.size();
+
+    let mut buff = Vec::new();
+    let mut encoder = png::Encoder::new(&mut buff, img.width(), img.height());
+    encoder.set_color(color);
+    encoder.set_depth(depth);
+    encoder.set_compression(png::Compression::Fast);
+    encoder.add_text_chunk(
+        "Model::Size".to_owned(),
+        format!("{} x {} x {}", size.x, size.y, size.z),
+    )?;
+    let mut writer = encoder.write_header()?;
+    if depth == png::BitDepth::Sixteen {
+        // PNG stores 16 bit samples in big endian order
+        let data = img
+            .as_bytes()
+            .chunks_exact(2)
+            .flat_map(|bytes| u16::from_ne_bytes([bytes[0], bytes[1]]).to_be_bytes())
+            .collect::<Vec<_>>();
+        writer.write_image_data(&data)?;
+    } else {
+        writer.write_image_data(img.as_bytes())?;
+    }
+    writer.finish()?;
+
+    Ok(buff)
+}
+
+/// Writes an encoded image to a file, or to stdout if the filename is `-`.
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+fn write_output(img_filename: &Path, buff: &[u8]) -> Result<(), Box<dyn Error>> {
+    use std::{ffi, fs, io};
+
+    // Choose output
+    // Write to stdout if user did not specify a file
+    let mut output: Box<dyn io::Write> = match &img_filename {
+        os if os == ffi::OsStr::new("-") => Box::new(io::stdout()),
+        out => Box::new(fs::File::create(out)?),
+    };
+
+    output.write_all(buff)?;
+    output.flush()?;
+
+    Ok(())
+}
+
+#[cfg(feature = "image")]
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::borrow_interior_mutable_const)]
+
+    use std::{cell::LazyCell, env, fs, io::ErrorKind};
+
+    use config::Config;
+
+    use glam::DVec3;
+
+    use super::*;
+
+    #[allow(clippy::declare_interior_mutable_const)]
+    const CONFIG: LazyCell<Config> = LazyCell::new(Config::default);
+
+    /// Largest difference of a channel from the golden image, to absorb differences between GPU
+    /// drivers.
+    const GOLDEN_TOLERANCE: u8 = 16;
+    /// Share of the pixels allowed to differ by more than the tolerance, as edges are rasterized
+    /// slightly differently by each driver.
+    const GOLDEN_MAX_MISMATCH: f64 = 0.01;
+
+    /// Renders `model` with the default options and compares it with `test/golden/<name>.png`.
+    ///
+    /// With `UPDATE_GOLDENS` set the render replaces the golden image instead. On a mismatch
+    /// the render is saved as `<name>.actual.png` next to the golden image, along with
+    /// `<name>.diff.png` marking the differing pixels in red.
+    async fn assert_golden(name: &str, model: &str) {
+        let opts = RenderOptions {
+            width: 128,
+            height: 128,
+            ..Default::default()
+        };
+        let actual = render_to_image(Path::new(model), &opts)
+            .await
+            .expect("Error in render function")
+            .into_rgba8();
+
+        let dir = Path::new("test/golden");
+        let golden_path = dir.join(format!("{name}.png"));
+        if env::var_os("UPDATE_GOLDENS").is_some() {
+            actual
+                .save(&golden_path)
+                .expect("Couldn't save golden image");
+            return;
+        }
+        let golden = image::open(&golden_path)
+            .unwrap_or_else(|e| {
+                panic!(
+                    "Couldn't open {}, run with UPDATE_GOLDENS=1 to create it: {e}",
+                    golden_path.display()
+                )
+            })
+            .into_rgba8();
+        assert_eq!(actual.dimensions(), golden.dimensions());
+
+        let mut mismatched = 0_u32;
+        // The golden image faded out, with the differing pixels in red
+        let diff = RgbaImage::from_fn(golden.width(), golden.height(), |x, y| {
+            let (a, g) = (actual.get_pixel(x, y), golden.get_pixel(x, y));
+            if a.0
+                .iter()
+                .zip(g.0)
+                .any(|(&a, g)| a.abs_diff(g) > GOLDEN_TOLERANCE)
+            {
+                mismatched += 1;
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([g[0] / 4, g[1] / 4, g[2] / 4, g[3].max(64)])
+            }
+        });
+
+        let share = f64::from(mismatched) / f64::from(golden.width() * golden.height());
+        if share > GOLDEN_MAX_MISMATCH {
+            actual
+                .save(dir.join(format!("{name}.actual.png")))
+                .expect("Couldn't save render");
+            diff.save(dir.join(format!("{name}.diff.png")))
+                .expect("Couldn't save diff image");
+            panic!(
+                "{mismatched} pixels differ from {}, see {name}.diff.png",
+                golden_path.display()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn cube_stl() {
+        let img_filename = Path::new("cube-stl.png");
+        match fs::remove_file(img_filename) {
+            Err(ref error) if error.kind() == ErrorKind::NotFound => (),
+            r => r.expect("Couldn't clean files before testing"),
+        }
+
+        render_to_file(
+            Path::new("test/data/cube.stl"),
+            img_filename,
+            ImageFormat::Png,
+            &(&*CONFIG).into(),
+        )
+        .await
+        .expect("Error in render function");
+
+        let size = fs::metadata(img_filename).expect("No file created").len();
+
+        assert_ne!(0, size);
+    }
+
+    #[tokio::test]
+    async fn embed_model_size() {
+        let img_filename = Path::new("cube-size.png");
+        let result = render_to_file(
+            Path::new("test/data/cube.stl"),
+            img_filename,
+            ImageFormat::Png,
+            &RenderOptions {
+                width: 64,
+                height: 64,
+                embed_model_size: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Error in render function");
+
+        // The cube is 20 x 20 x 10 in the file, it's only scaled to fit when rendering
+        assert_eq!(result.mesh.bounds.size(), Vec3::new(20.0, 20.0, 10.0));
+        let size = || {
+            let file = fs::File::open(img_filename).expect("No file created");
+            let reader = png::Decoder::new(std::io::BufReader::new(file))
+                .read_info()
+                .expect("Invalid PNG");
+            reader
+                .info()
+                .uncompressed_latin1_text
+                .iter()
+                .find(|chunk| chunk.keyword == "Model::Size")
+                .map(|chunk| chunk.text.clone())
+        };
+        assert_eq!(size().as_deref(), Some("20 x 20 x 10"));
+
+        // Cached images keep the size, whether they are rendered or served from the cache
+        let cache_dir = Path::new("test-cache-size");
+        match fs::remove_dir_all(cache_dir) {
+            Err(error) if error.kind() == ErrorKind::NotFound => (),
+            r => r.expect("Couldn't clean files before testing"),
+        }
+        let cache = ThumbnailCache::new(cache_dir).expect("Couldn't create cache");
+        for _ in 0..2 {
+            fs::remove_file(img_filename).expect("Couldn't clean up image file");
+            render_to_file_cached(
+                &cache,
+                Path::new("test/data/cube.stl"),
+                img_filename,
+                ImageFormat::Png,
+                &RenderOptions {
+                    width: 64,
+                    height: 64,
+                    embed_model_size: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("Error in render function");
+            assert_eq!(size().as_deref(), Some("20 x 20 x 10"));
+        }
+        assert_eq!(cache.hits(), 1);
+
+        // The pixels are the same as without the size, also for 16 bit images
+        for bit_depth in [BitDepth::Eight, BitDepth::Sixteen] {
+            let opts = RenderOptions {
+                width: 64,
+                height: 64,
+                bit_depth,
+                ..Default::default()
+            };
+            let expected = render_to_image(Path::new("test/data/cube.stl"), &opts)
+                .await
+                .expect("Error in render function");
+            render_to_file(
+                Path::new("test/data/cube.stl"),
+                img_filename,
+                ImageFormat::Png,
+                &RenderOptions {
+                    embed_model_size: true,
+                    ..opts
+                },
+            )
+            .await
+            .expect("Error in render function");
+            let img = image::open(img_filename).expect("Invalid PNG");
+            assert_eq!(expected, img);
+        }
+        fs::remove_file(img_filename).expect("Couldn't clean up image file");
+    }
+
+    #[tokio::test]
+    async fn cube_obj() {
+        let img_filename = Path::new("cube-obj.png");
+        match fs::remove_file(img_filename) {
+            Err(error) if error.kind() == ErrorKind::NotFound => (),
+            r => r.expect("Couldn't clean files before testing"),
+        }
+
+        render_to_file(
+            Path::new("test/data/cube.obj"),
+            img_filename,
+            ImageFormat::Png,
+            &(&*CONFIG).into(),
+        )
+        .await
+        .expect("Error in render function");
+
+        let size = fs::metadata(img_filename).expect("No file created").len();
+
+        assert_ne!(0, size);
+    }
+
+    #[tokio::test]
+    async fn cube_3mf() {
+        let img_filename = Path::new("cube-3mf.png");
+        match fs::remove_file(img_filename) {
+            Err(error) if error.kind() == ErrorKind::NotFound => (),
+            r => r.expect("Couldn't clean files before testing"),
+        }
+
+        render_to_file(
+            Path::new("test/data/cube.3mf"),
+            img_filename,
+            ImageFormat::Png,
+            &(&*CONFIG).into(),
+        )
+        .await
+        .expect("Error in render function");
+
+        let size = fs::metadata(img_filename).expect("No file created").len();
+
+        assert_ne!(0, size);
+    }
+
+    #[tokio::test]
+    async fn color_space() {
+        let model = Path::new("test/data/cube.stl");
+        let mut opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+
+        let srgb = render(model, &opts)
+            .await
+            .expect("Error in render function");
+        opts.color_space = ColorSpace::Linear;
+        let linear = render(model, &opts)
+            .await
+            .expect("Error in render function");
+
+        // The model covers the center of the image
+        let center = (32 * 64 + 32) * 4;
+        let srgb = &srgb[center..center + 4];
+        let linear = &linear[center..center + 4];
+
+        // sRGB encoding brightens midtones, so the linear values can only be darker
+        assert_eq!(srgb[3], linear[3]);
+        assert!(srgb[..3].iter().zip(&linear[..3]).all(|(s, l)| s >= l));
+        assert!(srgb[..3].iter().zip(&linear[..3]).any(|(s, l)| s > l));
+    }
+
+    #[tokio::test]
+    async fn premultiply_alpha() {
+        let buffer = render(
+            Path::new("test/data/cube.stl"),
+            &RenderOptions {
+                width: 64,
+                height: 64,
+                background: Background::Solid(glam::Vec4::new(1.0, 1.0, 1.0, 0.5)),
+                premultiply_alpha: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Error in render function");
+
+        assert!(buffer
+            .chunks_exact(4)
+            .all(|pixel| pixel[..3].iter().all(|&channel| channel <= pixel[3])));
+    }
+
+    #[tokio::test]
+    async fn rgb_output() {
+        let model = Path::new("test/data/cube.stl");
+        let mut opts = RenderOptions {
+            width: 64,
+            height: 64,
+            background: Background::Solid(glam::Vec4::new(1.0, 1.0, 1.0, 0.0)),
+            ..Default::default()
+        };
+
+        let rgba = render(model, &opts)
+            .await
+            .expect("Error in render function");
+        opts.output_format = PixelFormat::Rgb8;
+        let rgb = render(model, &opts)
+            .await
+            .expect("Error in render function");
+
+        assert_eq!(rgb.len(), 64 * 64 * 3);
+
+        for (rgba, rgb) in rgba.chunks_exact(4).zip(rgb.chunks_exact(3)) {
+            let alpha = f32::from(rgba[3]) / 255.0;
+            for (&straight, &composited) in rgba[..3].iter().zip(rgb) {
+                let expected = f32::from(straight).mul_add(alpha, 255.0 * (1.0 - alpha));
+                assert!((expected - f32::from(composited)).abs() <= 1.0);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn flatten_background() {
+        let model = Path::new("test/data/cube.stl");
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            flatten_background: Some(Vec3::ONE),
+            ..Default::default()
+        };
+
+        // The flatten color only applies to RGB output, RGBA output stays transparent
+        let rgba = render(model, &opts)
+            .await
+            .expect("Error in render function");
+        let rgb = render(
+            model,
+            &RenderOptions {
+                output_format: PixelFormat::Rgb8,
+                ..opts.clone()
+            },
+        )
+        .await
+        .expect("Error in render function");
+
+        for (x, y) in [(0, 0), (63, 0), (0, 63), (63, 63)] {
+            let i = y * 64 + x;
+            assert_eq!(rgba[i * 4 + 3], 0);
+            assert_eq!(rgb[i * 3..i * 3 + 3], [u8::MAX; 3]);
+        }
+        // The model itself isn't affected
+        let model_pixel = 32 * 64 + 32;
+        assert_eq!(rgba[model_pixel * 4 + 3], u8::MAX);
+        assert_eq!(
+            rgba[model_pixel * 4..model_pixel * 4 + 3],
+            rgb[model_pixel * 3..model_pixel * 3 + 3]
+        );
+    }
+
+    #[cfg(feature = "uri")]
+    #[tokio::test]
+    async fn save_freedesktop_thumbnail() {
+        let cache_dir = Path::new("test-thumbnails");
+        match fs::remove_dir_all(cache_dir) {
+            Err(error) if error.kind() == ErrorKind::NotFound => (),
+            r => r.expect("Couldn't clean files before testing"),
+        }
+        fs::create_dir(cache_dir).expect("Couldn't create cache directory");
+        let cache_dir = fs::canonicalize(cache_dir).expect("Error resolving path");
+        std::env::set_var("XDG_CACHE_HOME", &cache_dir);
+
+        let path = fs::canonicalize("test/data/cube.stl").expect("Couldn't find test model");
+        let uri = url::Url::from_file_path(&path).expect("Error building URI");
+        let saved = save_thumbnail_for_uri(
+            uri.as_str(),
+            ThumbnailSize::Normal,
+            &RenderOptions::default(),
+        )
+        .await
+        .expect("Error in render function");
+
+        assert!(saved.starts_with(&cache_dir));
+        // Only the thumbnail is left in the directory, no temporary file
+        let files = fs::read_dir(saved.parent().expect("No thumbnail directory"))
+            .expect("Couldn't list thumbnails")
+            .count();
+        assert_eq!(files, 1);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&saved)
+                .expect("No file created")
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        fs::remove_dir_all(cache_dir).expect("Couldn't clean up cache directory");
+    }
+
+    #[cfg(feature = "uri")]
+    #[tokio::test]
+    async fn freedesktop_thumbnail() {
+        let path = fs::canonicalize("test/data/cube.stl").expect("Couldn't find test model");
+        let uri = url::Url::from_file_path(&path).expect("Error building URI");
+        let buff = thumbnail_for_uri(
+            uri.as_str(),
+            ThumbnailSize::Normal,
+            &RenderOptions::default(),
+        )
+        .await
+        .expect("Error in render function");
+
+        let reader = png::Decoder::new(Cursor::new(buff))
+            .read_info()
+            .expect("Invalid PNG");
+        let info = reader.info();
+        assert_eq!((info.width, info.height), (128, 128));
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+        let text = |keyword| {
+            info.uncompressed_latin1_text
+                .iter()
+                .find(|chunk| chunk.keyword == keyword)
+                .map(|chunk| chunk.text.clone())
+        };
+        let metadata = fs::metadata(&path).expect("Couldn't read test model");
+        let mtime = metadata
+            .modified()
+            .expect("No modification time")
+            .duration_since(std::time::UNIX_EPOCH)
+            .expect("Invalid modification time")
+            .as_secs();
+        assert_eq!(text("Thumb::URI").as_deref(), Some(uri.as_str()));
+        assert_eq!(text("Thumb::MTime"), Some(mtime.to_string()));
+        assert_eq!(text("Thumb::Size"), Some(metadata.len().to_string()));
+
+        // Example from the spec
+        if let Some(path) = thumbnail_path("file:///home/jens/photos/me.png", ThumbnailSize::Large)
+        {
+            assert!(path.ends_with("thumbnails/large/c6ee772d9e49320e97ec29a7eb5b1697.png"));
+        }
+    }
+
+    #[tokio::test]
+    async fn cached_render() {
+        let cache_dir = Path::new("test-cache");
+        match fs::remove_dir_all(cache_dir) {
+            Err(error) if error.kind() == ErrorKind::NotFound => (),
+            r => r.expect("Couldn't clean files before testing"),
+        }
+
+        let cache = ThumbnailCache::new(cache_dir).expect("Couldn't create cache");
+        let model = Path::new("test/data/cube.stl");
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+
+        for img_filename in ["cube-cached-1.png", "cube-cached-2.png"] {
+            render_to_file_cached(
+                &cache,
+                model,
+                Path::new(img_filename),
+                ImageFormat::Png,
+                &opts,
+            )
+            .await
+            .expect("Error in render function");
+        }
+
+        assert_eq!(cache.misses(), 1);
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(
+            fs::read("cube-cached-1.png").expect("No file created"),
+            fs::read("cube-cached-2.png").expect("No file created")
+        );
+
+        // The same file named by a URI is served from the cache too
+        #[cfg(feature = "uri")]
+        {
+            let path = fs::canonicalize(model).expect("Error resolving path");
+            let uri = url::Url::from_file_path(path).expect("Error building URI");
+            render_to_file_cached(
+                &cache,
+                Path::new(uri.as_str()),
+                Path::new("cube-cached-3.png"),
+                ImageFormat::Png,
+                &opts,
+            )
+            .await
+            .expect("Error in render function");
+            assert_eq!(cache.hits(), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn cached_render_materials() {
+        let model_dir = Path::new("test-cache-materials");
+        match fs::remove_dir_all(model_dir) {
+            Err(error) if error.kind() == ErrorKind::NotFound => (),
+            r => r.expect("Couldn't clean files before testing"),
+        }
+        fs::create_dir(model_dir).expect("Couldn't create model directory");
+        let model = model_dir.join("two_materials.obj");
+        let library = model_dir.join("two_materials.mtl");
+        fs::copy("test/data/two_materials.obj", &model).expect("Couldn't copy model");
+        fs::copy("test/data/two_materials.mtl", &library).expect("Couldn't copy materials");
+
+        let cache = ThumbnailCache::new(model_dir.join("cache")).expect("Couldn't create cache");
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+        let render = |img_filename: &'static str| {
+            let (cache, model, opts) = (&cache, &model, &opts);
+            async move {
+                render_to_file_cached(
+                    cache,
+                    model,
+                    &model_dir.join(img_filename),
+                    ImageFormat::Png,
+                    opts,
+                )
+                .await
+                .expect("Error in render function");
+                fs::read(model_dir.join(img_filename)).expect("No file created")
+            }
+        };
+
+        let red = render("red.png").await;
+        fs::write(&library, "newmtl red\nKd 0 1 0\n\nnewmtl blue\nKd 0 0 1\n")
+            .expect("Couldn't edit materials");
+        let green = render("green.png").await;
+
+        assert_eq!(cache.misses(), 2);
+        assert_ne!(red, green);
+    }
+
+    #[test]
+    fn cache_key() {
+        let cache = ThumbnailCache::new("test-cache-key").expect("Couldn't create cache");
+        let opts = RenderOptions::default();
+        let path = cache.path(b"model", ImageFormat::Png, &opts);
+
+        assert_eq!(cache.path(b"model", ImageFormat::Png, &opts), path);
+        // Options that can't change the image share the entry
+        let reused = RenderOptions {
+            reuse_buffers: !opts.reuse_buffers,
+            ..Default::default()
+        };
+        assert_eq!(cache.path(b"model", ImageFormat::Png, &reused), path);
+
+        let wider = RenderOptions {
+            width: opts.width + 1,
+            ..Default::default()
+        };
+        assert_ne!(cache.path(b"model", ImageFormat::Png, &wider), path);
+        assert_ne!(cache.path(b"other", ImageFormat::Png, &opts), path);
+        assert_ne!(cache.path(b"model", ImageFormat::Jpeg, &opts), path);
+    }
+
+    #[tokio::test]
+    async fn multi_size() {
+        for img_filename in ["cube-multi-128.png", "cube-multi-256.png"] {
+            match fs::remove_file(img_filename) {
+                Err(error) if error.kind() == ErrorKind::NotFound => (),
+                r => r.expect("Couldn't clean files before testing"),
+            }
+        }
+
+        render_multi_size_to_files(
+            Path::new("test/data/cube.stl"),
+            Path::new("cube-multi.png"),
+            &[(128, 128), (256, 256)],
+            ImageFormat::Png,
+            &(&*CONFIG).into(),
+        )
+        .await
+        .expect("Error in render function");
+
+        assert_eq!(
+            image::image_dimensions("cube-multi-128.png").expect("No file created"),
+            (128, 128)
+        );
+        assert_eq!(
+            image::image_dimensions("cube-multi-256.png").expect("No file created"),
+            (256, 256)
+        );
+    }
+
+    #[tokio::test]
+    async fn far_clip_plane() {
+        let model = Path::new("test/data/cube.stl");
+        let mut opts = RenderOptions {
+            width: 64,
+            height: 64,
+            cam_position: glam::Vec3::new(0.0, -2000.0, 0.0),
+            cam_fov_deg: 0.1,
+            ..Default::default()
+        };
+
+        // The model is beyond the default far plane
+        let buffer = render(model, &opts)
+            .await
+            .expect("Error in render function");
+        assert!(buffer.chunks_exact(4).all(|pixel| pixel[3] == 0));
+
+        opts.far = 4096.0;
+        let buffer = render(model, &opts)
+            .await
+            .expect("Error in render function");
+        assert_eq!(buffer[(32 * 64 + 32) * 4 + 3], 255);
+
+        opts.near = opts.far;
+        assert!(render(model, &opts).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn silhouette_mask() {
+        let model = Path::new("test/data/cube.stl");
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ambient_occlusion: true,
+            ..Default::default()
+        };
+
+        let shaded = render(model, &opts)
+            .await
+            .expect("Error in render function");
+        let mask = render_mask(
+            model,
+            &RenderOptions {
+                // Ignored, the mask is always drawn over a transparent background
+                background: Background::Solid(glam::Vec4::ONE),
+                ..opts
+            },
+        )
+        .await
+        .expect("Error in render function");
+
+        assert_eq!(mask.len(), 64 * 64);
+        assert!(mask.contains(&u8::MAX) && mask.contains(&0));
+        for (coverage, pixel) in mask.iter().zip(shaded.chunks_exact(4)) {
+            assert_eq!(*coverage, pixel[3]);
+        }
+    }
+
+    #[tokio::test]
+    async fn mask_ignores_overlays() {
+        let model = Path::new("test/data/cube.stl");
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+
+        let mask = render_mask(model, &opts)
+            .await
+            .expect("Error in render function");
+        for overlaid in [
+            RenderOptions {
+                grid: Some(GridOptions {
+                    spacing: 5.0,
+                    ..Default::default()
+                }),
+                ..opts.clone()
+            },
+            // Wide lines along the silhouette would stick out of the model
+            RenderOptions {
+                wireframe: true,
+                line_width: 4.0,
+                ..opts.clone()
+            },
+        ] {
+            let overlaid = render_mask(model, &overlaid)
+                .await
+                .expect("Error in render function");
+            assert_eq!(mask, overlaid);
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_fov() {
+        for cam_fov_deg in [200.0, 180.0, 0.0, -45.0, f32::NAN] {
+            let opts = RenderOptions {
+                width: 64,
+                height: 64,
+                cam_fov_deg,
+                ..Default::default()
+            };
+            let error = render(Path::new("test/data/cube.stl"), &opts)
+                .await
+                .expect_err("Invalid FOV should be rejected");
+            assert!(
+                matches!(error.downcast_ref(), Some(RenderError::InvalidFov(_))),
+                "{cam_fov_deg}: {error}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn auto_frame() {
+        let model = Path::new("test/data/cube.stl");
+        let mut opts = RenderOptions {
+            width: 64,
+            height: 64,
+            auto_frame: true,
+            ..Default::default()
+        };
+
+        let mut coverage = Vec::new();
+        for fov in [20.0, 60.0] {
+            opts.cam_fov_deg = fov;
+            let buffer = render(model, &opts)
+                .await
+                .expect("Error in render function");
+            #[allow(clippy::cast_precision_loss)]
+            coverage.push(
+                buffer.chunks_exact(4).filter(|pixel| pixel[3] > 0).count() as f32 / (64.0 * 64.0),
+            );
+        }
+
+        // Perspective distortion changes the silhouette slightly between FOVs
+        assert!((coverage[0] - coverage[1]).abs() < 0.1, "{coverage:?}");
+    }
+
+    #[tokio::test]
+    async fn non_square_framing() {
+        let model = Path::new("test/data/cube.stl");
+        for (width, height) in [(1920, 480), (448, 1920)] {
+            let opts = RenderOptions {
+                width,
+                height,
+                ..Default::default()
+            };
+            let buffer = render(model, &opts)
+                .await
+                .expect("Error in render function");
+
+            // The model is drawn, without touching the edges of the image
+            let (width, height) = (usize::from(width), usize::from(height));
+            let opaque = |x: usize, y: usize| buffer[(y * width + x) * 4 + 3] > 0;
+            assert!((0..width).any(|x| opaque(x, height / 2)));
+            for y in 0..height {
+                assert!(!opaque(0, y) && !opaque(width - 1, y), "{width}x{height}");
+            }
+            for x in 0..width {
+                assert!(!opaque(x, 0) && !opaque(x, height - 1), "{width}x{height}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn auto_frame_long_model() {
+        // A box twenty times longer along X than along the other axes, seen from the front
+        let vertices = (0..8)
+            .map(|i| {
+                Vec3::new(
+                    if i & 1 == 0 { -10.0 } else { 10.0 },
+                    if i & 2 == 0 { -0.5 } else { 0.5 },
+                    if i & 4 == 0 { -0.5 } else { 0.5 },
+                )
+            })
+            .collect();
+        let indices = vec![
+            0, 2, 1, 1, 2, 3, 4, 5, 6, 5, 7, 6, 0, 1, 4, 1, 5, 4, 2, 6, 3, 3, 6, 7, 0, 4, 2, 2, 4,
+            6, 1, 3, 5, 3, 7, 5,
+        ];
+        let mesh = Mesh::from_raw(vertices, indices, None).expect("Error building mesh");
+
+        for orthographic in [false, true] {
+            let opts = RenderOptions {
+                width: 256,
+                height: 256,
+                cam_position: Vec3::new(0.0, -3.0, 0.0),
+                auto_frame: true,
+                orthographic,
+                ..Default::default()
+            };
+            let buffer = render_mesh(&mesh, &opts)
+                .await
+                .expect("Error in render function");
+
+            // The model isn't clipped, yet spans most of the width of the image
+            let opaque = |x: usize, y: usize| buffer[(y * 256 + x) * 4 + 3] > 0;
+            for i in 0..256 {
+                assert!(!opaque(0, i) && !opaque(255, i), "{orthographic}");
+                assert!(!opaque(i, 0) && !opaque(i, 255), "{orthographic}");
+            }
+            let columns = (0..256).filter(|&x| (0..256).any(|y| opaque(x, y))).count();
+            assert!(columns > 230, "{orthographic}: {columns}");
+        }
+    }
+
+    #[tokio::test]
+    async fn render_cancelled() {
+        let model = Path::new("test/data/cube.stl");
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+
+        let error = render_cancellable(model, &opts, &AtomicBool::new(true))
+            .await
+            .expect_err("Render wasn't cancelled");
+        assert!(matches!(error.downcast_ref(), Some(RenderError::Cancelled)));
+
+        let buffer = render_cancellable(model, &opts, &AtomicBool::new(false))
+            .await
+            .expect("Error in render function");
+        assert_eq!(
+            buffer,
+            render(model, &opts)
+                .await
+                .expect("Error in render function")
+        );
+    }
+
+    #[tokio::test]
+    async fn auto_levels() {
+        let median_luminance = |auto_levels| async move {
+            let opts = RenderOptions {
+                width: 64,
+                height: 64,
+                // A dim light, with a dark model as a result
+                lights: vec![DirectionalLight {
+                    direction: Vec3::new(-1.0, 1.0, 1.0),
+                    color: Vec3::splat(0.1),
+                }],
+                specular_strength: 0.0,
+                auto_levels,
+                ..Default::default()
+            };
+            let buffer = render(Path::new("test/data/cube.stl"), &opts)
+                .await
+                .expect("Error in render function");
+            let mut luminances = buffer
+                .chunks_exact(4)
+                .filter(|p| p[3] == u8::MAX)
+                .map(|p| {
+                    Vec3::new(f32::from(p[0]), f32::from(p[1]), f32::from(p[2]))
+                        .dot(Vec3::new(0.2126, 0.7152, 0.0722))
+                })
+                .collect::<Vec<_>>();
+            luminances.sort_unstable_by(f32::total_cmp);
+            luminances[luminances.len() / 2]
+        };
+
+        let dark = median_luminance(false).await;
+        let leveled = median_luminance(true).await;
+        assert!(leveled > dark * 2.0, "{dark} -> {leveled}");
+    }
+
+    #[tokio::test]
+    async fn rim_light() {
+        let render = |rim_light| async move {
+            let opts = RenderOptions {
+                width: 128,
+                height: 128,
+                cam_position: Vec3::new(0.0, -3.0, 0.0),
+                // Only the ambient light, which is the same everywhere
+                lights: Vec::new(),
+                rim_light,
+                ..Default::default()
+            };
+            render(Path::new("test/data/sphere.stl"), &opts)
+                .await
+                .expect("Error in render function")
+        };
+        let brightness = |buffer: &[u8], x: usize| {
+            let pixel = &buffer[(64 * 128 + x) * 4..][..4];
+            pixel[..3].iter().map(|&c| u32::from(c)).sum::<u32>()
+        };
+        // Just inside the silhouette, along the middle row
+        let edge = |buffer: &[u8]| {
+            let left = (0..128)
+                .find(|&x| buffer[(64 * 128 + x) * 4 + 3] == u8::MAX)
+                .expect("Model not drawn");
+            brightness(buffer, left + 1)
+        };
+
+        let unlit = render(None).await;
+        assert_eq!(edge(&unlit), brightness(&unlit, 64));
+        let lit = render(Some(RimLight {
+            color: Vec3::ONE,
+            power: 3.0,
+        }))
+        .await;
+        assert!(edge(&lit) > brightness(&lit, 64) + 100);
+    }
+
+    #[tokio::test]
+    async fn preloaded_mesh() {
+        let opts = RenderOptions::default();
+        let mesh = load_mesh(Path::new("test/data/cube.stl"), &opts)
+            .await
+            .expect("Error loading mesh");
+
+        let sizes = [(32, 32), (64, 48), (128, 128)];
+        let buffers = render_mesh_multi_size(&mesh, &sizes, &opts)
+            .await
+            .expect("Error in render function");
+        for (buffer, (width, height)) in buffers.iter().zip(sizes) {
+            assert_eq!(buffer.len(), usize::from(width) * usize::from(height) * 4);
+        }
+
+        let front = RenderOptions {
+            width: 64,
+            height: 64,
+            cam_position: CameraPreset::Front.position(opts.cam_position),
+            ..Default::default()
+        };
+        let top = RenderOptions {
+            cam_position: CameraPreset::Top.position(opts.cam_position),
+            ..front.clone()
+        };
+        let front_buffer = render_mesh(&mesh, &front)
+            .await
+            .expect("Error in render function");
+        let top_buffer = render_mesh(&mesh, &top)
+            .await
+            .expect("Error in render function");
+        assert_ne!(front_buffer, top_buffer);
+        // Same as loading the model again
+        assert_eq!(
+            front_buffer,
+            render(Path::new("test/data/cube.stl"), &front)
+                .await
+                .expect("Error in render function")
+        );
+    }
+
+    #[tokio::test]
+    async fn depth_test_disabled() {
+        // A red square in front of a green one, the nearer one first
+        let square = |y: f32| {
+            [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|(x, z)| Vec3::new(x, y, z))
+        };
+        let vertices = [square(-0.5), square(0.5)].concat();
+        let indices = vec![0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7];
+        let mut mesh = Mesh::from_raw(vertices, indices, None).expect("Error building mesh");
+        mesh.colors = [[Vec3::X; 4], [Vec3::Y; 4]].concat();
+
+        let center = |depth_test, depth_write| {
+            let mesh = &mesh;
+            async move {
+                let opts = RenderOptions {
+                    width: 64,
+                    height: 64,
+                    cam_position: Vec3::new(0.0, -3.0, 0.0),
+                    cull_mode: CullMode::None,
+                    depth_test,
+                    depth_write,
+                    ..Default::default()
+                };
+                let buffer = render_mesh(mesh, &opts)
+                    .await
+                    .expect("Error in render function");
+                let pixel = &buffer[(32 * 64 + 32) * 4..][..4];
+                (pixel[0], pixel[1])
+            }
+        };
+
+        // The nearer square hides the other one with depth testing
+        let (red, green) = center(true, true).await;
+        assert!(red > green, "{red} {green}");
+        // Otherwise the square drawn last covers the other one
+        let (red, green) = center(false, true).await;
+        assert!(green > red, "{red} {green}");
+        let (red, green) = center(true, false).await;
+        assert!(green > red, "{red} {green}");
+    }
+
+    #[tokio::test]
+    async fn highlight_backfaces() {
+        let mut mesh = load_mesh(Path::new("test/data/cube.stl"), &RenderOptions::default())
+            .await
+            .expect("Error loading mesh");
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            highlight_backfaces: Some(Vec3::new(1.0, 0.0, 1.0)),
+            ..Default::default()
+        };
+        let magenta = |buffer: &[u8]| {
+            buffer
+                .chunks_exact(4)
+                .filter(|p| p[..4] == [u8::MAX, 0, u8::MAX, u8::MAX])
+                .count()
+        };
+
+        let buffer = render_mesh(&mesh, &opts)
+            .await
+            .expect("Error in render function");
+        assert_eq!(magenta(&buffer), 0);
+
+        // Turn the cube inside out, its visible faces are now back faces
+        for triangle in mesh.indices.chunks_exact_mut(3) {
+            triangle.swap(1, 2);
+        }
+        for normal in &mut mesh.normals {
+            *normal = -*normal;
+        }
+        let buffer = render_mesh(&mesh, &opts)
+            .await
+            .expect("Error in render function");
+        assert_eq!(
+            &buffer[(32 * 64 + 32) * 4..][..4],
+            [u8::MAX, 0, u8::MAX, u8::MAX]
+        );
+        assert!(magenta(&buffer) > 64 * 64 / 8);
+    }
+
+    #[tokio::test]
+    async fn obj_vertex_colors() {
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            cam_position: Vec3::new(0.0, -3.0, 0.0),
+            cull_mode: CullMode::None,
+            ..Default::default()
+        };
+        let buffer = render(Path::new("test/data/vertex_colors.obj"), &opts)
+            .await
+            .expect("Error in render function");
+
+        // The square fades from red on the left to blue on the right
+        let pixel = |x: usize| &buffer[(32 * 64 + x) * 4..][..3];
+        let (left, middle, right) = (pixel(8), pixel(32), pixel(56));
+        assert!(left[0] > left[2], "{left:?}");
+        assert!(right[2] > right[0], "{right:?}");
+        assert!(
+            left[0] > middle[0] && middle[0] > right[0],
+            "{left:?} {middle:?} {right:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn grid() {
+        let render = |grid| async move {
+            let opts = RenderOptions {
+                width: 64,
+                height: 64,
+                grid,
+                ..Default::default()
+            };
+            render(Path::new("test/data/cube.stl"), &opts)
+                .await
+                .expect("Error in render function")
+        };
+        let rows = |buffer: &[u8], pixel: fn(&[u8]) -> bool| {
+            buffer
+                .chunks_exact(64 * 4)
+                .enumerate()
+                .filter(|(_, row)| row.chunks_exact(4).any(pixel))
+                .map(|(y, _)| y)
+                .collect::<Vec<_>>()
+        };
+        let green = |p: &[u8]| p[3] > 0 && p[1] > p[0].saturating_add(64) && p[1] > p[2];
+
+        let without = render(None).await;
+        assert!(rows(&without, green).is_empty());
+        // Lowest row of the model
+        let bottom = *rows(&without, |p| p[3] > 0)
+            .last()
+            .expect("The model isn't rendered");
+
+        let with = render(Some(GridOptions {
+            spacing: 5.0,
+            color: Vec3::Y,
+            ..Default::default()
+        }))
+        .await;
+        let grid_rows = rows(&with, green);
+        assert!(
+            grid_rows.iter().any(|&y| y > bottom),
+            "{grid_rows:?} {bottom}"
+        );
+    }
+
+    #[tokio::test]
+    async fn coverage() {
+        let img_filename = Path::new("coverage.png");
+        let render = |opts| async move {
+            render_to_file(
+                Path::new("test/data/cube.stl"),
+                img_filename,
+                ImageFormat::Png,
+                &opts,
+            )
+            .await
+            .expect("Error in render function")
+            .coverage
+        };
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+
+        let coverage = render(opts.clone()).await;
+        assert!((0.2..=0.7).contains(&coverage), "{coverage}");
+        // Read back the same way from half-float textures
+        let float = render(RenderOptions {
+            bit_depth: BitDepth::Sixteen,
+            ..opts.clone()
+        })
+        .await;
+        assert!((float - coverage).abs() < 0.01, "{float} {coverage}");
+        // Opaque backgrounds cover the whole frame
+        let opaque = render(RenderOptions {
+            background: Background::Solid(glam::Vec4::ONE),
+            ..opts
+        })
+        .await;
+        assert!((opaque - 1.0).abs() < f32::EPSILON, "{opaque}");
+    }
+
+    #[tokio::test]
+    async fn far_from_origin() {
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+        let near = load_mesh(Path::new("test/data/cube.stl"), &opts)
+            .await
+            .expect("Error loading mesh");
+        let far = load_mesh(Path::new("test/data/cube_far.stl"), &opts)
+            .await
+            .expect("Error loading mesh");
+        // The same cube, offset by a million on each axis, is moved next to the origin and still
+        // reports the coordinates of the file
+        assert_eq!(far.origin, near.bounds.center().as_dvec3() + 1e6);
+        assert_eq!(far.info().bounds.min, near.bounds.min + 1e6);
+        assert_eq!(far.info().bounds.max, near.bounds.max + 1e6);
+
+        let near = render_mesh(&near, &opts)
+            .await
+            .expect("Error in render function");
+        let far = render_mesh(&far, &opts)
+            .await
+            .expect("Error in render function");
+        assert!(near == far, "The far cube renders differently");
+    }
+
+    #[tokio::test]
+    async fn gradient_background() {
+        let buffer = render(
+            Path::new("test/data/cube.stl"),
+            &RenderOptions {
+                width: 64,
+                height: 64,
+                background: Background::VerticalGradient {
+                    top: glam::Vec4::new(1.0, 0.0, 0.0, 1.0),
+                    bottom: glam::Vec4::new(0.0, 0.0, 1.0, 1.0),
+                },
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Error in render function");
+
+        // The model doesn't cover the corners of the image
+        let top = &buffer[..4];
+        let bottom = &buffer[(63 * 64) * 4..(63 * 64 + 1) * 4];
+
+        assert!(top[0] > top[2], "{top:?}");
+        assert!(bottom[2] > bottom[0], "{bottom:?}");
+        assert_eq!((top[3], bottom[3]), (255, 255));
+    }
+
+    #[tokio::test]
+    async fn specular_highlights() {
+        let model = Path::new("test/data/cube.stl");
+        let mut opts = RenderOptions {
+            width: 64,
+            height: 64,
+            shininess: 8.0,
+            ..Default::default()
+        };
+
+        let brightest = |buffer: Vec<u8>| {
+            buffer
+                .chunks_exact(4)
+                .map(|pixel| pixel[..3].iter().map(|&c| u32::from(c)).sum::<u32>())
+                .max()
+                .unwrap_or_default()
+        };
+
+        let glossy = brightest(
+            render(model, &opts)
+                .await
+                .expect("Error in render function"),
+        );
+        opts.specular_strength = 0.0;
+        let matte = brightest(
+            render(model, &opts)
+                .await
+                .expect("Error in render function"),
+        );
+
+        assert!(glossy > matte, "glossy: {glossy}, matte: {matte}");
+    }
+
+    #[tokio::test]
+    async fn multiple_lights() {
+        let model = Path::new("test/data/cube.stl");
+        let mut opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+
+        let one_light = render(model, &opts)
+            .await
+            .expect("Error in render function");
+        opts.lights = DirectionalLight::three_point();
+        let three_lights = render(model, &opts)
+            .await
+            .expect("Error in render function");
+
+        assert_ne!(one_light, three_lights);
+    }
+
+    #[tokio::test]
+    async fn threemf_colors() {
+        let buffer = render(
+            Path::new("test/data/two_colors.3mf"),
+            &RenderOptions {
+                width: 64,
+                height: 64,
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Error in render function");
+
+        let pixels = buffer.chunks_exact(4).collect::<Vec<_>>();
+        let dominant = |c: usize| {
+            pixels.iter().any(|p| {
+                p[3] == 255 && (0..3).all(|other| other == c || p[c] > p[other].saturating_mul(2))
+            })
+        };
+        assert!(dominant(0), "red object not rendered");
+        assert!(dominant(1), "green object not rendered");
+    }
+
+    #[tokio::test]
+    async fn multi_size_cancel() {
+        let img_filename = Path::new("cube-cancel.png");
+        let sizes = [(64, 64), (128, 128), (192, 192)];
+        for &(width, height) in &sizes {
+            match fs::remove_file(sized_filename(img_filename, width, height)) {
+                Err(ref error) if error.kind() == ErrorKind::NotFound => (),
+                r => r.expect("Couldn't clean files before testing"),
+            }
+        }
+
+        let mut reports = Vec::new();
+        render_multi_size_to_files_with_progress(
+            Path::new("test/data/cube.stl"),
+            img_filename,
+            &sizes,
+            ImageFormat::Png,
+            &(&*CONFIG).into(),
+            |progress| {
+                reports.push(progress);
+                ControlFlow::Break(())
+            },
+        )
+        .await
+        .expect("Error in render function");
+
+        assert_eq!(
+            reports,
+            [Progress {
+                completed: 1,
+                total: 3
+            }]
+        );
+        assert!(sized_filename(img_filename, 64, 64).exists());
+        assert!(!sized_filename(img_filename, 128, 128).exists());
+        assert!(!sized_filename(img_filename, 192, 192).exists());
+    }
+
+    #[tokio::test]
+    async fn point_cloud() {
+        let model = Path::new("test/data/points.obj");
+        let mesh = Mesh::load(
+            model.to_str().unwrap(),
+            None,
+            StlMode::Auto,
+            &ObjectSelector::All,
+            false,
+        )
+        .expect("Error loading mesh");
+        assert_eq!(mesh.vertices.len(), 14);
+        assert!(mesh.indices.is_empty());
+
+        let buffer = render(
+            model,
+            &RenderOptions {
+                width: 64,
+                height: 64,
+                point_size: 4.0,
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Error in render function");
+
+        assert!(buffer.chunks_exact(4).any(|p| p[3] == 255));
+    }
+
+    #[tokio::test]
+    async fn unsupported_sample_count() {
+        let error = render(
+            Path::new("test/data/cube.stl"),
+            &RenderOptions {
+                width: 64,
+                height: 64,
+                sample_count: 3,
+                ..Default::default()
+            },
+        )
+        .await
+        .expect_err("Sample count 3 should be rejected");
+
+        assert!(matches!(
+            error.downcast_ref::<RenderError>(),
+            Some(RenderError::UnsupportedSampleCount { requested: 3, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn auto_up() {
+        let mask = |model, auto_up| async move {
+            let opts = RenderOptions {
+                width: 64,
+                height: 64,
+                auto_up,
+                ..Default::default()
+            };
+            render_mask(Path::new(model), &opts)
+                .await
+                .expect("Error in render function")
+        };
+        // Pixels covered in only one of the masks
+        let mismatch = |a: &[u8], b: &[u8]| {
+            a.iter()
+                .zip(b)
+                .filter(|&(&a, &b)| (a > 127) != (b > 127))
+                .count()
+        };
+
+        // The same boat, exported Y-up to OBJ
+        let stl = mask("test/data/3DBenchy.stl", true).await;
+        let obj = mask("test/data/3DBenchy.obj", true).await;
+        let lying = mask("test/data/3DBenchy.obj", false).await;
+
+        assert_eq!(stl, mask("test/data/3DBenchy.stl", false).await);
+        let upright = mismatch(&stl, &obj);
+        assert!(upright < 64 * 64 / 100, "{upright} pixels differ");
+        assert!(mismatch(&stl, &lying) > upright * 10);
+    }
+
+    #[tokio::test]
+    async fn model_rotation() {
+        // Width and height of the bounding box of the opaque pixels
+        let silhouette = |buffer: Vec<u8>| {
+            let (mut min, mut max) = ((usize::MAX, usize::MAX), (0, 0));
+            for (i, _) in buffer.chunks_exact(4).enumerate().filter(|(_, p)| p[3] > 0) {
+                let (x, y) = (i % 64, i / 64);
+                min = (min.0.min(x), min.1.min(y));
+                max = (max.0.max(x), max.1.max(y));
+            }
+            (max.0 - min.0, max.1 - min.1)
+        };
+
+        // The cube model is a 20 x 20 x 10 box, seen from the front
+        let model = Path::new("test/data/cube.stl");
+        let mut opts = RenderOptions {
+            width: 64,
+            height: 64,
+            cam_position: glam::Vec3::new(0.0, -4.0, 0.0),
+            ..Default::default()
+        };
+        let (width, height) = silhouette(
+            render(model, &opts)
+                .await
+                .expect("Error in render function"),
+        );
+        assert!(width > height);
+
+        opts.model_rotation = glam::Quat::from_rotation_y(90f32.to_radians());
+        let (width, height) = silhouette(
+            render(model, &opts)
+                .await
+                .expect("Error in render function"),
+        );
+        assert!(width < height);
+    }
+
+    #[tokio::test]
+    async fn sixteen_bit_png() {
+        let img_filename = Path::new("cube-16-bit.png");
+        match fs::remove_file(img_filename) {
+            Err(ref error) if error.kind() == ErrorKind::NotFound => (),
+            r => r.expect("Couldn't clean files before testing"),
+        }
+
+        render_to_file(
+            Path::new("test/data/cube.stl"),
+            img_filename,
+            ImageFormat::Png,
+            &RenderOptions {
+                width: 64,
+                height: 64,
+                bit_depth: BitDepth::Sixteen,
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Error in render function");
+
+        let img = image::open(img_filename).expect("Error decoding image");
+        assert_eq!(img.color(), image::ColorType::Rgba16);
+        assert!(img.to_rgba16().pixels().any(|p| p[3] == u16::MAX));
+    }
+
+    #[tokio::test]
+    async fn float_exr() {
+        let img_filename = Path::new("cube-float.exr");
+        match fs::remove_file(img_filename) {
+            Err(ref error) if error.kind() == ErrorKind::NotFound => (),
+            r => r.expect("Couldn't clean files before testing"),
+        }
+
+        render_to_file(
+            Path::new("test/data/cube.stl"),
+            img_filename,
+            ImageFormat::OpenExr,
+            &RenderOptions {
+                width: 64,
+                height: 64,
+                bit_depth: BitDepth::Float,
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Error in render function");
+
+        let img = image::open(img_filename).expect("Error decoding image");
+        assert_eq!((img.width(), img.height()), (64, 64));
+        assert_eq!(img.color(), image::ColorType::Rgba32F);
+        let img = img.to_rgba32f();
+        assert!(img.pixels().any(|p| p[3] >= 1.0));
+        // Shaded faces hold linear values between the 8-bit steps
+        assert!(img
+            .pixels()
+            .any(|p| p[0] > 0.0 && p[0] < 1.0 && (p[0] * 255.0).fract().abs() > 0.01));
+    }
+
+    #[tokio::test]
+    async fn hand_built_mesh() {
+        let vertices = vec![
+            Vec3::new(-1.0, 0.0, -1.0),
+            Vec3::new(1.0, 0.0, -1.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ];
+        let mesh = Mesh {
+            normals: vec![Vec3::NEG_Y; 3],
+            colors: vec![Vec3::ONE; 3],
+            indices: vec![0, 1, 2],
+            bounds: BoundingBox {
+                min: Vec3::new(-1.0, 0.0, -1.0),
+                max: Vec3::new(1.0, 0.0, 1.0),
+            },
+            vertices,
+            format: None,
+            normals_recomputed: false,
+            stl_mode: None,
+            stl_colors: false,
+            object_starts: vec![0],
+            origin: DVec3::ZERO,
+        };
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+
+        let img = render_mesh_to_image(&mesh, &opts)
+            .await
+            .expect("Error in render function")
+            .into_rgba8();
+
+        // The triangle faces the camera and covers the center of the image
+        assert_ne!(0, img.get_pixel(32, 32).0[3]);
+        assert_eq!(0, img.get_pixel(0, 0).0[3]);
+    }
+
+    #[tokio::test]
+    async fn zipped_model() {
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+        let zipped = render(Path::new("test/data/cube_obj.zip"), &opts)
+            .await
+            .expect("Error in render function");
+        let loose = render(Path::new("test/data/cube.obj"), &opts)
+            .await
+            .expect("Error in render function");
+        assert!(zipped == loose, "The zipped model renders differently");
+
+        // Archives without a model fail with a clear error
+        let mut archive = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        archive
+            .start_file("notes.txt", zip::write::SimpleFileOptions::default())
+            .expect("Error writing archive");
+        let archive = archive
+            .finish()
+            .expect("Error writing archive")
+            .into_inner();
+        let error = Mesh::from_zip(
+            Cursor::new(archive),
+            StlMode::Auto,
+            &ObjectSelector::All,
+            false,
+        )
+        .expect_err("Archive without a model should fail to load");
+        assert!(matches!(error, MeshError::NoModelInArchive));
+    }
+
+    #[tokio::test]
+    async fn single_point_mesh() {
+        let mesh = Mesh {
+            vertices: vec![Vec3::splat(5.0)],
+            normals: vec![Vec3::Z],
+            colors: vec![Vec3::ONE],
+            indices: Vec::new(),
+            bounds: BoundingBox {
+                min: Vec3::splat(5.0),
+                max: Vec3::splat(5.0),
+            },
+            format: None,
+            normals_recomputed: false,
+            stl_mode: None,
+            stl_colors: false,
+            object_starts: vec![0],
+            origin: DVec3::ZERO,
+        };
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            point_size: 8.0,
+            ..Default::default()
+        };
+
+        let img = render_mesh_to_image(&mesh, &opts)
+            .await
+            .expect("Error in render function")
+            .into_rgba8();
+
+        // The point has no size to fit in the frame, it is drawn at the center
+        assert_ne!(0, img.get_pixel(32, 32).0[3]);
+        assert_eq!(0, img.get_pixel(0, 0).0[3]);
+    }
+
+    #[tokio::test]
+    async fn mirrored_mesh() {
+        // A triangle facing the camera, symmetric along the x axis
+        let mesh = Mesh::from_raw(
+            vec![
+                Vec3::new(-1.0, 0.0, -1.0),
+                Vec3::new(1.0, 0.0, -1.0),
+                Vec3::new(0.0, 0.0, 1.0),
+            ],
+            vec![0, 1, 2],
+            None,
+        )
+        .expect("Error building mesh");
+        let render = |mirror| {
+            let mesh = &mesh;
+            async move {
+                let opts = RenderOptions {
+                    width: 64,
+                    height: 64,
+                    mirror,
+                    ..Default::default()
+                };
+                let buffer = render_mesh(mesh, &opts)
+                    .await
+                    .expect("Error in render function");
+                buffer.chunks_exact(4).filter(|p| p[3] > 0).count()
+            }
+        };
+
+        // Mirroring reverses the winding, the triangle must not be culled
+        let plain = render(None).await;
+        assert_ne!(0, plain);
+        assert_eq!(plain, render(Some(Axis::X)).await);
+    }
+
+    #[tokio::test]
+    async fn culling_disabled() {
+        // A triangle facing away from the camera
+        let mesh = Mesh::from_raw(
+            vec![
+                Vec3::new(-1.0, 0.0, -1.0),
+                Vec3::new(0.0, 0.0, 1.0),
+                Vec3::new(1.0, 0.0, -1.0),
+            ],
+            vec![0, 1, 2],
+            None,
+        )
+        .expect("Error building mesh");
+        let render = |cull_mode| {
+            let mesh = &mesh;
+            async move {
+                let opts = RenderOptions {
+                    width: 64,
+                    height: 64,
+                    cull_mode,
+                    ..Default::default()
+                };
+                render_mesh_to_image(mesh, &opts)
+                    .await
+                    .expect("Error in render function")
+                    .into_rgba8()
+            }
+        };
+
+        assert_eq!(0, render(CullMode::Back).await.get_pixel(32, 32).0[3]);
+        assert_ne!(0, render(CullMode::None).await.get_pixel(32, 32).0[3]);
+    }
+
+    #[tokio::test]
+    async fn two_sided_lighting() {
+        // A triangle facing away from the camera
+        let mesh = Mesh::from_raw(
+            vec![
+                Vec3::new(-1.0, 0.0, -1.0),
+                Vec3::new(0.0, 0.0, 1.0),
+                Vec3::new(1.0, 0.0, -1.0),
+            ],
+            vec![0, 1, 2],
+            None,
+        )
+        .expect("Error building mesh");
+        let brightness = |two_sided| {
+            let mesh = &mesh;
+            async move {
+                let opts = RenderOptions {
+                    width: 64,
+                    height: 64,
+                    cull_mode: CullMode::None,
+                    two_sided,
+                    ..Default::default()
+                };
+                let img = render_mesh_to_image(mesh, &opts)
+                    .await
+                    .expect("Error in render function")
+                    .into_rgba8();
+                img.get_pixel(32, 32).0[..3]
+                    .iter()
+                    .map(|&c| u32::from(c))
+                    .sum::<u32>()
+            }
+        };
+
+        // Only the ambient light reaches the back of the triangle without two-sided lighting
+        assert!(brightness(true).await > brightness(false).await * 2);
+    }
+
+    #[tokio::test]
+    async fn multi_resolution_ico() {
+        let img_filename = Path::new("cube.ico");
+        match fs::remove_file(img_filename) {
+            Err(ref error) if error.kind() == ErrorKind::NotFound => (),
+            r => r.expect("Couldn't clean files before testing"),
+        }
+
+        render_to_ico(
+            Path::new("test/data/cube.stl"),
+            img_filename,
+            &[(16, 16), (32, 32), (64, 64)],
+            &RenderOptions::default(),
+        )
+        .await
+        .expect("Error in render function");
+
+        // The directory lists the width and height of each layer in its 16 byte entries
+        let ico = fs::read(img_filename).expect("No file created");
+        assert_eq!([0, 0, 1, 0, 3, 0], ico[..6]);
+        let sizes = ico[6..6 + 3 * 16]
+            .chunks_exact(16)
+            .map(|entry| (entry[0], entry[1]))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![(16, 16), (32, 32), (64, 64)], sizes);
+
+        // The largest layer is decoded
+        let img = image::open(img_filename).expect("Error decoding image");
+        assert_eq!((64, 64), (img.width(), img.height()));
+        assert!(img.to_rgba8().pixels().any(|p| p[3] == u8::MAX));
+    }
+
+    #[test]
+    fn blocking() {
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+
+        let buffer = render_blocking(Path::new("test/data/cube.stl"), &opts)
+            .expect("Error in render function");
+
+        assert_eq!(buffer.len(), 64 * 64 * 4);
+        assert!(buffer.chunks_exact(4).any(|p| p[3] > 0));
+    }
+
+    #[tokio::test]
+    async fn model_bytes() {
+        let model = fs::read("test/data/cube.stl").expect("Couldn't read test model");
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+
+        let from_bytes = render_bytes(&model, &opts)
+            .await
+            .expect("Error in render function");
+        let from_file = render(Path::new("test/data/cube.stl"), &opts)
+            .await
+            .expect("Error in render function");
+
+        assert_eq!(from_bytes, from_file);
+    }
+
+    #[tokio::test]
+    async fn model_bytes_base_dir() {
+        let model = fs::read("test/data/two_materials.obj").expect("Couldn't read test model");
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            input_format: Some(InputFormat::Obj),
+            ..Default::default()
+        };
+
+        // The materials are only found with a base directory
+        let from_file = render(Path::new("test/data/two_materials.obj"), &opts)
+            .await
+            .expect("Error in render function");
+        let without_dir = render_bytes(&model, &opts)
+            .await
+            .expect("Error in render function");
+        let with_dir = render_bytes(
+            &model,
+            &RenderOptions {
+                obj_base_dir: Some("test/data".into()),
+                ..opts.clone()
+            },
+        )
+        .await
+        .expect("Error in render function");
+
+        assert_eq!(with_dir, from_file);
+        assert_ne!(without_dir, from_file);
+    }
+
+    #[tokio::test]
+    async fn prewarm() {
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+        let mesh = load_mesh(Path::new("test/data/cube.stl"), &opts)
+            .await
+            .expect("Error loading mesh");
+        let cold = render_mesh(&mesh, &opts)
+            .await
+            .expect("Error in render function");
+
+        prewarm_gpu(&opts).await.expect("Error prewarming the GPU");
+        let warm = render_mesh(&mesh, &opts)
+            .await
+            .expect("Error in render function");
+        assert_eq!(cold, warm);
+
+        // The pipelines for the options are built, so invalid ones fail ahead of time
+        let error = prewarm_gpu(&RenderOptions {
+            custom_shader: Some("not wgsl".to_owned()),
+            ..opts
+        })
+        .await
+        .expect_err("Invalid shader should fail");
+        assert!(
+            matches!(
+                error.downcast_ref::<RenderError>(),
+                Some(RenderError::ShaderCompile(_))
+            ),
+            "{error}"
+        );
+    }
+
+    #[tokio::test]
+    async fn strict_normals() {
+        let model = b"v 0 0 0\nv 1 0 0\nv 0 1 0\nv 2 0 0\nf 1 2 3\nf 1 2 4\n";
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            input_format: Some(InputFormat::Obj),
+            ..Default::default()
+        };
+
+        let buffer = render_bytes(model, &opts)
+            .await
+            .expect("Error in render function");
+        assert!(buffer.chunks_exact(4).any(|p| p[3] > 0));
+
+        let strict = RenderOptions {
+            strict_normals: true,
+            ..opts
+        };
+        let error = render_bytes(model, &strict)
+            .await
+            .expect_err("Zero area triangle should be rejected");
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(MeshError::DegenerateNormal(1))
+        ));
+    }
+
+    #[tokio::test]
+    async fn golden_cube_stl() {
+        assert_golden("cube_stl", "test/data/cube.stl").await;
+    }
+
+    #[tokio::test]
+    async fn golden_cube_obj() {
+        assert_golden("cube_obj", "test/data/cube.obj").await;
+    }
+
+    #[tokio::test]
+    async fn golden_cube_3mf() {
+        assert_golden("cube_3mf", "test/data/cube.3mf").await;
+    }
+
+    #[tokio::test]
+    async fn ansi_preview() {
+        let text = render_to_ansi(
+            Path::new("test/data/cube.stl"),
+            20,
+            10,
+            false,
+            &RenderOptions::default(),
+        )
+        .await
+        .expect("Error in render function");
+
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 10);
+        assert!(lines.iter().all(|line| line.len() == 20));
+        assert_ne!(lines[5].as_bytes()[10], b' ');
+        assert_eq!(lines[0].trim(), "");
+
+        let colored = render_to_ansi(
+            Path::new("test/data/cube.stl"),
+            20,
+            10,
+            true,
+            &RenderOptions::default(),
+        )
+        .await
+        .expect("Error in render function");
+        assert!(colored.contains("\x1b[38;2;"));
+    }
+
+    #[tokio::test]
+    async fn auto_fix_winding() {
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+        let mut mesh = Mesh::load(
+            "test/data/cube.stl",
+            None,
+            StlMode::Auto,
+            &ObjectSelector::All,
+            false,
+        )
+        .expect("Error loading mesh");
+        let coverage = |buffer: Vec<u8>| buffer.chunks_exact(4).filter(|p| p[3] > 0).count();
+        let full = coverage(
+            render_mesh(&mesh, &opts)
+                .await
+                .expect("Error in render function"),
+        );
+
+        // Every other triangle inverted, so half of each side is culled
+        for face in mesh.indices.chunks_exact_mut(6) {
+            face.swap(1, 2);
+        }
+        let holes = coverage(
+            render_mesh(&mesh, &opts)
+                .await
+                .expect("Error in render function"),
+        );
+        mesh.fix_winding();
+        let fixed = coverage(
+            render_mesh(&mesh, &opts)
+                .await
+                .expect("Error in render function"),
+        );
+
+        assert!(holes < full, "{holes} >= {full}");
+        assert_eq!(fixed, full);
+    }
+
+    #[tokio::test]
+    async fn convex_hull() {
+        let model = Path::new("test/data/cube.stl");
+        let mut opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+        let coverage = |buffer: Vec<u8>| buffer.chunks_exact(4).filter(|p| p[3] > 0).count();
+
+        let full = coverage(
+            render(model, &opts)
+                .await
+                .expect("Error in render function"),
+        );
+        opts.convex_hull = true;
+        let hull = coverage(
+            render(model, &opts)
+                .await
+                .expect("Error in render function"),
+        );
+
+        // The cube is its own hull
+        assert!(full > 0);
+        assert_eq!(hull, full);
+    }
+
+    #[tokio::test]
+    async fn auto_color_objects() {
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            auto_color_objects: true,
+            ..Default::default()
+        };
+
+        let buffer = render(Path::new("test/data/two_cubes.obj"), &opts)
+            .await
+            .expect("Error in render function");
+        // The first object is reddish and the second bluish, whatever the shading
+        let opaque = buffer.chunks_exact(4).filter(|p| p[3] == 255);
+        let reddish = opaque.clone().filter(|p| p[0] > p[2].saturating_add(32));
+        let bluish = opaque.filter(|p| p[2] > p[0].saturating_add(32));
+        assert!(reddish.count() > 0 && bluish.count() > 0);
+    }
+
+    #[tokio::test]
+    async fn bounding_box_overlay() {
+        let render = |debug_overlay| async move {
+            let opts = RenderOptions {
+                width: 64,
+                height: 64,
+                debug_overlay,
+                ..Default::default()
+            };
+            render(Path::new("test/data/sphere.stl"), &opts)
+                .await
+                .expect("Error in render function")
+        };
+        let plain = render(DebugOverlay::None).await;
+        let overlay = render(DebugOverlay::BoundingBox).await;
+
+        // The corners of the box stick out of the sphere
+        let outside = plain
+            .chunks_exact(4)
+            .zip(overlay.chunks_exact(4))
+            .filter(|(plain, overlay)| plain[3] == 0 && overlay[3] > 0)
+            .count();
+        assert!(outside > 10, "{outside} overlay pixels outside the model");
+    }
+
+    #[tokio::test]
+    async fn line_width() {
+        let render = |line_width| async move {
+            let opts = RenderOptions {
+                width: 1024,
+                height: 1024,
+                debug_overlay: DebugOverlay::BoundingBox,
+                line_width,
+                ..Default::default()
+            };
+            render(Path::new("test/data/sphere.stl"), &opts)
+                .await
+                .expect("Error in render function")
+        };
+        // Width of the first line crossing the middle row from the left, counting the pixels
+        // covered at least halfway
+        let thickness = |buffer: &[u8]| {
+            buffer[512 * 1024 * 4..513 * 1024 * 4]
+                .chunks_exact(4)
+                .skip_while(|p| p[3] == 0)
+                .take_while(|p| p[3] > 0 && p[2] < 128)
+                .filter(|p| p[3] >= 128)
+                .count()
+        };
+
+        let thin = thickness(&render(1.0).await);
+        let thick = thickness(&render(4.0).await);
+        assert!((1..=2).contains(&thin), "{thin} pixels thick");
+        assert!((4..=5).contains(&thick), "{thick} pixels thick");
+    }
+
+    #[tokio::test]
+    async fn wireframe_depth_bias() {
+        let render = |wireframe, depth_bias| async move {
+            let opts = RenderOptions {
+                width: 128,
+                height: 128,
+                wireframe,
+                depth_bias,
+                ..Default::default()
+            };
+            render(Path::new("test/data/cube.stl"), &opts)
+                .await
+                .expect("Error in render function")
+        };
+        let edge_pixels = |buffer: &[u8]| {
+            buffer
+                .chunks_exact(4)
+                .filter(|p| p[3] == 255 && p[..3].iter().all(|&c| c < 48))
+                .count()
+        };
+        let bias = DepthBias {
+            constant: 4,
+            slope_scale: 1.0,
+            clamp: 0.0,
+        };
+
+        assert_eq!(edge_pixels(&render(false, bias).await), 0);
+        let unbiased = edge_pixels(&render(true, DepthBias::default()).await);
+        let biased = render(true, bias).await;
+        // The edges lie on the faces, which hide parts of them without the bias
+        assert!(edge_pixels(&biased) > 64);
+        assert!(edge_pixels(&biased) >= unbiased);
+        assert_eq!(biased, render(true, bias).await);
+    }
+
+    #[tokio::test]
+    async fn solid_with_edges() {
+        let render = |shading| async move {
+            let opts = RenderOptions {
+                width: 128,
+                height: 128,
+                shading,
+                ..Default::default()
+            };
+            render(Path::new("test/data/cube.stl"), &opts)
+                .await
+                .expect("Error in render function")
+        };
+        let count = |buffer: &[u8], filter: fn(&[u8]) -> bool| {
+            buffer
+                .chunks_exact(4)
+                .filter(|p| p[3] == 255 && filter(p))
+                .count()
+        };
+        let dark = |p: &[u8]| p[..3].iter().all(|&c| c < 48);
+        let shaded = |p: &[u8]| p[2] > 128;
+
+        let solid = render(Shading::Solid).await;
+        let edges = render(Shading::SolidWithEdges {
+            edge_color: Vec3::ZERO,
+            thickness: 2.0,
+        })
+        .await;
+        assert_eq!(count(&solid, dark), 0);
+        // The 9 visible edges of the cube are at least a few pixels long each
+        assert!(count(&edges, dark) > 9 * 20);
+        assert!(count(&edges, shaded) > count(&solid, shaded) / 2);
+    }
+
+    #[tokio::test]
+    async fn toon_shading() {
+        let render = |shading| async move {
+            let opts = RenderOptions {
+                width: 128,
+                height: 128,
+                sample_count: AntiAlias::Off.sample_count(),
+                shading,
+                ..Default::default()
+            };
+            render(Path::new("test/data/sphere.stl"), &opts)
+                .await
+                .expect("Error in render function")
+        };
+        // Distinct luminance levels of the pixels covered by the model
+        let levels = |buffer: &[u8]| {
+            buffer
+                .chunks_exact(4)
+                .filter(|p| p[3] == u8::MAX)
+                .map(|p| u32::from(p[0]) * 299 + u32::from(p[1]) * 587 + u32::from(p[2]) * 114)
+                .collect::<std::collections::HashSet<_>>()
+                .len()
+        };
+
+        let smooth = levels(&render(Shading::Solid).await);
+        let toon = levels(
+            &render(Shading::Toon {
+                bands: 3,
+                edge_color: None,
+                thickness: 1.0,
+            })
+            .await,
+        );
+        // Unlit, half lit and fully lit, with or without the highlight
+        assert!(toon <= 6, "{toon} levels");
+        assert!(smooth > 20, "{smooth} levels");
+    }
+
+    #[tokio::test]
+    async fn xray_shading() {
+        let cube = load_mesh(Path::new("test/data/cube.stl"), &RenderOptions::default())
+            .await
+            .expect("Error loading mesh");
+        // The cube with a smaller one inside, turned inside out, for a cavity
+        let center = cube.bounds.center();
+        let count = u32::try_from(cube.vertices.len()).expect("Too many vertices");
+        let mut vertices = cube.vertices.clone();
+        vertices.extend(cube.vertices.iter().map(|&v| center + (v - center) * 0.4));
+        let mut normals = cube.normals.clone();
+        normals.extend(cube.normals.iter().map(|&n| -n));
+        let mut indices = cube.indices.clone();
+        indices.extend(
+            cube.indices
+                .chunks_exact(3)
+                .flat_map(|t| [t[0], t[2], t[1]].map(|i| i + count)),
+        );
+        let hollow = Mesh::from_raw(vertices, indices, Some(normals)).expect("Error building mesh");
+
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            shading: Shading::XRay { opacity: 0.2 },
+            ..Default::default()
+        };
+        let brightness = |buffer: &[u8], x: usize, y: usize| {
+            buffer[(y * 64 + x) * 4..][..3]
+                .iter()
+                .map(|&c| u32::from(c))
+                .sum::<u32>()
+        };
+        let solid = render_mesh(&cube, &opts)
+            .await
+            .expect("Error in render function");
+        let hollow = render_mesh(&hollow, &opts)
+            .await
+            .expect("Error in render function");
+
+        // Seen through the middle, the walls of the cavity add to the front and back faces
+        assert!(
+            brightness(&hollow, 32, 32) > brightness(&solid, 32, 32) + 30,
+            "{} <= {}",
+            brightness(&hollow, 32, 32),
+            brightness(&solid, 32, 32)
+        );
+        // The back faces show through the front ones
+        let alpha = solid[(32 * 64 + 32) * 4 + 3];
+        assert!(alpha > 80 && alpha < u8::MAX, "{alpha}");
+    }
+
+    #[tokio::test]
+    async fn fxaa_smooths_edges() {
+        let render = |fxaa| async move {
+            let opts = RenderOptions {
+                width: 64,
+                height: 64,
+                sample_count: AntiAlias::Off.sample_count(),
+                fxaa,
+                ..Default::default()
+            };
+            render(Path::new("test/data/sphere.stl"), &opts)
+                .await
+                .expect("Error in render function")
+        };
+        // Without anti-aliasing every pixel is either covered by the model or not
+        let partial = |pixels: &[u8]| {
+            pixels
+                .chunks_exact(4)
+                .filter(|pixel| pixel[3] > 0 && pixel[3] < u8::MAX)
+                .count()
+        };
+
+        assert_eq!(partial(&render(false).await), 0);
+        let smoothed = partial(&render(true).await);
+        assert!(smoothed > 10, "{smoothed} partially covered pixels");
+    }
+
+    #[tokio::test]
+    async fn custom_shader() {
+        let flat = "
+            struct VertBindings {
+                perspective: mat4x4<f32>,
+                modelview: mat4x4<f32>,
+            }
+            @group(0) @binding(0) var<uniform> v_bindings: VertBindings;
+
+            @vertex
+            fn vert_main(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+                return v_bindings.perspective * v_bindings.modelview * vec4<f32>(position, 1.0);
+            }
+
+            @fragment
+            fn frag_main() -> @location(0) vec4<f32> {
+                return vec4<f32>(1.0, 0.0, 0.0, 1.0);
+            }
+        ";
+        let render = |custom_shader: &str| {
+            let opts = RenderOptions {
+                width: 64,
+                height: 64,
+                custom_shader: Some(custom_shader.to_owned()),
+                ..Default::default()
+            };
+            async move { render(Path::new("test/data/cube.stl"), &opts).await }
+        };
+
+        let buffer = render(flat).await.expect("Error in render function");
+        let covered = buffer
+            .chunks_exact(4)
+            .filter(|pixel| pixel[3] == u8::MAX)
+            .collect::<Vec<_>>();
+        assert!(covered.len() > 100);
+        assert!(covered.iter().all(|pixel| pixel[..3] == [0xff, 0, 0]));
+
+        for invalid in [
+            "not wgsl",
+            &flat.replace("frag_main", "fragment_main"),
+            &flat.replace("@location(0) position", "@location(7) position"),
+        ] {
+            let error = render(invalid)
+                .await
+                .expect_err("Invalid shader should fail");
+            assert!(
+                matches!(
+                    error.downcast_ref::<RenderError>(),
+                    Some(RenderError::ShaderCompile(_))
+                ),
+                "{error}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn unaligned_width() {
+        // 400 bytes per row, which the GPU pads to 512
+        let opts = RenderOptions {
+            width: 100,
+            height: 100,
+            ..Default::default()
+        };
+        let buffer = render(Path::new("test/data/sphere.stl"), &opts)
+            .await
+            .expect("Error in render function");
+        assert_eq!(buffer.len(), 100 * 100 * 4);
+
+        // The sphere is centered in every row, padding left in the rows would shift each one
+        // further than the last
+        let mut rows = 0;
+        for row in buffer.chunks_exact(100 * 4) {
+            let covered = row
+                .chunks_exact(4)
+                .enumerate()
+                .filter(|(_, pixel)| pixel[3] == u8::MAX)
+                .map(|(x, _)| x);
+            if let (Some(first), Some(last)) = (covered.clone().min(), covered.max()) {
+                assert!((first + last).abs_diff(99) <= 2, "{first}..={last}");
+                rows += 1;
+            }
+        }
+        assert!(rows > 20, "{rows} rows");
+    }
+
+    #[tokio::test]
+    async fn taa_smooths_edges() {
+        let render = |taa_samples| async move {
+            let opts = RenderOptions {
+                width: 64,
+                height: 64,
+                sample_count: AntiAlias::Off.sample_count(),
+                taa_samples,
+                ..Default::default()
+            };
+            render(Path::new("test/data/sphere.stl"), &opts)
+                .await
+                .expect("Error in render function")
+        };
+        let partial = |pixels: &[u8]| {
+            pixels
+                .chunks_exact(4)
+                .filter(|pixel| pixel[3] > 0 && pixel[3] < u8::MAX)
+                .count()
+        };
+
+        let single = render(1).await;
+        assert_eq!(partial(&single), 0);
+        let smoothed = render(4).await;
+        assert!(
+            partial(&smoothed) > 10,
+            "{} partially covered pixels",
+            partial(&smoothed)
+        );
+        // The frames are centered on the same point, the silhouette doesn't move
+        let coverage = |pixels: &[u8]| pixels.chunks_exact(4).map(|p| u32::from(p[3])).sum::<u32>();
+        let (single, smoothed) = (coverage(&single), coverage(&smoothed));
+        assert!(
+            single.abs_diff(smoothed) < single / 50,
+            "{single} != {smoothed}"
+        );
+    }
+
+    #[tokio::test]
+    async fn depth_of_field() {
+        let render = |depth_of_field| async move {
+            let opts = RenderOptions {
+                width: 128,
+                height: 128,
+                depth_of_field,
+                ..Default::default()
+            };
+            render(Path::new("test/data/cube.stl"), &opts)
+                .await
+                .expect("Error in render function")
+        };
+        // Sum of the squared differences between neighbouring pixels, which blurring lowers.
+        // Colors are premultiplied as they don't matter where the image is transparent
+        let contrast = |pixels: &[u8]| -> u64 {
+            let premultiplied = pixels
+                .chunks_exact(4)
+                .flat_map(|p| p.iter().map(|&c| u64::from(c) * u64::from(p[3]) / 255))
+                .collect::<Vec<_>>();
+            let row = 128 * 4;
+            (0..premultiplied.len() - row - 4)
+                .map(|i| {
+                    premultiplied[i].abs_diff(premultiplied[i + 4]).pow(2)
+                        + premultiplied[i].abs_diff(premultiplied[i + row]).pow(2)
+                })
+                .sum()
+        };
+
+        let sharp = contrast(&render(None).await);
+        // The camera is about 4.9 units from the center of the model, which is all behind the
+        // focal plane
+        let blurred = contrast(
+            &render(Some(DofOptions {
+                focal_distance: 2.0,
+                aperture: 0.05,
+            }))
+            .await,
+        );
+        assert!(blurred < sharp / 2, "{blurred} >= {sharp} / 2");
+    }
+
+    #[tokio::test]
+    async fn solid_background_color() {
+        // Any corner of the image, which the model doesn't cover
+        for color_space in [ColorSpace::Srgb, ColorSpace::Linear] {
+            for bit_depth in [BitDepth::Eight, BitDepth::Sixteen] {
+                let color = [0x33_u8, 0x80, 0xc4, 0xff];
+                let buffer = render(
+                    Path::new("test/data/cube.stl"),
+                    &RenderOptions {
+                        width: 64,
+                        height: 64,
+                        background: Background::Solid(
+                            glam::Vec4::from_array(color.map(f32::from)) / 255.0,
+                        ),
+                        color_space,
+                        bit_depth,
+                        ..Default::default()
+                    },
+                )
+                .await
+                .expect("Error in render function");
+
+                let corner = if bit_depth == BitDepth::Eight {
+                    buffer[..4].to_vec()
+                } else {
+                    // Rounded to 8 bits, as the half-float target is less precise than 16 bits
+                    buffer[..8]
+                        .chunks_exact(2)
+                        .map(|c| {
+                            let channel = f32::from(u16::from_ne_bytes([c[0], c[1]])) / 257.0;
+                            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                            {
+                                channel.round() as u8
+                            }
+                        })
+                        .collect()
+                };
+                assert_eq!(corner, color, "{color_space:?}, {bit_depth:?}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn matte_background() {
+        let render = |matte_background| async move {
+            let opts = RenderOptions {
+                width: 64,
+                height: 64,
+                // Linear values so the blending can be checked without decoding them
+                color_space: ColorSpace::Linear,
+                matte_background,
+                ..Default::default()
+            };
+            render(Path::new("test/data/sphere.stl"), &opts)
+                .await
+                .expect("Error in render function")
+        };
+        let plain = render(None).await;
+        let matted = render(Some(Vec3::X)).await;
+
+        // Edges blend into the matte in proportion to how much of them is not covered
+        let mut edges = 0;
+        for (plain, matted) in plain.chunks_exact(4).zip(matted.chunks_exact(4)) {
+            assert_eq!(plain[3], matted[3]);
+            let expected = u32::from(plain[0]) + u32::from(u8::MAX - plain[3]);
+            assert!(
+                u32::from(matted[0]).abs_diff(expected.min(255)) <= 2,
+                "{plain:?} matted to {matted:?}"
+            );
+            if plain[3] > 0 && plain[3] < u8::MAX {
+                edges += 1;
+            }
+        }
+        assert!(edges > 10, "{edges} anti-aliased edge pixels");
+    }
+
+    #[tokio::test]
+    async fn game_isometric() {
+        // A cube with every face a square of its own vertices, colored red, green or blue by the
+        // axis it faces
+        let (mut vertices, mut normals, mut colors, mut indices) = (vec![], vec![], vec![], vec![]);
+        for axis in 0..3 {
+            for sign in [1.0, -1.0] {
+                let normal = Vec3::AXES[axis] * sign;
+                let (u, v) = (Vec3::AXES[(axis + 1) % 3], Vec3::AXES[(axis + 2) % 3]);
+                let start = u32::try_from(vertices.len()).expect("Too many vertices");
+                vertices.extend([
+                    normal - u - v,
+                    normal + u - v,
+                    normal + u + v,
+                    normal - u + v,
+                ]);
+                normals.extend([normal; 4]);
+                colors.extend([Vec3::AXES[axis]; 4]);
+                // Counterclockwise seen from outside the cube
+                indices.extend(if sign > 0.0 {
+                    [0, 1, 2, 0, 2, 3].map(|i| start + i)
+                } else {
+                    [0, 2, 1, 0, 3, 2].map(|i| start + i)
+                });
+            }
+        }
+        let mut mesh = Mesh::from_raw(vertices, indices, Some(normals)).expect("Invalid mesh");
+        mesh.colors = colors;
+
+        let view = CameraPreset::GameIsometric;
+        let opts = RenderOptions {
+            width: 256,
+            height: 256,
+            cam_position: view.position(RenderOptions::default().cam_position),
+            orthographic: view.orthographic(),
+            // Light from the camera, which shines on the three visible faces alike
+            lights: vec![DirectionalLight {
+                direction: Vec3::Z,
+                color: Vec3::ONE,
+            }],
+            specular_strength: 0.0,
+            ..Default::default()
+        };
+        let buffer = render_mesh(&mesh, &opts)
+            .await
+            .expect("Error in render function");
+
+        // The three visible faces cover the same area, whatever their shade
+        let mut areas = [0; 3];
+        for pixel in buffer.chunks_exact(4).filter(|p| p[3] == u8::MAX) {
+            let brightest = (0..3).max_by_key(|&c| pixel[c]).unwrap_or_default();
+            areas[brightest] += 1;
+        }
+        let (smallest, largest) = (areas.iter().min(), areas.iter().max());
+        assert!(
+            smallest
+                .zip(largest)
+                .is_some_and(|(s, l)| s * 100 >= l * 97),
+            "{areas:?} pixels per face"
+        );
+
+        // Without perspective, the far top corner is as far from the center of the image as the
+        // near bottom one
+        let rows = buffer
+            .chunks_exact(256 * 4)
+            .map(|row| row.chunks_exact(4).any(|p| p[3] > 0))
+            .collect::<Vec<_>>();
+        let above = rows[..128].iter().filter(|&&covered| covered).count();
+        let below = rows[128..].iter().filter(|&&covered| covered).count();
+        assert!(
+            above.abs_diff(below) <= 1,
+            "{above} rows above, {below} below"
+        );
+    }
+
+    #[tokio::test]
+    async fn views_sheet() {
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+        let views = [CameraPreset::Front, CameraPreset::Top, CameraPreset::Right];
+        let sheet = render_views_sheet(Path::new("test/data/cube.stl"), &views, 2, &opts)
+            .await
+            .expect("Error in render function");
+
+        // Two columns and two rows, the last cell is empty
+        assert_eq!(sheet.len(), 128 * 128 * 4);
+        let covered = |column: usize, row: usize| {
+            (0..64)
+                .flat_map(|y| (0..64).map(move |x| (x, y)))
+                .filter(|&(x, y)| sheet[((row * 64 + y) * 128 + column * 64 + x) * 4 + 3] > 0)
+                .count()
+        };
+        assert!(covered(0, 0) > 100);
+        assert!(covered(1, 0) > 100);
+        assert!(covered(0, 1) > 100);
+        assert_eq!(covered(1, 1), 0);
+
+        // Orthographic views don't change the size of the model with the distance, so its width
+        // is the same from the front and the top
+        let width = |column: usize| {
+            (0..64)
+                .filter(|x| sheet[(32 * 128 + column * 64 + x) * 4 + 3] == u8::MAX)
+                .count()
+        };
+        assert!(width(0) > 0);
+        assert_eq!(width(0), width(1));
+    }
+
+    #[tokio::test]
+    async fn contact_sheet() {
+        let opts = RenderOptions::default();
+        let files = ["cube.stl", "cube.obj", "cube.3mf", "cube_ascii.stl"]
+            .map(|name| Path::new("test/data").join(name));
+        let ContactSheet {
+            pixels: sheet,
+            failures,
+        } = render_contact_sheet(&files, 2, (64, 48), true, &opts)
+            .await
+            .expect("Error in render function");
+
+        assert!(failures.is_empty());
+        assert_eq!(sheet.len(), 128 * 96 * 4);
+        let pixel = |column: usize, row: usize, x: usize, y: usize| {
+            let start = ((row * 48 + y) * 128 + column * 64 + x) * 4;
+            &sheet[start..start + 4]
+        };
+        for (column, row) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            // The model above the label and the label itself
+            let model = (0..36)
+                .flat_map(|y| (0..64).map(move |x| (x, y)))
+                .filter(|&(x, y)| pixel(column, row, x, y)[3] > 0)
+                .count();
+            assert!(model > 100, "{model} model pixels in cell {column}, {row}");
+            assert!((41..46)
+                .flat_map(|y| (0..64).map(move |x| (x, y)))
+                .any(|(x, y)| pixel(column, row, x, y) == [64, 64, 64, u8::MAX]));
+        }
+
+        assert!(render_contact_sheet(&[], 2, (64, 48), true, &opts)
+            .await
+            .is_err());
+
+        // Files that fail to load leave their cell empty, with a red label, and cells too small
+        // for a label get none
+        let files =
+            ["cube.stl", "cube_solid_header.stl"].map(|name| Path::new("test/data").join(name));
+        for cell in [(64, 48), (8, 4)] {
+            let sheet = render_contact_sheet(&files, 2, cell, true, &opts)
+                .await
+                .expect("Error in render function");
+            assert_eq!(sheet.failures.len(), 1);
+            assert_eq!(sheet.failures[0].0, files[1]);
+            let failed = sheet
+                .pixels
+                .chunks_exact(4)
+                .enumerate()
+                .filter(|(i, _)| i % (2 * usize::from(cell.0)) >= usize::from(cell.0))
+                .map(|(_, pixel)| pixel)
+                .collect::<Vec<_>>();
+            assert_eq!(cell.1 > 4, failed.contains(&&FAILED_LABEL_COLOR[..]));
+            assert!(failed
+                .iter()
+                .all(|&pixel| pixel[3] == 0 || pixel == FAILED_LABEL_COLOR));
+        }
     }
 }
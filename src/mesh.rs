@@ -1,13 +1,28 @@
 use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{HashMap, HashSet, VecDeque},
     fs::File,
-    io::{self, BufReader, Cursor, Read, Seek},
-    path::Path,
+    io::{self, BufRead, BufReader, Read, Seek},
+    path::{Component, Path, PathBuf},
 };
+#[cfg(not(target_arch = "wasm32"))]
+use std::{io::Cursor, panic, thread};
 
-use glam::{Mat4, Vec3};
-use stl_io::{read_stl, Normal, Triangle, Vector};
+use glam::{DMat4, DVec3, Mat4, Quat, Vec3};
+use quick_xml::events::{BytesStart, Event};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use stl_io::{read_stl, IndexedMesh, Normal, Triangle, TriangleIterator, Vector};
+use tracing::warn;
+use zip::ZipArchive;
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::callback::CallbackFuture;
 use crate::error::MeshError;
+use crate::render::{Axis, CenterMode};
+
+mod hull;
 
 #[derive(Debug, Clone)]
 pub struct BoundingBox {
@@ -27,10 +42,16 @@ impl BoundingBox {
         self.max = self.max.max(v);
     }
 
+    #[must_use]
     pub fn center(&self) -> Vec3 {
         (self.min + self.max) * 0.5
     }
 
+    #[must_use]
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
     fn length(&self) -> f32 {
         self.max.x - self.min.x
     }
@@ -48,251 +69,3021 @@ impl BoundingBox {
 pub struct Mesh {
     pub vertices: Vec<Vec3>,
     pub normals: Vec<Vec3>,
+    /// Per-vertex colors, white when the file doesn't provide any
+    pub colors: Vec<Vec3>,
     pub indices: Vec<u32>,
     pub bounds: BoundingBox,
+    /// Format of the file the mesh was loaded from
+    pub format: Option<InputFormat>,
+    /// Whether the normals were computed from the geometry instead of read from the file
+    pub normals_recomputed: bool,
+    /// Encoding the mesh was read as, for STL files
+    pub stl_mode: Option<StlMode>,
+    /// Whether the binary STL file has triangle or default colors, which aren't rendered
+    pub stl_colors: bool,
+    /// Index of the first vertex of each object the mesh was combined from, in order
+    pub object_starts: Vec<usize>,
+    /// Offset subtracted from the vertices of models far from the origin when they are loaded, so
+    /// `f32` keeps their precision. Adding it back gives the coordinates of the file
+    pub origin: DVec3,
+}
+
+/// An edge between triangles of a mesh, see [`Mesh::edges`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Edge {
+    pub start: Vec3,
+    pub end: Vec3,
+    /// Normals of the triangles on either side, twice the same one for edges of a single triangle
+    pub normals: [Vec3; 2],
+    /// Whether the edge is a crease or a boundary, drawn from any view. Other edges are only
+    /// drawn on the silhouette
+    pub sharp: bool,
+}
+
+/// Statistics about a loaded mesh.
+#[derive(Debug, Clone)]
+pub struct MeshInfo {
+    pub format: Option<InputFormat>,
+    pub triangle_count: usize,
+    pub vertex_count: usize,
+    /// Vertices at distinct positions, the vertex count if the mesh was welded. STL files repeat
+    /// the vertices shared by triangles, so it is much lower than `vertex_count` for them
+    pub unique_vertex_count: usize,
+    /// Bounds in model units, before the model is scaled to fit the image
+    pub bounds: BoundingBox,
+    pub normals_recomputed: bool,
+    pub stl_mode: Option<StlMode>,
+    /// Whether the binary STL file has triangle or default colors
+    pub stl_colors: bool,
+}
+
+/// Encoding of a STL file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StlMode {
+    /// Detected from the file header, files whose first line starts with `solid ` are ASCII.
+    #[default]
+    Auto,
+    /// Text STL.
+    Ascii,
+    /// Binary STL, even if the header starts with `solid `.
+    Binary,
+}
+
+impl std::fmt::Display for StlMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Auto => "auto",
+            Self::Ascii => "ascii",
+            Self::Binary => "binary",
+        })
+    }
+}
+
+impl StlMode {
+    /// Detects the encoding the same way `stl_io` does, leaving the reader at the start.
+    fn detect<R>(model_file: &mut R) -> io::Result<Self>
+    where
+        R: Read + Seek,
+    {
+        // Files whose first line isn't valid UTF-8 are binary as well
+        let mut header = String::new();
+        let is_text = BufReader::new(&mut *model_file)
+            .read_line(&mut header)
+            .is_ok();
+        model_file.rewind()?;
+        Ok(if is_text && header.starts_with("solid ") {
+            Self::Ascii
+        } else {
+            Self::Binary
+        })
+    }
+}
+
+/// Objects of a model file to load, for files that hold several like OBJ and 3MF.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ObjectSelector {
+    /// Every object, merged into a single mesh.
+    #[default]
+    All,
+    /// The object at the given index, counted from 0 in file order.
+    ByIndex(usize),
+    /// The first object with the given name.
+    ByName(String),
+}
+
+impl std::fmt::Display for ObjectSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::All => f.write_str("all"),
+            Self::ByIndex(index) => write!(f, "index {index}"),
+            Self::ByName(name) => write!(f, "name \"{name}\""),
+        }
+    }
+}
+
+impl ObjectSelector {
+    /// Whether the object with the given index and name is selected.
+    fn matches(&self, index: usize, name: Option<&str>) -> bool {
+        match self {
+            Self::All => true,
+            Self::ByIndex(i) => *i == index,
+            Self::ByName(n) => name == Some(n.as_str()),
+        }
+    }
+
+    /// Indices of the selected objects, at most one unless every object is selected.
+    fn select<'a>(
+        &self,
+        names: impl IntoIterator<Item = Option<&'a str>>,
+    ) -> Result<Vec<usize>, MeshError> {
+        let mut selected = names
+            .into_iter()
+            .enumerate()
+            .filter(|(index, name)| self.matches(*index, *name))
+            .map(|(index, _)| index);
+        let selected = if *self == Self::All {
+            selected.collect()
+        } else {
+            selected.next().into_iter().collect::<Vec<_>>()
+        };
+        if selected.is_empty() && *self != Self::All {
+            return Err(MeshError::ObjectNotFound(self.to_string()));
+        }
+        Ok(selected)
+    }
+}
+
+/// Supported model file formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    Stl,
+    Obj,
+    Threemf,
+    /// Zip archive holding a model file in one of the other formats
+    Zip,
+}
+
+impl std::fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Stl => "stl",
+            Self::Obj => "obj",
+            Self::Threemf => "3mf",
+            Self::Zip => "zip",
+        })
+    }
+}
+
+impl InputFormat {
+    /// Axis pointing up by the convention of the format: Y for OBJ, which most modelling tools
+    /// export Y-up, and Z for the formats of 3D printing.
+    #[must_use]
+    pub const fn up_axis(self) -> Axis {
+        match self {
+            Self::Obj => Axis::Y,
+            Self::Stl | Self::Threemf | Self::Zip => Axis::Z,
+        }
+    }
+
+    /// Matches a file extension (case-insensitive) to an `InputFormat`.
+    #[must_use]
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_lowercase().as_str() {
+            "stl" => Some(Self::Stl),
+            "obj" => Some(Self::Obj),
+            "3mf" => Some(Self::Threemf),
+            "zip" => Some(Self::Zip),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the first bytes of a model file and its total length in bytes.
+    ///
+    /// Only formats recognized with confidence are returned: 3MF files are zip archives starting
+    /// with `PK\x03\x04`, which are taken as plain archives if their first member is a model
+    /// file, binary STL files have exactly 50 bytes per triangle after the 84-byte
+    /// header, ASCII STL files start with `solid` and OBJ files are text with vertex or face lines.
+    #[must_use]
+    pub fn sniff(header: &[u8], len: u64) -> Option<Self> {
+        let binary_stl_len = header.get(80..84).map(|count| {
+            84 + 50 * u64::from(u32::from_le_bytes([count[0], count[1], count[2], count[3]]))
+        });
+
+        if header.starts_with(b"PK\x03\x04") {
+            // Name of the first member, after the 30-byte local file header
+            let name = header.get(26..28).and_then(|len| {
+                header.get(30..30 + usize::from(u16::from_le_bytes([len[0], len[1]])))
+            });
+            let member_format = name
+                .and_then(|name| std::str::from_utf8(name).ok())
+                .and_then(|name| name.rsplit_once('.'))
+                .and_then(|(_, ext)| Self::from_extension(ext));
+            if member_format.is_some() {
+                Some(Self::Zip)
+            } else {
+                Some(Self::Threemf)
+            }
+        } else if binary_stl_len == Some(len) || header.trim_ascii_start().starts_with(b"solid") {
+            Some(Self::Stl)
+        } else if header
+            .iter()
+            .all(|b| b.is_ascii_graphic() || b.is_ascii_whitespace())
+            && header
+                .split(|&b| b == b'\n')
+                .any(|line| matches!(line.trim_ascii_start(), [b'v' | b'f', b' ' | b'\t', ..]))
+        {
+            Some(Self::Obj)
+        } else {
+            None
+        }
+    }
 }
 
 impl Mesh {
     /// Load mesh data from file (if provided) or stdin
-    pub fn load(model_filename: &str, recalc_normals: bool) -> Result<Self, MeshError> {
-        // TODO: Add support for URIs instead of plain file names
+    ///
+    /// The format is taken from `format` if provided, otherwise from the file extension. When
+    /// neither is available (e.g. reading from stdin), the format is guessed from the file contents.
+    /// For formats with several objects, only the ones matched by `objects` are loaded. With the
+    /// `uri` feature, `file://` URIs are read too, remote models need [`Mesh::from_uri`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file cannot be read, its format cannot be
+    /// determined, or it does not contain a valid model or the selected object.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(
+        model_filename: &str,
+        format: Option<InputFormat>,
+        stl_mode: StlMode,
+        objects: &ObjectSelector,
+        recalc_normals: bool,
+    ) -> Result<Self, MeshError> {
+        // Thumbnailers may be handed URIs instead of plain file names
         // https://developer.gnome.org/integration-guide/stable/thumbnailer.html.en
+        // Only local files are read here, downloads are left to explicit calls of `from_uri`.
+        #[cfg(feature = "uri")]
+        if model_filename.contains("://") {
+            let path = file_uri_path(model_filename)?;
+            return Self::load(&path, format, stl_mode, objects, recalc_normals);
+        }
 
         if model_filename == "-" {
             // create_stl_reader requires Seek, so we must read the entire stream into memory before proceeding.
             // So I guess this can just consume all RAM if it gets bad input. Hmmm....
             let mut input_buffer = Vec::new();
             io::stdin().read_to_end(&mut input_buffer)?;
-            return Self::from_stl(Cursor::new(input_buffer), recalc_normals);
+            let format = format
+                .or_else(|| {
+                    InputFormat::sniff(
+                        &input_buffer[..input_buffer.len().min(512)],
+                        input_buffer.len() as u64,
+                    )
+                })
+                .ok_or(MeshError::UnsupportedFormat)?;
+            return Self::from_reader(
+                Cursor::new(input_buffer),
+                format,
+                stl_mode,
+                objects,
+                recalc_normals,
+            );
         }
 
         let model_filename = Path::new(model_filename);
-        let model_file = File::open(model_filename)?;
-        match model_filename
-            .extension()
-            .and_then(|s| s.to_str())
-            .unwrap_or("")
-            .to_lowercase()
-            .as_str()
-        {
-            "obj" => Self::from_obj(model_file, recalc_normals),
-            "stl" => Self::from_stl(model_file, recalc_normals),
-            "3mf" => Self::from_3mf(model_file, recalc_normals),
-            _ => Err(MeshError::UnsupportedFormat),
-        }
-    }
+        let load = || {
+            let mut model_file = File::open(model_filename)?;
+            let format = file_format(model_filename, &mut model_file, format)?;
 
-    pub fn from_3mf<R>(model_file: R, _recalc_normals: bool) -> Result<Self, MeshError>
-    where
-        R: Read + Seek,
-    {
-        let models = threemf::read(model_file)?;
-        let mut result = None;
-        let vertex_translator = |vertex: &threemf::model::Vertex| {
-            #[allow(clippy::cast_possible_truncation)]
-            stl_io::Vertex::new([vertex.x as f32, vertex.y as f32, vertex.z as f32])
+            // Material libraries are looked up next to the file
+            if format == InputFormat::Obj {
+                return Self::from_obj_with_materials(
+                    model_file,
+                    model_filename.parent(),
+                    objects,
+                    recalc_normals,
+                );
+            }
+            Self::from_reader(model_file, format, stl_mode, objects, recalc_normals)
         };
 
-        let mut offset = 0;
+        // Name the file in IO errors, they are hard to make sense of otherwise
+        load().map_err(|e| name_file(e, model_filename))
+    }
 
-        // Combine all the models into a single mesh.
-        for model in models {
-            for object in model.resources.object {
-                let Some(mesh) = &object.mesh else { continue };
-                for (i, triangle) in mesh.triangles.triangle.iter().enumerate() {
-                    // Re-use `Mesh::process_tri`, which creates new vertices for every
-                    // triangle.
-                    // Possible optimization: re-use triangles instead.
-                    let triangle = Triangle {
-                        normal: Normal::new([1.0, 0.0, 0.0]),
-                        vertices: [
-                            vertex_translator(&mesh.vertices.vertex[triangle.v1]),
-                            vertex_translator(&mesh.vertices.vertex[triangle.v2]),
-                            vertex_translator(&mesh.vertices.vertex[triangle.v3]),
-                        ],
-                    };
+    /// Load mesh data like [`Mesh::load`], with each build item of 3MF files as a separate mesh
+    ///
+    /// Build items are placed instances of the objects of a 3MF file, they are returned with
+    /// their transforms applied, in file order. `objects` selects items by index or by the name
+    /// of their object. Other formats are loaded as a single mesh.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the same errors as [`Mesh::load`], and an error if an item
+    /// refers to an object that doesn't exist.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_all(
+        model_filename: &str,
+        format: Option<InputFormat>,
+        stl_mode: StlMode,
+        objects: &ObjectSelector,
+        recalc_normals: bool,
+    ) -> Result<Vec<Self>, MeshError> {
+        let load = || {
+            let path = Path::new(model_filename);
+            if model_filename.contains("://") || model_filename == "-" {
+                return Ok(None);
+            }
+            let mut model_file = File::open(path)?;
+            if file_format(path, &mut model_file, format)? != InputFormat::Threemf {
+                return Ok(None);
+            }
+            Self::from_3mf_items(model_file, objects).map(Some)
+        };
 
-                    let f_mesh = result.get_or_insert_with(|| Self {
-                        vertices: Vec::new(),
-                        normals: Vec::new(),
-                        indices: Vec::new(),
-                        bounds: BoundingBox::new(&triangle.vertices[0]),
-                    });
+        match load().map_err(|e| name_file(e, Path::new(model_filename)))? {
+            Some(meshes) => Ok(meshes),
+            None => Ok(vec![Self::load(
+                model_filename,
+                format,
+                stl_mode,
+                objects,
+                recalc_normals,
+            )?]),
+        }
+    }
 
-                    f_mesh.process_tri(&triangle, true);
-                    f_mesh.indices.extend(
-                        (0..3)
-                            .map(|j| u32::try_from((offset + i) * 3 + j))
-                            .collect::<Result<Vec<_>, _>>()
-                            .map_err(|e| MeshError::InvalidThreemf(e.to_string()))?,
-                    );
-                }
-                if let Some(ref mut f_mesh) = result {
-                    offset += mesh.triangles.triangle.len();
-                    // 3MF files don't have normals, so we need to calculate them.
-                    f_mesh.compute_smooth_normals();
+    /// Load mesh data like [`Mesh::load`], on a separate thread so reading and parsing the file
+    /// doesn't block the async executor
+    ///
+    /// # Errors
+    ///
+    /// This function will return the same errors as [`Mesh::load`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn load_async(
+        model_filename: &str,
+        format: Option<InputFormat>,
+        stl_mode: StlMode,
+        objects: &ObjectSelector,
+        recalc_normals: bool,
+    ) -> Result<Self, MeshError> {
+        let model_filename = model_filename.to_owned();
+        let objects = objects.clone();
+        on_thread(move || Self::load(&model_filename, format, stl_mode, &objects, recalc_normals))
+            .await
+    }
+
+    /// Load mesh data from a `file://`, `http://` or `https://` URI
+    ///
+    /// Remote models are downloaded into memory first, up to 256 MiB and with a timeout of 30
+    /// seconds to connect and between reads. The format is taken from `format` if provided,
+    /// otherwise from the extension of the URI path, or guessed from the contents.
+    ///
+    /// Unlike [`Mesh::load`], which only reads `file://` URIs, this fetches whatever URI it is
+    /// given, so only pass it URIs from trusted sources.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the URI is invalid or uses another scheme, the model
+    /// cannot be read or downloaded or is too large, or it does not contain a valid model or the
+    /// selected object.
+    #[cfg(all(feature = "uri", not(target_arch = "wasm32")))]
+    pub fn from_uri(
+        uri: &str,
+        format: Option<InputFormat>,
+        stl_mode: StlMode,
+        objects: &ObjectSelector,
+        recalc_normals: bool,
+    ) -> Result<Self, MeshError> {
+        let url = url::Url::parse(uri).map_err(|e| MeshError::InvalidUri(format!("{uri}: {e}")))?;
+        match url.scheme() {
+            "file" => Self::load(
+                &file_uri_path(uri)?,
+                format,
+                stl_mode,
+                objects,
+                recalc_normals,
+            ),
+            "http" | "https" => {
+                let mut buffer = Vec::new();
+                ureq::AgentBuilder::new()
+                    .timeout_connect(DOWNLOAD_TIMEOUT)
+                    .timeout_read(DOWNLOAD_TIMEOUT)
+                    .build()
+                    .get(uri)
+                    .call()
+                    .map_err(|e| MeshError::Download(e.to_string()))?
+                    .into_reader()
+                    .take(MAX_DOWNLOAD_SIZE + 1)
+                    .read_to_end(&mut buffer)?;
+                if buffer.len() as u64 > MAX_DOWNLOAD_SIZE {
+                    return Err(MeshError::Download(format!(
+                        "{uri} is larger than {MAX_DOWNLOAD_SIZE} bytes"
+                    )));
                 }
+                let format = format
+                    .or_else(|| {
+                        url.path_segments()?
+                            .next_back()
+                            .and_then(|name| Path::new(name).extension()?.to_str())
+                            .and_then(InputFormat::from_extension)
+                    })
+                    .or_else(|| {
+                        InputFormat::sniff(&buffer[..buffer.len().min(512)], buffer.len() as u64)
+                    })
+                    .ok_or(MeshError::UnsupportedFormat)?;
+                Self::from_reader(
+                    Cursor::new(buffer),
+                    format,
+                    stl_mode,
+                    objects,
+                    recalc_normals,
+                )
             }
+            scheme => Err(MeshError::InvalidUri(format!(
+                "unsupported scheme {scheme} in {uri}"
+            ))),
         }
+    }
+
+    /// Load mesh data like [`Mesh::from_uri`], on a separate thread so downloading and parsing
+    /// the model doesn't block the async executor
+    ///
+    /// # Errors
+    ///
+    /// This function will return the same errors as [`Mesh::from_uri`].
+    #[cfg(all(feature = "uri", not(target_arch = "wasm32")))]
+    pub async fn from_uri_async(
+        uri: &str,
+        format: Option<InputFormat>,
+        stl_mode: StlMode,
+        objects: &ObjectSelector,
+        recalc_normals: bool,
+    ) -> Result<Self, MeshError> {
+        let uri = uri.to_owned();
+        let objects = objects.clone();
+        on_thread(move || Self::from_uri(&uri, format, stl_mode, &objects, recalc_normals)).await
+    }
 
-        result.ok_or(MeshError::NoMeshData)
+    /// Load mesh data in the given format from a reader
+    ///
+    /// `stl_mode` is only used for STL data, and `objects` for OBJ and 3MF data.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the data cannot be read, is not a valid model or
+    /// doesn't contain the selected object.
+    pub fn from_reader<R>(
+        model_file: R,
+        format: InputFormat,
+        stl_mode: StlMode,
+        objects: &ObjectSelector,
+        recalc_normals: bool,
+    ) -> Result<Self, MeshError>
+    where
+        R: Read + Seek,
+    {
+        match format {
+            InputFormat::Obj => Self::from_obj(model_file, objects, recalc_normals),
+            InputFormat::Stl => Self::from_stl(model_file, stl_mode, recalc_normals),
+            InputFormat::Threemf => Self::from_3mf(model_file, objects, recalc_normals),
+            InputFormat::Zip => Self::from_zip(model_file, stl_mode, objects, recalc_normals),
+        }
     }
 
-    pub fn from_stl<R>(mut model_file: R, recalc_normals: bool) -> Result<Self, MeshError>
+    /// Load mesh data from the first model file in a zip archive
+    ///
+    /// The format of the model is taken from its extension, nested archives are skipped.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the data is not a valid zip archive, the archive
+    /// holds no model file, or the model can't be loaded.
+    pub fn from_zip<R>(
+        model_file: R,
+        stl_mode: StlMode,
+        objects: &ObjectSelector,
+        recalc_normals: bool,
+    ) -> Result<Self, MeshError>
     where
         R: Read + Seek,
     {
-        let stl = read_stl(&mut model_file)?;
-        let mut mesh: Option<Self> = None;
-        for (i, face) in stl.faces.iter().enumerate() {
-            let triangle = Triangle {
-                normal: face.normal,
-                vertices: [
-                    stl.vertices[face.vertices[0]],
-                    stl.vertices[face.vertices[1]],
-                    stl.vertices[face.vertices[2]],
-                ],
+        let mut zip = ZipArchive::new(model_file)?;
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i)?;
+            let format = std::path::Path::new(file.name())
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .and_then(InputFormat::from_extension);
+            let Some(format) = format.filter(|&format| format != InputFormat::Zip) else {
+                continue;
             };
+            if !file.is_file() || file.name().starts_with("__MACOSX/") {
+                continue;
+            }
 
-            let mesh = mesh.get_or_insert_with(|| Self {
-                vertices: Vec::new(),
-                normals: Vec::new(),
-                indices: Vec::new(),
-                bounds: BoundingBox::new(&triangle.vertices[0]),
-            });
+            // Members can't be seeked, which the STL and 3MF readers require
+            let mut buffer = Vec::with_capacity(usize::try_from(file.size()).unwrap_or(0));
+            file.read_to_end(&mut buffer)?;
+            return Self::from_reader(
+                io::Cursor::new(buffer),
+                format,
+                stl_mode,
+                objects,
+                recalc_normals,
+            );
+        }
+        Err(MeshError::NoModelInArchive)
+    }
 
-            mesh.process_tri(&triangle, recalc_normals);
-            for j in 0..3 {
-                mesh.indices.push(
-                    u32::try_from(i * 3 + j).map_err(|e| MeshError::InvalidStl(e.to_string()))?,
-                );
-            }
+    /// Load mesh data from a 3MF file
+    ///
+    /// Objects are counted across all the models of the file, skipping those without a mesh.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the data cannot be read, is encrypted, is not a
+    /// valid 3MF model or doesn't contain the selected object.
+    pub fn from_3mf<R>(
+        model_file: R,
+        objects: &ObjectSelector,
+        _recalc_normals: bool,
+    ) -> Result<Self, MeshError>
+    where
+        R: Read + Seek,
+    {
+        let (models, colors) = read_3mf(model_file)?;
+        let meshes = models
+            .iter()
+            .enumerate()
+            .flat_map(|(m, model)| {
+                model
+                    .resources
+                    .object
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(o, object)| {
+                        Some((m, o, object.name.as_deref(), object.mesh.as_ref()?))
+                    })
+            })
+            .collect::<Vec<_>>();
+        let selected = objects.select(meshes.iter().map(|(_, _, name, _)| *name))?;
+
+        // Combine all the selected objects into a single mesh.
+        let objects = selected
+            .into_iter()
+            .map(|i| {
+                let (m, o, _, mesh) = meshes[i];
+                vec![ThreemfPart {
+                    mesh,
+                    colors: colors.get(m).and_then(|model| model.get(o)),
+                    transform: DMat4::IDENTITY,
+                }]
+            })
+            .collect::<Vec<_>>();
+        mesh_from_3mf_parts(&objects)
+    }
+
+    /// Load each build item of a 3MF file as a separate mesh, with the transform of the item
+    /// applied
+    ///
+    /// Items are counted across all the models of the file, `objects` selects them by index or
+    /// by the name of their object. Objects made of components are combined into a single mesh.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the data cannot be read, is encrypted, is not a
+    /// valid 3MF model, an item refers to an object that doesn't exist, or no item is selected.
+    pub fn from_3mf_items<R>(
+        model_file: R,
+        objects: &ObjectSelector,
+    ) -> Result<Vec<Self>, MeshError>
+    where
+        R: Read + Seek,
+    {
+        let (models, colors) = read_3mf(model_file)?;
+        let items = models
+            .iter()
+            .enumerate()
+            .flat_map(|(m, model)| model.build.item.iter().map(move |item| (m, item)))
+            .collect::<Vec<_>>();
+        let selected = objects.select(items.iter().map(|&(m, item)| {
+            models[m]
+                .resources
+                .object
+                .iter()
+                .find(|object| object.id == item.objectid)
+                .and_then(|object| object.name.as_deref())
+        }))?;
+
+        let meshes = selected
+            .into_iter()
+            .map(|i| {
+                let (m, item) = items[i];
+                let mut parts = Vec::new();
+                collect_3mf_parts(
+                    &mut parts,
+                    &models[m],
+                    colors.get(m),
+                    item.objectid,
+                    threemf_transform(item.transform),
+                    0,
+                )?;
+                mesh_from_3mf_parts(&[parts])
+            })
+            .collect::<Result<Vec<_>, MeshError>>()?;
+
+        if meshes.is_empty() {
+            return Err(MeshError::NoMeshData);
+        }
+        Ok(meshes)
+    }
+
+    /// Load mesh data from a STL file
+    ///
+    /// With [`StlMode::Auto`] the encoding is detected from the header, otherwise the data is
+    /// parsed with the given encoding regardless of what the header looks like.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the data cannot be read or is not a valid STL model.
+    pub fn from_stl<R>(
+        mut model_file: R,
+        stl_mode: StlMode,
+        recalc_normals: bool,
+    ) -> Result<Self, MeshError>
+    where
+        R: Read + Seek,
+    {
+        let detected = StlMode::detect(&mut model_file)?;
+        let binary = matches!(
+            (stl_mode, detected),
+            (StlMode::Binary, _) | (StlMode::Auto, StlMode::Binary)
+        );
+        let stl_colors = binary && has_stl_colors(&mut model_file)?;
+        let (stl, stl_mode) = match stl_mode {
+            StlMode::Auto => (read_stl(&mut model_file)?, detected),
+            mode if mode == detected => (read_stl(&mut model_file)?, mode),
+            // stl_io can't be told which encoding to use, so the forced one is parsed here
+            StlMode::Ascii => (read_ascii_stl(model_file)?, StlMode::Ascii),
+            StlMode::Binary => (read_binary_stl(model_file)?, StlMode::Binary),
+        };
+        let first_face = stl.faces.first().ok_or(MeshError::EmptyMesh)?;
+        let vertex_count =
+            u32::try_from(stl.faces.len() * 3).map_err(|e| MeshError::InvalidStl(e.to_string()))?;
+
+        #[cfg(feature = "parallel")]
+        let faces = stl.faces.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let faces = stl.faces.iter();
+
+        // Every face gets its own 3 vertices, so normals from the file can be used as they are
+        let vertices = faces
+            .clone()
+            .flat_map(|face| {
+                face.vertices.map(|i| {
+                    let v = stl.vertices[i];
+                    Vec3::new(v[0], v[1], v[2])
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut bounds = BoundingBox::new(&stl.vertices[first_face.vertices[0]]);
+        for v in &vertices {
+            bounds.expand(&Vector::new([v.x, v.y, v.z]));
         }
 
-        let mut mesh = mesh.ok_or(MeshError::EmptyMesh)?;
+        let mut mesh = Self {
+            colors: vec![Vec3::ONE; vertices.len()],
+            vertices,
+            normals: Vec::new(),
+            indices: (0..vertex_count).collect(),
+            bounds,
+            format: Some(InputFormat::Stl),
+            normals_recomputed: false,
+            stl_mode: Some(stl_mode),
+            stl_colors,
+            object_starts: vec![0],
+            origin: DVec3::ZERO,
+        };
+        mesh.recenter();
 
-        if mesh.normals.is_empty() {
+        // Use normals from STL file if they are all provided
+        if recalc_normals
+            || faces
+                .clone()
+                .any(|face| face.normal == Vector::new([0.0, 0.0, 0.0]))
+        {
             mesh.compute_smooth_normals();
+        } else {
+            mesh.normals = faces
+                .flat_map(|face| {
+                    let n = Vec3::new(face.normal[0], face.normal[1], face.normal[2]);
+                    // TODO: Figure out how to get away with 1 normal instead of 3
+                    [n; 3]
+                })
+                .collect();
         }
 
         Ok(mesh)
     }
 
-    pub fn from_obj<R>(obj_file: R, _recalc_normals: bool) -> Result<Self, MeshError>
+    /// Load mesh data from a OBJ file
+    ///
+    /// Each `o` or `g` statement starts a new object. Point clouds are always loaded whole.
+    /// Vertices are colored from the `v x y z r g b` extension when the file has it.
+    /// Normals computed for files without them are only smoothed within the `s` smoothing
+    /// groups.
+    ///
+    /// Without the directory of the file the material libraries it references can't be found,
+    /// every face is white. See [`Mesh::from_obj_with_materials`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the data cannot be read, is not a valid OBJ model or
+    /// doesn't contain the selected object.
+    pub fn from_obj<R>(
+        obj_file: R,
+        objects: &ObjectSelector,
+        recalc_normals: bool,
+    ) -> Result<Self, MeshError>
     where
         R: Read,
     {
-        let mut model = BufReader::new(obj_file);
-        let (models, _) = tobj::load_obj_buf(&mut model, &tobj::GPU_LOAD_OPTIONS, |_| {
-            Err(tobj::LoadError::GenericFailure)
-        })?;
+        Self::from_obj_with_materials(obj_file, None, objects, recalc_normals)
+    }
+
+    /// Load mesh data from a OBJ file like [`Mesh::from_obj`], coloring the faces with the
+    /// diffuse color of their material
+    ///
+    /// Colors some exporters write after the position on `v` lines are tinted by the material.
+    ///
+    /// `mtllib` paths are relative to `material_dir`, and libraries outside of it are refused
+    /// so untrusted models can't read other files. Exporters often reference material libraries
+    /// they don't write, so missing, unreadable or refused ones are skipped with a warning and
+    /// their faces left white.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the data cannot be read, is not a valid OBJ model,
+    /// references a material library that isn't valid or doesn't contain the selected object.
+    pub fn from_obj_with_materials<R>(
+        mut obj_file: R,
+        material_dir: Option<&Path>,
+        objects: &ObjectSelector,
+        recalc_normals: bool,
+    ) -> Result<Self, MeshError>
+    where
+        R: Read,
+    {
+        let mut buffer = Vec::new();
+        obj_file.read_to_end(&mut buffer)?;
+        // tobj only keeps the error of the last library, and drops it if another one loaded
+        let invalid_library = RefCell::new(None);
+        let (models, materials) =
+            tobj::load_obj_buf(&mut buffer.as_slice(), &tobj::GPU_LOAD_OPTIONS, |name| {
+                let file = material_dir
+                    .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+                    .and_then(|dir| File::open(companion_path(dir, name)?));
+                match file {
+                    Ok(file) => tobj::load_mtl_buf(&mut BufReader::new(file)).inspect_err(|e| {
+                        invalid_library.replace(Some(format!("{}: {e}", name.display())));
+                    }),
+                    Err(e) => {
+                        warn!(
+                            "Material library {} can't be read, using the default material: {e}",
+                            name.display()
+                        );
+                        Ok(Default::default())
+                    }
+                }
+            })?;
+        if let Some(library) = invalid_library.into_inner() {
+            return Err(MeshError::InvalidObj(format!(
+                "Invalid material library {library}"
+            )));
+        }
+        let materials = materials.unwrap_or_default();
+        let diffuse = |model: &tobj::Model| {
+            model
+                .mesh
+                .material_id
+                .and_then(|id| materials.get(id)?.diffuse)
+                .map_or(Vec3::ONE, Vec3::from)
+        };
+
+        // tobj only keeps the vertices referenced by faces, so files without any are read as
+        // point clouds instead
+        if models.iter().all(|model| model.mesh.indices.is_empty()) {
+            return Self::from_obj_points(&buffer);
+        }
+
+        // Smoothing group of each triangle, across all the objects
+        let face_groups = obj_smoothing_groups(&buffer).filter(|groups| {
+            let triangles = models
+                .iter()
+                .map(|m| m.mesh.indices.len() / 3)
+                .sum::<usize>();
+            if groups.len() != triangles {
+                warn!("Smoothing groups don't match the faces, ignoring them");
+            }
+            groups.len() == triangles
+        });
+        let first_faces = models
+            .iter()
+            .scan(0, |first, model| {
+                let start = *first;
+                *first += model.mesh.indices.len() / 3;
+                Some(start)
+            })
+            .collect::<Vec<_>>();
+
+        // tobj starts a new model where the material changes, the models of an object share its
+        // name
+        let mut object_models: Vec<std::ops::Range<usize>> = Vec::new();
+        for (i, model) in models.iter().enumerate() {
+            match object_models.last_mut() {
+                Some(object) if models[object.start].name == model.name => object.end = i + 1,
+                _ => object_models.push(i..i + 1),
+            }
+        }
 
-        let first_mesh = &models.first().ok_or(MeshError::EmptyMesh)?.mesh;
+        let models = objects
+            .select(
+                object_models
+                    .iter()
+                    .map(|object| Some(models[object.start].name.as_str())),
+            )?
+            .into_iter()
+            .flat_map(|i| {
+                let object = object_models[i].clone();
+                object.clone().map(move |j| (j, j == object.start))
+            })
+            .map(|(i, starts_object)| (first_faces[i], &models[i], starts_object))
+            .collect::<Vec<_>>();
+
+        let first_mesh = &models.first().ok_or(MeshError::EmptyMesh)?.1.mesh;
         let mut first_vertex = first_mesh.positions.iter();
         let mut mesh = Self {
             vertices: Vec::with_capacity(first_mesh.positions.len() / 3),
             normals: Vec::with_capacity(first_mesh.normals.len() / 3),
+            colors: Vec::with_capacity(first_mesh.positions.len() / 3),
             indices: Vec::with_capacity(first_mesh.indices.len()),
             bounds: BoundingBox::new(&Vector::new([
                 *first_vertex.next().ok_or(MeshError::EmptyMesh)?,
                 *first_vertex.next().ok_or(MeshError::EmptyMesh)?,
                 *first_vertex.next().ok_or(MeshError::EmptyMesh)?,
             ])),
+            format: Some(InputFormat::Obj),
+            normals_recomputed: false,
+            stl_mode: None,
+            stl_colors: false,
+            object_starts: Vec::with_capacity(models.len()),
+            origin: DVec3::ZERO,
         };
 
+        // With single indexing tobj lays normals out parallel to the positions, unless only some
+        // faces have normals, then they no longer line up with the vertices and can't be used
+        let mut file_normals = Vec::with_capacity(first_mesh.positions.len() / 3);
         let mut offset = 0;
-        for model in &models {
-            let indices = &model.mesh.indices;
+        for (first_face, model, starts_object) in models {
             let normals = &model.mesh.normals;
-            let positions = &model.mesh.positions;
+            let use_file_normals = !recalc_normals && normals.len() == model.mesh.positions.len();
+            let mut indices = Cow::Borrowed(&model.mesh.indices);
+            let mut positions = model
+                .mesh
+                .positions
+                .chunks_exact(3)
+                .map(|v| Vec3::new(v[0], v[1], v[2]))
+                .collect::<Vec<_>>();
+            // Colors written after the position on `v` lines, tinted by the material
+            let color = diffuse(model);
+            let mut colors = if model.mesh.vertex_color.len() == model.mesh.positions.len() {
+                model
+                    .mesh
+                    .vertex_color
+                    .chunks_exact(3)
+                    .map(|c| Vec3::new(c[0], c[1], c[2]) * color)
+                    .collect()
+            } else {
+                vec![color; positions.len()]
+            };
+
+            // Faces in different smoothing groups don't share vertices, so their normals are
+            // computed separately
+            if let Some(face_groups) = face_groups.as_ref().filter(|_| !use_file_normals) {
+                let face_groups = &face_groups[first_face..first_face + indices.len() / 3];
+                let (split, sources) = split_smoothing_groups(&indices, face_groups);
+                indices = Cow::Owned(split);
+                positions = sources.iter().map(|&i| positions[i]).collect();
+                colors = sources.into_iter().map(|i| colors[i]).collect();
+            }
 
+            if starts_object {
+                mesh.object_starts.push(mesh.vertices.len());
+            }
             mesh.indices.extend(indices.iter().map(|i| i + offset));
-            offset += u32::try_from(positions.len() / 3)
-                .map_err(|e| MeshError::InvalidObj(e.to_string()))?;
+            offset +=
+                u32::try_from(positions.len()).map_err(|e| MeshError::InvalidObj(e.to_string()))?;
 
-            for vertices in positions
-                .chunks_exact(3)
-                .map(|v| Vec3::new(v[0], v[1], v[2]))
-            {
+            for vertices in positions {
                 mesh.bounds
                     .expand(&Vector::new([vertices.x, vertices.y, vertices.z]));
                 mesh.vertices.push(vertices);
             }
+            mesh.colors.extend(colors);
 
-            if normals.is_empty() {
-                mesh.compute_smooth_normals();
+            if use_file_normals {
+                file_normals.extend(
+                    normals
+                        .chunks_exact(3)
+                        .map(|n| Some(Vec3::new(n[0], n[1], n[2]))),
+                );
             } else {
-                mesh.normals
-                    .extend(normals.chunks_exact(3).map(|n| Vec3::new(n[0], n[1], n[2])));
+                if !normals.is_empty() {
+                    warn!(
+                        "Only some faces of {} have normals, computing them instead",
+                        model.name
+                    );
+                }
+                file_normals.resize(mesh.vertices.len(), None);
+            }
+        }
+
+        mesh.recenter();
+        if file_normals.iter().all(Option::is_some) {
+            mesh.normals = file_normals.into_iter().flatten().collect();
+        } else {
+            mesh.compute_smooth_normals();
+            for (normal, file_normal) in mesh.normals.iter_mut().zip(file_normals) {
+                if let Some(file_normal) = file_normal {
+                    *normal = file_normal;
+                    mesh.normals_recomputed = false;
+                }
             }
         }
         Ok(mesh)
     }
 
-    // Move the mesh to be centered at the origin
-    // and scaled to fit a 2 x 2 x 2 box. This means that
-    // all coordinates will be between -1.0 and 1.0
-    pub fn scale_and_center(&self) -> Mat4 {
-        // Move center to origin
-        let center = self.bounds.center();
-        let translation_vector = Vec3::new(-center.x, -center.y, -center.z);
-        let translation_matrix = Mat4::from_translation(translation_vector);
-        // Scale
-        let longest = self
-            .bounds
-            .length()
-            .max(self.bounds.width())
-            .max(self.bounds.height());
-        let scale = 2.0 / longest;
-        let scale_matrix = Mat4::from_scale(Vec3::splat(scale));
-        scale_matrix * translation_matrix
-    }
+    /// Load the vertices of a OBJ file without faces as a point cloud
+    fn from_obj_points(buffer: &[u8]) -> Result<Self, MeshError> {
+        let invalid = |e: &dyn std::fmt::Display| MeshError::InvalidObj(e.to_string());
+        let text = std::str::from_utf8(buffer).map_err(|e| invalid(&e))?;
+
+        let mut vertices = Vec::new();
+        let mut colors = Vec::new();
+        for line in text.lines() {
+            let mut words = line.split_whitespace();
+            if words.next() != Some("v") {
+                continue;
+            }
+            let mut coords = words.by_ref().take(3).map(str::parse::<f32>);
+            let mut coord = || {
+                coords
+                    .next()
+                    .ok_or_else(|| invalid(&"vertex with less than 3 coordinates"))?
+                    .map_err(|e| invalid(&e))
+            };
+            vertices.push(Vec3::new(coord()?, coord()?, coord()?));
+            // Points without a color after their position are white
+            let color = words.map(str::parse::<f32>).collect::<Result<Vec<_>, _>>();
+            colors.push(match color.as_deref() {
+                Ok(&[r, g, b]) => Vec3::new(r, g, b),
+                _ => Vec3::ONE,
+            });
+        }
+
+        let first = vertices.first().ok_or(MeshError::EmptyMesh)?;
+        let mut bounds = BoundingBox::new(&Vector::new(first.to_array()));
+        for v in &vertices {
+            bounds.expand(&Vector::new(v.to_array()));
+        }
 
-    fn process_tri(&mut self, tri: &Triangle, recalc_normals: bool) {
-        self.vertices.extend(tri.vertices.iter().map(|v| {
-            self.bounds.expand(v);
-            Vec3::new(v[0], v[1], v[2])
-        }));
+        let mut mesh = Self {
+            colors,
+            vertices,
+            normals: Vec::new(),
+            indices: Vec::new(),
+            bounds,
+            format: Some(InputFormat::Obj),
+            normals_recomputed: false,
+            stl_mode: None,
+            stl_colors: false,
+            object_starts: vec![0],
+            origin: DVec3::ZERO,
+        };
+        mesh.recenter();
+        Ok(mesh)
+    }
 
-        // Use normal from STL file if it is provided
-        if !(recalc_normals || (tri.normal == Vector::new([0.0, 0.0, 0.0]))) {
-            let n = Vec3::new(tri.normal[0], tri.normal[1], tri.normal[2]);
-            // TODO: Figure out how to get away with 1 normal instead of 3
-            for _ in 0..3 {
-                self.normals.push(n);
+    /// Builds a mesh from raw vertex positions and triangle indices
+    ///
+    /// Normals are computed from the triangles when `normals` is `None`. Every vertex is white.
+    /// Models far from the origin are moved next to it like loaded ones, see [`Mesh::origin`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if there are no vertices, the number of indices isn't a
+    /// multiple of 3, an index is out of bounds, or there isn't a normal for every vertex.
+    pub fn from_raw(
+        vertices: Vec<Vec3>,
+        indices: Vec<u32>,
+        normals: Option<Vec<Vec3>>,
+    ) -> Result<Self, MeshError> {
+        let first = vertices.first().ok_or(MeshError::EmptyMesh)?;
+        if !indices.len().is_multiple_of(3) {
+            return Err(MeshError::InvalidMesh(format!(
+                "{} indices don't make whole triangles",
+                indices.len()
+            )));
+        }
+        if let Some(&index) = indices
+            .iter()
+            .find(|&&i| usize::try_from(i).map_or(true, |i| i >= vertices.len()))
+        {
+            return Err(MeshError::InvalidMesh(format!(
+                "Index {index} is out of bounds for {} vertices",
+                vertices.len()
+            )));
+        }
+        if let Some(normals) = &normals {
+            if normals.len() != vertices.len() {
+                return Err(MeshError::InvalidMesh(format!(
+                    "{} normals for {} vertices",
+                    normals.len(),
+                    vertices.len()
+                )));
             }
         }
+
+        let mut bounds = BoundingBox::new(&Vector::new(first.to_array()));
+        for v in &vertices {
+            bounds.expand(&Vector::new(v.to_array()));
+        }
+
+        let mut mesh = Self {
+            colors: vec![Vec3::ONE; vertices.len()],
+            vertices,
+            normals: Vec::new(),
+            indices,
+            bounds,
+            format: None,
+            normals_recomputed: false,
+            stl_mode: None,
+            stl_colors: false,
+            object_starts: vec![0],
+            origin: DVec3::ZERO,
+        };
+        mesh.recenter();
+        match normals {
+            Some(normals) => mesh.normals = normals,
+            None => mesh.compute_smooth_normals(),
+        }
+        Ok(mesh)
     }
 
-    /// Calculates the normal of an indexed mesh, smoothing normals for shared vertices.
+    /// Removes the triangles, or points of point clouds, using vertices with NaN or infinite
+    /// coordinates, which broken exporters sometimes write. The vertices no longer used are
+    /// removed as well, and the bounds are recomputed from the remaining ones.
     ///
-    /// Based on code from Bevy's `compute_normals` function.
-    /// <https://github.com/bevyengine/bevy/blob/v0.15.0-rc.1/crates/bevy_mesh/src/mesh.rs#L665-L714>
-    fn compute_smooth_normals(&mut self) {
-        let positions = &self.vertices;
-        let mut normals = vec![Vec3::ZERO; positions.len()];
-
-        self.indices.chunks_exact(3).for_each(|face| {
-            let [a, b, c] = [face[0] as usize, face[1] as usize, face[2] as usize];
-
-            // Calculate face normal (triangle's normal)
-            let normal = {
-                let a = positions[a];
-                let b = positions[b];
-                let c = positions[c];
-                (b - a).cross(c - a).normalize()
-            };
+    /// Returns the number of triangles or points removed.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `strict` is set and there are non-finite vertices,
+    /// or if nothing is left after removing them.
+    pub fn sanitize(&mut self, strict: bool) -> Result<usize, MeshError> {
+        let Some(first) = self.vertices.iter().position(|v| !v.is_finite()) else {
+            return Ok(0);
+        };
+        if strict {
+            return Err(MeshError::NonFiniteVertex(first));
+        }
 
-            // Accumulate the normal to each vertex of the triangle
-            normals[a] += normal;
-            normals[b] += normal;
-            normals[c] += normal;
-        });
+        let points = self.indices.is_empty();
+        let finite = |i: &u32| {
+            self.vertices
+                .get(*i as usize)
+                .is_some_and(|v| v.is_finite())
+        };
+        // Vertices only used by the removed triangles are dropped too, so they don't count
+        // towards the bounds or the center of the model
+        let (keep, removed) = if points {
+            let keep = self
+                .vertices
+                .iter()
+                .map(|v| v.is_finite())
+                .collect::<Vec<_>>();
+            let removed = keep.iter().filter(|&&keep| !keep).count();
+            (keep, removed)
+        } else {
+            let indices = self
+                .indices
+                .chunks_exact(3)
+                .filter(|triangle| triangle.iter().all(finite))
+                .flatten()
+                .copied()
+                .collect::<Vec<_>>();
+            let removed = (self.indices.len() - indices.len()) / 3;
+            self.indices = indices;
+            let mut keep = vec![false; self.vertices.len()];
+            for &i in &self.indices {
+                keep[i as usize] = true;
+            }
+            (keep, removed)
+        };
 
-        // Normalize the vertex normals
-        self.normals = normals
+        // New index of each vertex, the number of vertices kept before it
+        let remap = keep
             .iter()
-            .map(|normal| normal.normalize_or_zero())
-            .collect();
+            .scan(0, |kept, &keep| {
+                let index = *kept;
+                *kept += u32::from(keep);
+                Some(index)
+            })
+            .collect::<Vec<_>>();
+        for i in &mut self.indices {
+            *i = remap[*i as usize];
+        }
+        let kept = keep.iter().filter(|&&keep| keep).count();
+        for start in &mut self.object_starts {
+            *start = remap.get(*start).map_or(kept, |&start| start as usize);
+        }
+        let retain = |values: &mut Vec<Vec3>| {
+            if values.len() == keep.len() {
+                let mut keep = keep.iter();
+                values.retain(|_| keep.next() == Some(&true));
+            }
+        };
+        retain(&mut self.normals);
+        retain(&mut self.colors);
+        retain(&mut self.vertices);
+
+        let first = *self.vertices.first().ok_or(MeshError::EmptyMesh)?;
+        self.bounds = self.vertices.iter().fold(
+            BoundingBox {
+                min: first,
+                max: first,
+            },
+            |bounds, &v| BoundingBox {
+                min: bounds.min.min(v),
+                max: bounds.max.max(v),
+            },
+        );
+        // Models far from the origin weren't moved next to it with non-finite bounds
+        self.recenter();
+
+        // Smooth normals were averaged with the removed triangles
+        if self.normals_recomputed && !points {
+            self.compute_smooth_normals();
+        }
+
+        warn!(
+            "Removed {removed} {} with NaN or infinite coordinates",
+            if points { "points" } else { "triangles" }
+        );
+        Ok(removed)
+    }
+
+    /// Checks that every triangle has a usable normal. By default triangles with zero area get a
+    /// zero normal and are shaded black, this is for callers that would rather reject the model.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error with the index of the first triangle that has zero
+    /// area, or that the file gives a zero or non-finite normal.
+    pub fn check_normals(&self) -> Result<(), MeshError> {
+        let usable = |n: Vec3| n.try_normalize().is_some();
+        let first = self.indices.chunks_exact(3).position(|face| {
+            let [a, b, c] = [face[0], face[1], face[2]].map(|i| self.vertices[i as usize]);
+            !usable((b - a).cross(c - a))
+                || (self.normals.len() == self.vertices.len()
+                    && !face.iter().all(|&i| usable(self.normals[i as usize])))
+        });
+        first.map_or(Ok(()), |triangle| {
+            Err(MeshError::DegenerateNormal(triangle))
+        })
+    }
+
+    /// Replaces the vertex colors with a distinct color for each object the mesh was combined
+    /// from, so the parts of an assembly can be told apart. The colors only depend on the order
+    /// of the objects.
+    pub fn color_objects(&mut self) {
+        self.colors.resize(self.vertices.len(), Vec3::ONE);
+        let ends = self
+            .object_starts
+            .iter()
+            .skip(1)
+            .copied()
+            .chain([self.vertices.len()]);
+        for (i, (&start, end)) in self.object_starts.iter().zip(ends).enumerate() {
+            self.colors[start..end].fill(object_color(i));
+        }
+    }
+
+    /// The convex hull of the vertices, drawn flat shaded, for a simplified silhouette of the
+    /// model.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the vertices are all on one plane, which has no
+    /// 3D hull.
+    pub fn convex_hull(&self) -> Result<Self, MeshError> {
+        let (points, triangles) = hull::convex_hull(&self.vertices).ok_or_else(|| {
+            MeshError::InvalidMesh("The model is flat and has no convex hull".to_string())
+        })?;
+
+        // Every triangle gets its own 3 vertices, with the normal of the triangle
+        let vertices = triangles
+            .iter()
+            .flat_map(|t| t.map(|i| points[i]))
+            .collect::<Vec<_>>();
+        let normals = vertices
+            .chunks_exact(3)
+            .flat_map(|t| [(t[1] - t[0]).cross(t[2] - t[0]).normalize_or_zero(); 3])
+            .collect();
+        let indices = (0..).take(vertices.len()).collect();
+        let mut hull = Self::from_raw(vertices, indices, Some(normals))?;
+        hull.format = self.format;
+        hull.stl_mode = self.stl_mode;
+        hull.origin = self.origin;
+        Ok(hull)
+    }
+
+    /// Flips triangles wound the other way than their neighbours, so back-face culling doesn't
+    /// drop them, and returns how many were flipped.
+    ///
+    /// Triangles sharing an edge, vertices at the same position being treated as the same
+    /// vertex, are made to follow the winding of the first one. Each connected part is then
+    /// turned so its faces point away from the center of its bounding box on the whole, as they
+    /// do on closed surfaces. Normals of vertices only used by flipped triangles are flipped with
+    /// them if they pointed the wrong way.
+    pub fn fix_winding(&mut self) -> usize {
+        let ids = self.welded_ids();
+        let triangles = self.indices.len() / 3;
+        let corner = |t: usize, i: usize| self.indices[t * 3 + i] as usize;
+
+        // Triangles on each edge, and whether they go along it from the lower vertex id
+        let mut edges = HashMap::<_, Vec<(usize, bool)>>::new();
+        for t in 0..triangles {
+            for (i, j) in [(0, 1), (1, 2), (2, 0)] {
+                let (start, end) = (ids[corner(t, i)], ids[corner(t, j)]);
+                edges
+                    .entry((start.min(end), start.max(end)))
+                    .or_default()
+                    .push((t, start < end));
+            }
+        }
+
+        let mut flipped = vec![None; triangles];
+        for first in 0..triangles {
+            if flipped[first].is_some() {
+                continue;
+            }
+            flipped[first] = Some(false);
+            let mut part = vec![first];
+            let mut queue = VecDeque::from([first]);
+            while let Some(t) = queue.pop_front() {
+                let flip = flipped[t] == Some(true);
+                for (i, j) in [(0, 1), (1, 2), (2, 0)] {
+                    let (start, end) = (ids[corner(t, i)], ids[corner(t, j)]);
+                    // Only edges between two triangles tell which way they should go
+                    let [(a, a_forward), (b, b_forward)] =
+                        edges[&(start.min(end), start.max(end))][..]
+                    else {
+                        continue;
+                    };
+                    let (other, other_forward) = if a == t {
+                        (b, b_forward)
+                    } else {
+                        (a, a_forward)
+                    };
+                    if flipped[other].is_none() {
+                        // Consistent neighbours go along a shared edge in opposite directions
+                        flipped[other] = Some(other_forward == ((start < end) != flip));
+                        part.push(other);
+                        queue.push_back(other);
+                    }
+                }
+            }
+
+            // Area weighted normals pointing away from the center of the part, on the whole
+            let (min, max) = part
+                .iter()
+                .flat_map(|&t| (0..3).map(move |i| corner(t, i)))
+                .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), v| {
+                    (min.min(self.vertices[v]), max.max(self.vertices[v]))
+                });
+            let center = (min + max) * 0.5;
+            let outward = part
+                .iter()
+                .map(|&t| {
+                    let [a, b, c] = [0, 1, 2].map(|i| self.vertices[corner(t, i)]);
+                    let normal = (b - a).cross(c - a);
+                    let normal = if flipped[t] == Some(true) {
+                        -normal
+                    } else {
+                        normal
+                    };
+                    normal.dot((a + b + c) / 3.0 - center)
+                })
+                .sum::<f32>();
+            if outward < 0.0 {
+                for &t in &part {
+                    flipped[t] = flipped[t].map(|flip| !flip);
+                }
+            }
+        }
+
+        let mut shared = vec![false; self.vertices.len()];
+        for (t, flip) in flipped.iter().enumerate() {
+            if *flip == Some(false) {
+                for i in 0..3 {
+                    shared[corner(t, i)] = true;
+                }
+            }
+        }
+        let has_normals = self.normals.len() == self.vertices.len();
+        let mut count = 0;
+        for (t, flip) in flipped.into_iter().enumerate() {
+            if flip != Some(true) {
+                continue;
+            }
+            self.indices.swap(t * 3 + 1, t * 3 + 2);
+            count += 1;
+            if has_normals {
+                let [a, b, c] = [0, 1, 2].map(|i| self.vertices[self.indices[t * 3 + i] as usize]);
+                let normal = (b - a).cross(c - a);
+                for i in 0..3 {
+                    let v = self.indices[t * 3 + i] as usize;
+                    if !shared[v] && self.normals[v].dot(normal) < 0.0 {
+                        self.normals[v] = -self.normals[v];
+                    }
+                }
+            }
+        }
+        if count > 0 {
+            warn!("Flipped {count} triangles wound inconsistently");
+        }
+        count
+    }
+
+    /// Index of the first vertex at the same position as each vertex.
+    fn welded_ids(&self) -> Vec<usize> {
+        let mut welded = HashMap::new();
+        self.vertices
+            .iter()
+            .map(|v| {
+                let id = welded.len();
+                *welded.entry(v.to_array().map(f32::to_bits)).or_insert(id)
+            })
+            .collect()
+    }
+
+    /// Edges between the triangles, vertices at the same position being treated as the same
+    /// vertex. Edges of a single triangle or more than two, and edges where the triangles meet at
+    /// more than `crease_angle` radians are sharp. Edges between triangles in the same plane are
+    /// left out, as they are neither sharp nor ever on the silhouette.
+    #[must_use]
+    pub fn edges(&self, crease_angle: f32) -> Vec<Edge> {
+        let ids = self.welded_ids();
+
+        // Normals of the triangles on each edge, in the order the edges are first seen
+        let mut edge_index = HashMap::new();
+        let mut edges = Vec::<(Vec3, Vec3, Vec<Vec3>)>::new();
+        for face in self.indices.chunks_exact(3) {
+            let [a, b, c] = [face[0], face[1], face[2]].map(|i| self.vertices[i as usize]);
+            let normal = (b - a).cross(c - a).normalize_or_zero();
+            for (i, j) in [(0, 1), (1, 2), (2, 0)] {
+                let (start, end) = (face[i] as usize, face[j] as usize);
+                let key = (ids[start].min(ids[end]), ids[start].max(ids[end]));
+                let index = *edge_index.entry(key).or_insert_with(|| {
+                    edges.push((self.vertices[start], self.vertices[end], Vec::new()));
+                    edges.len() - 1
+                });
+                edges[index].2.push(normal);
+            }
+        }
+
+        let min_crease = crease_angle.cos();
+        edges
+            .into_iter()
+            .filter_map(|(start, end, normals)| {
+                let edge = |normals, sharp| Edge {
+                    start,
+                    end,
+                    normals,
+                    sharp,
+                };
+                match normals[..] {
+                    [first, second] => {
+                        let cos = first.dot(second);
+                        (cos < 0.9999).then(|| edge([first, second], cos < min_crease))
+                    }
+                    _ => Some(edge([normals[0]; 2], true)),
+                }
+            })
+            .collect()
+    }
+
+    /// Statistics about the mesh, with the bounds in the coordinates of the file.
+    #[must_use]
+    pub fn info(&self) -> MeshInfo {
+        MeshInfo {
+            format: self.format,
+            triangle_count: self.indices.len() / 3,
+            vertex_count: self.vertices.len(),
+            unique_vertex_count: self
+                .vertices
+                .iter()
+                .map(|v| v.to_array().map(f32::to_bits))
+                .collect::<HashSet<_>>()
+                .len(),
+            bounds: BoundingBox {
+                min: (self.bounds.min.as_dvec3() + self.origin).as_vec3(),
+                max: (self.bounds.max.as_dvec3() + self.origin).as_vec3(),
+            },
+            normals_recomputed: self.normals_recomputed,
+            stl_mode: self.stl_mode,
+            stl_colors: self.stl_colors,
+        }
+    }
+
+    // Move the mesh to be centered at the origin
+    // and scaled to fit a 2 x 2 x 2 box. This means that
+    // all coordinates will be between -1.0 and 1.0
+    #[must_use]
+    pub fn scale_and_center(&self) -> Mat4 {
+        // Move center to origin
+        let center = self.bounds.center();
+        let translation_vector = Vec3::new(-center.x, -center.y, -center.z);
+        let translation_matrix = Mat4::from_translation(translation_vector);
+        // Scale
+        let longest = self
+            .bounds
+            .length()
+            .max(self.bounds.width())
+            .max(self.bounds.height());
+        let scale_matrix = Mat4::from_scale(Vec3::splat(fit_scale(longest)));
+        scale_matrix * translation_matrix
+    }
+
+    /// Like [`Mesh::scale_and_center`], for the mesh rotated by `rotation` and centered on the
+    /// point picked by `center_mode`.
+    ///
+    /// The bounds are recomputed from the rotated vertices so the rotated model fits the box as
+    /// tightly as the original one. Models not centered on their bounding box are scaled so their
+    /// farthest vertex from the center still fits.
+    #[must_use]
+    pub fn rotate_scale_and_center(&self, rotation: Quat, center_mode: CenterMode) -> Mat4 {
+        let (center, longest) = match center_mode {
+            CenterMode::BoundingBox if rotation == Quat::IDENTITY => {
+                return self.scale_and_center();
+            }
+            CenterMode::BoundingBox => {
+                let (min, max) = self
+                    .vertices
+                    .iter()
+                    .map(|&v| rotation * v)
+                    .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), v| {
+                        (min.min(v), max.max(v))
+                    });
+                ((min + max) * 0.5, (max - min).max_element())
+            }
+            CenterMode::Centroid => {
+                let center = rotation * self.centroid();
+                let reach = self.vertices.iter().fold(Vec3::ZERO, |reach, &v| {
+                    reach.max((rotation * v - center).abs())
+                });
+                (center, reach.max_element() * 2.0)
+            }
+            CenterMode::BoundingSphere => {
+                let (center, radius) = self.bounding_sphere();
+                (rotation * center, radius * 2.0)
+            }
+        };
+        let translation_matrix = Mat4::from_translation(-center);
+        let scale_matrix = Mat4::from_scale(Vec3::splat(fit_scale(longest)));
+        scale_matrix * translation_matrix * Mat4::from_quat(rotation)
+    }
+
+    /// Center of the surface of the mesh, with each triangle weighted by its area.
+    ///
+    /// Meshes without area, like point clouds, use the average of their vertices instead.
+    #[must_use]
+    pub fn centroid(&self) -> Vec3 {
+        let (sum, area) =
+            self.indices
+                .chunks_exact(3)
+                .fold((Vec3::ZERO, 0.0), |(sum, total), triangle| {
+                    let [a, b, c] = [0, 1, 2].map(|i| self.vertices[triangle[i] as usize]);
+                    let area = (b - a).cross(c - a).length() * 0.5;
+                    (sum + (a + b + c) / 3.0 * area, total + area)
+                });
+        if area > 0.0 {
+            sum / area
+        } else if self.vertices.is_empty() {
+            self.bounds.center()
+        } else {
+            // Vertex counts of meshes that fit in memory are represented closely enough
+            #[allow(clippy::cast_precision_loss)]
+            let count = self.vertices.len() as f32;
+            self.vertices.iter().sum::<Vec3>() / count
+        }
+    }
+
+    /// Sphere holding every vertex, as its center and radius, close to the smallest one.
+    ///
+    /// Uses Ritter's algorithm: a sphere around the two vertices farthest apart is grown to
+    /// each vertex outside of it.
+    fn bounding_sphere(&self) -> (Vec3, f32) {
+        let Some(&first) = self.vertices.first() else {
+            return (self.bounds.center(), 0.0);
+        };
+        let farthest = |from: Vec3| {
+            self.vertices
+                .iter()
+                .copied()
+                .max_by(|a, b| {
+                    a.distance_squared(from)
+                        .total_cmp(&b.distance_squared(from))
+                })
+                .unwrap_or(from)
+        };
+        let a = farthest(first);
+        let b = farthest(a);
+
+        let mut center = (a + b) * 0.5;
+        let mut radius = a.distance(b) * 0.5;
+        for &v in &self.vertices {
+            let distance = v.distance(center);
+            if distance > radius {
+                let grown = (radius + distance) * 0.5;
+                center += (v - center) * ((distance - grown) / distance);
+                radius = grown;
+            }
+        }
+        (center, radius)
+    }
+
+    /// Moves the vertices of models far from the origin next to it, adding the offset to
+    /// `origin`.
+    ///
+    /// Models from surveying or GIS can have coordinates in the millions, where `f32` is left with
+    /// too few digits for the details of the model and the smoothed normals and the view jitter.
+    /// The vertices of STL and OBJ files are already `f32` when they are read, so the digits lost
+    /// then aren't restored, this only keeps the normals computed afterwards and the render from
+    /// losing more. 3MF coordinates are doubles, `mesh_from_3mf_parts` moves them before they are
+    /// narrowed instead.
+    fn recenter(&mut self) {
+        let origin = far_origin(self.bounds.min.as_dvec3(), self.bounds.max.as_dvec3());
+        if origin == DVec3::ZERO {
+            return;
+        }
+        let shift = |v: Vec3| (v.as_dvec3() - origin).as_vec3();
+        for v in &mut self.vertices {
+            *v = shift(*v);
+        }
+        self.bounds = BoundingBox {
+            min: shift(self.bounds.min),
+            max: shift(self.bounds.max),
+        };
+        self.origin += origin;
+    }
+
+    /// Calculates the normal of an indexed mesh, smoothing normals for shared vertices.
+    ///
+    /// Based on code from Bevy's `compute_normals` function.
+    /// <https://github.com/bevyengine/bevy/blob/v0.15.0-rc.1/crates/bevy_mesh/src/mesh.rs#L665-L714>
+    fn compute_smooth_normals(&mut self) {
+        #[cfg(not(feature = "parallel"))]
+        let normals = sum_face_normals(&self.vertices, &self.indices).into_iter();
+        #[cfg(feature = "parallel")]
+        let normals = par_sum_face_normals(&self.vertices, &self.indices).into_par_iter();
+
+        // Normalize the vertex normals
+        self.normals = normals.map(Vec3::normalize_or_zero).collect();
+        self.normals_recomputed = true;
+    }
+}
+
+/// Normal of a triangle, zero for triangles with no area so they don't turn the normals of their
+/// neighbours into NaN.
+fn face_normal(positions: &[Vec3], face: &[u32]) -> Vec3 {
+    let [a, b, c] = [face[0] as usize, face[1] as usize, face[2] as usize];
+    (positions[b] - positions[a])
+        .cross(positions[c] - positions[a])
+        .normalize_or_zero()
+}
+
+/// Sum of the normals of the triangles using each vertex, added in the order of the triangles.
+#[cfg(any(not(feature = "parallel"), test))]
+fn sum_face_normals(positions: &[Vec3], indices: &[u32]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::ZERO; positions.len()];
+    for face in indices.chunks_exact(3) {
+        let normal = face_normal(positions, face);
+        for &i in face {
+            normals[i as usize] += normal;
+        }
+    }
+    normals
+}
+
+/// Like [`sum_face_normals`], on all threads.
+///
+/// Each vertex gathers the normals of its triangles from a list of them, in the order of the
+/// triangles, so the sums are the same as the serial ones whatever the number of threads.
+#[cfg(feature = "parallel")]
+fn par_sum_face_normals(positions: &[Vec3], indices: &[u32]) -> Vec<Vec3> {
+    let face_normals = indices
+        .par_chunks_exact(3)
+        .map(|face| face_normal(positions, face))
+        .collect::<Vec<_>>();
+
+    // The triangles of vertex `v` are `faces[starts[v]..starts[v + 1]]`
+    let mut starts = vec![0; positions.len() + 1];
+    for &i in indices {
+        starts[i as usize + 1] += 1;
+    }
+    for v in 0..positions.len() {
+        starts[v + 1] += starts[v];
+    }
+    let mut faces = vec![0; indices.len()];
+    let mut next = starts.clone();
+    for (face, vertices) in indices.chunks_exact(3).enumerate() {
+        for &i in vertices {
+            faces[next[i as usize]] = face;
+            next[i as usize] += 1;
+        }
+    }
+
+    starts
+        .par_windows(2)
+        .map(|range| {
+            faces[range[0]..range[1]]
+                .iter()
+                .fold(Vec3::ZERO, |normal, &face| normal + face_normals[face])
+        })
+        .collect()
+}
+
+/// Adapts an iterator of triangles so `stl_io` can index them.
+struct Triangles<I>(I);
+
+impl<I> Iterator for Triangles<I>
+where
+    I: Iterator<Item = io::Result<Triangle>>,
+{
+    type Item = io::Result<Triangle>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+impl<I> TriangleIterator for Triangles<I> where I: Iterator<Item = io::Result<Triangle>> {}
+
+/// Distance from the origin, in sizes of the model, past which models are moved next to it.
+const RECENTER_DISTANCE: f64 = 100.0;
+
+/// Scale fitting a model whose longest side is `longest` in a 2 x 2 x 2 box.
+///
+/// Models collapsed to a single point have no size to fit, they are left unscaled instead of
+/// producing an infinite or NaN matrix.
+fn fit_scale(longest: f32) -> f32 {
+    let scale = 2.0 / longest;
+    if scale.is_finite() {
+        scale
+    } else {
+        1.0
+    }
+}
+
+/// Offset moving a model with bounds `min` and `max` next to the origin, zero unless the model is
+/// more than [`RECENTER_DISTANCE`] times its size away from it.
+fn far_origin(min: DVec3, max: DVec3) -> DVec3 {
+    let center = (min + max) * 0.5;
+    if center.is_finite()
+        && center.abs().max_element() > (max - min).max_element() * RECENTER_DISTANCE
+    {
+        center
+    } else {
+        DVec3::ZERO
+    }
+}
+
+/// Whether a binary STL file has colors, from a `COLOR=` default color in its header or non-zero
+/// attribute bytes, where the common color extensions store the triangle colors.
+/// Only the attributes of the first triangles are checked, the reader is left at the start.
+fn has_stl_colors<R>(model_file: &mut R) -> io::Result<bool>
+where
+    R: Read + Seek,
+{
+    const SAMPLED_TRIANGLES: u64 = 256;
+
+    model_file.rewind()?;
+    let mut data = Vec::new();
+    model_file
+        .take(84 + SAMPLED_TRIANGLES * 50)
+        .read_to_end(&mut data)?;
+    model_file.rewind()?;
+
+    let header = &data[..data.len().min(80)];
+    let colored_header = header.windows(6).any(|w| w == b"COLOR=");
+    let colored_triangles = data
+        .get(84..)
+        .unwrap_or_default()
+        .chunks_exact(50)
+        .any(|face| face[48..] != [0, 0]);
+    Ok(colored_header || colored_triangles)
+}
+
+/// Parses a binary STL file, ignoring the contents of the header.
+fn read_binary_stl<R>(mut model_file: R) -> Result<IndexedMesh, MeshError>
+where
+    R: Read,
+{
+    let mut buffer = Vec::new();
+    model_file.read_to_end(&mut buffer)?;
+
+    let invalid = |message: &str| MeshError::InvalidStl(message.to_string());
+    let count = buffer
+        .get(80..84)
+        .and_then(|count| count.try_into().ok())
+        .map(u32::from_le_bytes)
+        .ok_or_else(|| invalid("binary STL header is truncated"))?;
+    let faces = buffer
+        .get(84..)
+        .and_then(|faces| faces.get(..count as usize * 50))
+        .ok_or_else(|| invalid("binary STL has less triangles than its header claims"))?;
+
+    // Each triangle is a normal and 3 vertices of 3 little-endian floats, then 2 attribute bytes
+    let vector = |bytes: &[u8]| {
+        let mut floats = bytes
+            .chunks_exact(4)
+            .map(|f| f32::from_le_bytes([f[0], f[1], f[2], f[3]]));
+        [(); 3].map(|()| floats.next().unwrap_or_default())
+    };
+    let triangles = faces.chunks_exact(50).map(|face| {
+        Ok(Triangle {
+            normal: Normal::new(vector(&face[0..12])),
+            vertices: [
+                Vector::new(vector(&face[12..24])),
+                Vector::new(vector(&face[24..36])),
+                Vector::new(vector(&face[36..48])),
+            ],
+        })
+    });
+
+    Ok(Triangles(triangles).as_indexed_triangles()?)
+}
+
+/// Parses an ASCII STL file, without requiring the `solid ` header.
+fn read_ascii_stl<R>(mut model_file: R) -> Result<IndexedMesh, MeshError>
+where
+    R: Read,
+{
+    let mut text = String::new();
+    model_file
+        .read_to_string(&mut text)
+        .map_err(|e| MeshError::InvalidStl(e.to_string()))?;
+
+    let mut words = text.split_whitespace();
+    let vector = |words: &mut std::str::SplitWhitespace, keyword: &str| {
+        let mut coords = words.take(3).map(str::parse::<f32>);
+        let mut coord = || match coords.next() {
+            Some(Ok(c)) => Ok(c),
+            _ => Err(MeshError::InvalidStl(format!(
+                "invalid {keyword} in ASCII STL"
+            ))),
+        };
+        Ok::<_, MeshError>([coord()?, coord()?, coord()?])
+    };
+
+    let mut triangles = Vec::new();
+    let mut normal = None;
+    let mut vertices = Vec::with_capacity(3);
+    while let Some(word) = words.next() {
+        match word {
+            "normal" => {
+                normal = Some(vector(&mut words, "normal")?);
+                vertices.clear();
+            }
+            "vertex" => {
+                vertices.push(Vector::new(vector(&mut words, "vertex")?));
+                if let Ok(face) = <[Vector<f32>; 3]>::try_from(vertices.as_slice()) {
+                    triangles.push(Ok(Triangle {
+                        normal: Normal::new(normal.take().unwrap_or_default()),
+                        vertices: face,
+                    }));
+                    vertices.clear();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(Triangles(triangles.into_iter()).as_indexed_triangles()?)
+}
+
+/// Colors of each triangle corner, grouped by model and object in document order.
+type ThreemfColors = Vec<Vec<Vec<[Vec3; 3]>>>;
+
+/// Components can refer to objects made of components in turn, cycles are cut off at this depth.
+const MAX_3MF_COMPONENT_DEPTH: usize = 32;
+
+/// Reads the models of a 3MF file and the colors of their triangles.
+fn read_3mf<R>(mut model_file: R) -> Result<(Vec<threemf::model::Model>, ThreemfColors), MeshError>
+where
+    R: Read + Seek,
+{
+    // Encrypted parts would otherwise fail to parse with an obscure error
+    if is_encrypted_3mf(&mut model_file) {
+        return Err(MeshError::EncryptedThreemf);
+    }
+    model_file.rewind()?;
+    let models = threemf::read(&mut model_file)?;
+    model_file.rewind()?;
+    let colors = read_3mf_colors(model_file)?;
+    Ok((models, colors))
+}
+
+/// Matrix of a 3MF transform, the 3x4 matrix of a row vector convention listed row by row.
+fn threemf_transform(transform: Option<[f64; 12]>) -> DMat4 {
+    transform.map_or(DMat4::IDENTITY, |t| {
+        DMat4::from_cols_array(&[
+            t[0], t[1], t[2], 0.0, t[3], t[4], t[5], 0.0, t[6], t[7], t[8], 0.0, t[9], t[10],
+            t[11], 1.0,
+        ])
+    })
+}
+
+/// Mesh of a 3MF object placed by a transform, with the colors of its triangles.
+struct ThreemfPart<'a> {
+    mesh: &'a threemf::model::Mesh,
+    colors: Option<&'a Vec<[Vec3; 3]>>,
+    transform: DMat4,
+}
+
+/// Adds the mesh of the 3MF object `id`, and of the objects its components refer to, placed by
+/// `transform` to `parts`.
+fn collect_3mf_parts<'a>(
+    parts: &mut Vec<ThreemfPart<'a>>,
+    model: &'a threemf::model::Model,
+    colors: Option<&'a Vec<Vec<[Vec3; 3]>>>,
+    id: usize,
+    transform: DMat4,
+    depth: usize,
+) -> Result<(), MeshError> {
+    if depth > MAX_3MF_COMPONENT_DEPTH {
+        return Err(MeshError::InvalidThreemf(format!(
+            "Components of object {id} are nested too deeply"
+        )));
+    }
+    let (o, object) = model
+        .resources
+        .object
+        .iter()
+        .enumerate()
+        .find(|(_, object)| object.id == id)
+        .ok_or_else(|| MeshError::InvalidThreemf(format!("Object {id} doesn't exist")))?;
+
+    if let Some(mesh) = &object.mesh {
+        parts.push(ThreemfPart {
+            mesh,
+            colors: colors.and_then(|model| model.get(o)),
+            transform,
+        });
+    }
+    for component in object.components.iter().flat_map(|c| &c.component) {
+        collect_3mf_parts(
+            parts,
+            model,
+            colors,
+            component.objectid,
+            transform * threemf_transform(component.transform),
+            depth + 1,
+        )?;
+    }
+    Ok(())
+}
+
+/// Combines 3MF parts into a mesh, each list of parts being an object of it.
+fn mesh_from_3mf_parts(objects: &[Vec<ThreemfPart>]) -> Result<Mesh, MeshError> {
+    let position = |part: &ThreemfPart, index: usize| {
+        let v = &part.mesh.vertices.vertex[index];
+        part.transform.transform_point3(DVec3::new(v.x, v.y, v.z))
+    };
+
+    // 3MF coordinates are doubles, models far from the origin are moved next to it before they
+    // are narrowed so they keep their precision
+    let (min, max) = objects
+        .iter()
+        .flatten()
+        .flat_map(|part| (0..part.mesh.vertices.vertex.len()).map(|i| position(part, i)))
+        .fold((DVec3::INFINITY, DVec3::NEG_INFINITY), |(min, max), v| {
+            (min.min(v), max.max(v))
+        });
+    let origin = far_origin(min, max);
+
+    let mut vertices = Vec::new();
+    let mut colors = Vec::new();
+    let mut object_starts = vec![0];
+    for object in objects {
+        if !vertices.is_empty() {
+            object_starts.push(vertices.len());
+        }
+        for part in object {
+            // Every triangle gets its own 3 vertices, like STL files
+            for (i, triangle) in part.mesh.triangles.triangle.iter().enumerate() {
+                vertices.extend(
+                    [triangle.v1, triangle.v2, triangle.v3]
+                        .map(|index| (position(part, index) - origin).as_vec3()),
+                );
+                colors.extend(
+                    part.colors
+                        .and_then(|triangles| triangles.get(i))
+                        .unwrap_or(&[Vec3::ONE; 3]),
+                );
+            }
+        }
+    }
+
+    let first = *vertices.first().ok_or(MeshError::NoMeshData)?;
+    let bounds = vertices.iter().fold(
+        BoundingBox {
+            min: first,
+            max: first,
+        },
+        |bounds, &v| BoundingBox {
+            min: bounds.min.min(v),
+            max: bounds.max.max(v),
+        },
+    );
+    let vertex_count =
+        u32::try_from(vertices.len()).map_err(|e| MeshError::InvalidThreemf(e.to_string()))?;
+    let mut mesh = Mesh {
+        vertices,
+        normals: Vec::new(),
+        colors,
+        indices: (0..vertex_count).collect(),
+        bounds,
+        format: Some(InputFormat::Threemf),
+        normals_recomputed: false,
+        stl_mode: None,
+        stl_colors: false,
+        object_starts,
+        origin,
+    };
+    // 3MF files don't have normals, so we need to calculate them.
+    mesh.compute_smooth_normals();
+    Ok(mesh)
+}
+
+/// Format of the model file, from `format` if provided, otherwise from the extension of the file
+/// or guessed from its contents.
+#[cfg(not(target_arch = "wasm32"))]
+fn file_format(
+    model_filename: &Path,
+    model_file: &mut File,
+    format: Option<InputFormat>,
+) -> Result<InputFormat, MeshError> {
+    if let Some(format) = format.or_else(|| {
+        model_filename
+            .extension()
+            .and_then(|s| s.to_str())
+            .and_then(InputFormat::from_extension)
+    }) {
+        return Ok(format);
+    }
+    let mut header = Vec::with_capacity(512);
+    model_file.take(512).read_to_end(&mut header)?;
+    model_file.rewind()?;
+    InputFormat::sniff(&header, model_file.metadata()?.len()).ok_or(MeshError::UnsupportedFormat)
+}
+
+/// Largest model [`Mesh::from_uri`] downloads, in bytes.
+#[cfg(all(feature = "uri", not(target_arch = "wasm32")))]
+const MAX_DOWNLOAD_SIZE: u64 = 256 << 20;
+
+/// Time [`Mesh::from_uri`] waits to connect and for each read of a download.
+#[cfg(all(feature = "uri", not(target_arch = "wasm32")))]
+const DOWNLOAD_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Local path of a `file://` URI, other schemes are refused.
+#[cfg(all(feature = "uri", not(target_arch = "wasm32")))]
+pub fn file_uri_path(uri: &str) -> Result<String, MeshError> {
+    let url = url::Url::parse(uri).map_err(|e| MeshError::InvalidUri(format!("{uri}: {e}")))?;
+    if url.scheme() != "file" {
+        return Err(MeshError::InvalidUri(format!(
+            "{uri} is not a local file, remote models are only loaded by Mesh::from_uri"
+        )));
+    }
+    url.to_file_path()
+        .map_err(|()| MeshError::InvalidUri(format!("{uri} is not a local path")))?
+        .into_os_string()
+        .into_string()
+        .map_err(|_| MeshError::InvalidUri(format!("{uri} is not valid UTF-8")))
+}
+
+/// Runs `load` on a separate thread, so reading and parsing a model doesn't block the async
+/// executor.
+#[cfg(not(target_arch = "wasm32"))]
+async fn on_thread<F>(load: F) -> Result<Mesh, MeshError>
+where
+    F: FnOnce() -> Result<Mesh, MeshError> + Send + panic::UnwindSafe + 'static,
+{
+    let loaded = CallbackFuture::default();
+    let callback = loaded.callback();
+    thread::spawn(move || {
+        // Panics are passed on to the caller instead of leaving the future pending forever
+        callback(panic::catch_unwind(load));
+    });
+    loaded
+        .await
+        .unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
+
+/// Names the model file in IO errors, they are hard to make sense of otherwise.
+#[cfg(not(target_arch = "wasm32"))]
+fn name_file(error: MeshError, model_filename: &Path) -> MeshError {
+    match error {
+        MeshError::Io(source) => MeshError::File {
+            path: model_filename.to_path_buf(),
+            source,
+        },
+        e => e,
+    }
+}
+
+/// Contents of the material libraries an OBJ model in `material_dir` references, in order, with
+/// `None` for the ones that can't be read. They are looked up like
+/// [`Mesh::from_obj_with_materials`] does.
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+pub fn obj_material_libraries(obj: &[u8], material_dir: &Path) -> Vec<Option<Vec<u8>>> {
+    obj.split(|&b| b == b'\n')
+        .filter_map(|line| {
+            let line = std::str::from_utf8(line).ok()?.trim();
+            // Names can include spaces, like tobj the rest of the line is the name
+            let (keyword, name) = line.split_once(' ')?;
+            (keyword == "mtllib").then(|| {
+                companion_path(material_dir, Path::new(name.trim()))
+                    .and_then(std::fs::read)
+                    .ok()
+            })
+        })
+        .collect()
+}
+
+/// Path of the file `name` referenced by a model in `base_dir`, like an OBJ material library.
+/// Absolute paths, `..` components and symbolic links leading out of `base_dir` are refused.
+fn companion_path(base_dir: &Path, name: &Path) -> io::Result<PathBuf> {
+    let outside = || {
+        io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "the path leads outside of the model directory",
+        )
+    };
+    if !name
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(outside());
+    }
+    let path = base_dir.join(name);
+    if !path.canonicalize()?.starts_with(base_dir.canonicalize()?) {
+        return Err(outside());
+    }
+    Ok(path)
+}
+
+/// Whether the 3MF archive holds parts encrypted with a zip password, or uses the secure content
+/// extension, which keeps the keys to its encrypted parts in a keystore part. Data that isn't a
+/// zip archive is left for `threemf::read` to reject.
+fn is_encrypted_3mf<R>(model_file: R) -> bool
+where
+    R: Read + Seek,
+{
+    let Ok(mut zip) = ZipArchive::new(model_file) else {
+        return false;
+    };
+    (0..zip.len()).any(|i| {
+        zip.by_index_raw(i).is_ok_and(|file| {
+            file.encrypted()
+                || file
+                    .name()
+                    .rsplit('/')
+                    .next()
+                    .is_some_and(|name| name.eq_ignore_ascii_case("keystore.xml"))
+        })
+    })
+}
+
+/// Reads the vertex colors referenced by the triangles of a 3MF file.
+///
+/// The `threemf` crate doesn't parse material resources, so the model files are scanned a second
+/// time for `<basematerials>` and `<colorgroup>` entries and the `pid`/`p1`/`p2`/`p3` properties
+/// of objects and triangles. Triangles without a resolvable color are white.
+fn read_3mf_colors<R>(model_file: R) -> Result<ThreemfColors, threemf::Error>
+where
+    R: Read + Seek,
+{
+    let mut zip = ZipArchive::new(model_file)?;
+    let mut models = Vec::new();
+
+    // Walk the archive in the same order as `threemf::read` so models line up
+    for i in 0..zip.len() {
+        let file = zip.by_index(i)?;
+        #[allow(clippy::case_sensitive_file_extension_comparisons)]
+        if !file.name().ends_with(".model") {
+            continue;
+        }
+
+        let mut reader = quick_xml::Reader::from_reader(BufReader::new(file));
+        let mut buf = Vec::new();
+        let mut groups: HashMap<String, Vec<Vec3>> = HashMap::new();
+        let mut group = None;
+        let mut object_property = (None, None);
+        let mut objects: Vec<Vec<[Vec3; 3]>> = Vec::new();
+
+        loop {
+            match reader.read_event_into(&mut buf)? {
+                Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                    b"basematerials" | b"colorgroup" => {
+                        group = attribute(&e, b"id")?;
+                    }
+                    b"base" | b"color" => {
+                        let name: &[u8] = if e.local_name().as_ref() == b"base" {
+                            b"displaycolor"
+                        } else {
+                            b"color"
+                        };
+                        if let (Some(id), Some(color)) = (&group, attribute(&e, name)?) {
+                            groups
+                                .entry(id.clone())
+                                .or_default()
+                                .push(parse_3mf_color(&color).unwrap_or(Vec3::ONE));
+                        }
+                    }
+                    b"object" => {
+                        objects.push(Vec::new());
+                        object_property = (attribute(&e, b"pid")?, attribute(&e, b"pindex")?);
+                    }
+                    b"triangle" => {
+                        let Some(triangles) = objects.last_mut() else {
+                            continue;
+                        };
+                        let pid = attribute(&e, b"pid")?.or_else(|| object_property.0.clone());
+                        let p1 = attribute(&e, b"p1")?.or_else(|| object_property.1.clone());
+                        let p2 = attribute(&e, b"p2")?.or_else(|| p1.clone());
+                        let p3 = attribute(&e, b"p3")?.or_else(|| p1.clone());
+                        let group = pid.and_then(|pid| groups.get(&pid));
+                        triangles.push([p1, p2, p3].map(|index| {
+                            group
+                                .zip(index.and_then(|i| i.parse::<usize>().ok()))
+                                .and_then(|(colors, i)| colors.get(i))
+                                .copied()
+                                .unwrap_or(Vec3::ONE)
+                        }));
+                    }
+                    _ => {}
+                },
+                Event::End(e)
+                    if matches!(e.local_name().as_ref(), b"basematerials" | b"colorgroup") =>
+                {
+                    group = None;
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+
+        models.push(objects);
+    }
+
+    Ok(models)
+}
+
+/// Returns the unescaped value of an XML attribute, if present.
+fn attribute(element: &BytesStart, name: &[u8]) -> Result<Option<String>, quick_xml::Error> {
+    element
+        .try_get_attribute(name)?
+        .map(|attr| attr.unescape_value().map(std::borrow::Cow::into_owned))
+        .transpose()
+}
+
+/// Parses a 3MF `#RRGGBB` or `#RRGGBBAA` color, ignoring the alpha channel.
+fn parse_3mf_color(color: &str) -> Option<Vec3> {
+    let hex = color.strip_prefix('#')?;
+    if !matches!(hex.len(), 6 | 8) {
+        return None;
+    }
+    let channel = |i: usize| {
+        u8::from_str_radix(hex.get(i..i + 2)?, 16)
+            .ok()
+            .map(|c| f32::from(c) / 255.0)
+    };
+    Some(Vec3::new(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Smoothing group of each triangle of an OBJ file in file order, with polygons split in
+/// triangles like tobj does, or `None` if the file has no `s` statements. Faces of group 0, or
+/// `s off`, aren't smoothed.
+fn obj_smoothing_groups(buffer: &[u8]) -> Option<Vec<u32>> {
+    let text = std::str::from_utf8(buffer).ok()?;
+    let mut found = false;
+    let mut group = 0;
+    let mut groups = Vec::new();
+    for line in text.lines() {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("s") => {
+                found = true;
+                group = words.next().and_then(|g| g.parse().ok()).unwrap_or(0);
+            }
+            Some("f") => {
+                let triangles = words.count().saturating_sub(2);
+                groups.extend(std::iter::repeat_n(group, triangles));
+            }
+            _ => {}
+        }
+    }
+    found.then_some(groups)
+}
+
+/// Gives the faces of each smoothing group their own copies of the vertices they share with
+/// other groups, so their normals are smoothed separately. Faces of group 0 aren't smoothed at
+/// all and get their own copy of every vertex.
+///
+/// Returns the new indices, and the vertex each new vertex is a copy of.
+fn split_smoothing_groups(indices: &[u32], face_groups: &[u32]) -> (Vec<u32>, Vec<usize>) {
+    let mut copies = HashMap::new();
+    let mut sources = Vec::new();
+    let mut split = Vec::with_capacity(indices.len());
+    for (face, (vertices, &group)) in indices.chunks_exact(3).zip(face_groups).enumerate() {
+        for &vertex in vertices {
+            let key = (vertex, group, if group == 0 { face } else { 0 });
+            let copy = *copies.entry(key).or_insert_with(|| {
+                sources.push(vertex as usize);
+                sources.len() - 1
+            });
+            // There are at most as many copies as indices, which tobj counts in u32
+            #[allow(clippy::cast_possible_truncation)]
+            split.push(copy as u32);
+        }
+    }
+    (split, sources)
+}
+
+/// Color of the object at `index` for [`Mesh::color_objects`]. Hues are spaced by the golden
+/// angle, so neighbouring objects get very different colors however many there are.
+fn object_color(index: usize) -> Vec3 {
+    const GOLDEN_RATIO_CONJUGATE: f32 = 0.618_034;
+    #[allow(clippy::cast_precision_loss)]
+    let hue = (index as f32 * GOLDEN_RATIO_CONJUGATE).fract() * 6.0;
+    // Fully saturated, as the colors are multiplied with the bluish model color
+    let channel = |offset: f32| (((hue + offset) % 6.0 - 3.0).abs() - 1.0).clamp(0.0, 1.0);
+    Vec3::new(channel(0.0), channel(4.0), channel(2.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_stl(filename: &str, stl_mode: StlMode) -> Result<Mesh, MeshError> {
+        Mesh::load(filename, None, stl_mode, &ObjectSelector::All, false)
+    }
+
+    #[test]
+    fn ascii_and_binary_stl() {
+        let binary = load_stl("test/data/cube.stl", StlMode::Auto).expect("Error loading mesh");
+        let ascii =
+            load_stl("test/data/cube_ascii.stl", StlMode::Auto).expect("Error loading mesh");
+
+        assert_eq!(binary.stl_mode, Some(StlMode::Binary));
+        assert_eq!(ascii.stl_mode, Some(StlMode::Ascii));
+        assert_eq!(binary.vertices, ascii.vertices);
+        assert_eq!(binary.indices, ascii.indices);
+    }
+
+    #[test]
+    fn unique_vertices() {
+        let info = load_stl("test/data/cube.stl", StlMode::Auto)
+            .expect("Error loading mesh")
+            .info();
+
+        // Every triangle has its own copy of the corners of the cube
+        assert_eq!(info.vertex_count, 36);
+        assert_eq!(info.unique_vertex_count, 8);
+    }
+
+    #[test]
+    fn stl_colors() {
+        let plain = load_stl("test/data/cube.stl", StlMode::Auto).expect("Error loading mesh");
+        assert!(!plain.info().stl_colors);
+        let colored =
+            load_stl("test/data/cube_colors.stl", StlMode::Auto).expect("Error loading mesh");
+        assert!(colored.info().stl_colors);
+
+        // A default color in the header is enough, even without triangle colors
+        let mut data = std::fs::read("test/data/cube.stl").expect("Error reading file");
+        data[..10].copy_from_slice(b"COLOR=\xff\x00\x00\xff");
+        let header = Mesh::from_stl(io::Cursor::new(data), StlMode::Auto, false)
+            .expect("Error loading mesh");
+        assert!(header.info().stl_colors);
+    }
+
+    #[test]
+    fn forced_stl_mode() {
+        let binary = load_stl("test/data/cube.stl", StlMode::Auto).expect("Error loading mesh");
+
+        // The header of this binary file starts with `solid `, which fools the detection
+        assert!(load_stl("test/data/cube_solid_header.stl", StlMode::Auto)
+            .map_or(true, |mesh| mesh.vertices != binary.vertices));
+        let forced = load_stl("test/data/cube_solid_header.stl", StlMode::Binary)
+            .expect("Error loading mesh");
+        assert_eq!(forced.stl_mode, Some(StlMode::Binary));
+        assert_eq!(forced.vertices, binary.vertices);
+
+        // Unnamed solids have no space after `solid`, so they are detected as binary
+        let unnamed = std::fs::read_to_string("test/data/cube_ascii.stl")
+            .expect("Error reading file")
+            .replacen("solid cube", "solid", 1);
+        let ascii = Mesh::from_stl(Cursor::new(unnamed), StlMode::Ascii, false)
+            .expect("Error loading mesh");
+        assert_eq!(ascii.stl_mode, Some(StlMode::Ascii));
+        assert_eq!(ascii.vertices, binary.vertices);
+    }
+
+    #[tokio::test]
+    async fn load_async() {
+        let sync = load_stl("test/data/cube.stl", StlMode::Auto).expect("Error loading mesh");
+        let loaded = Mesh::load_async(
+            "test/data/cube.stl",
+            None,
+            StlMode::Auto,
+            &ObjectSelector::All,
+            false,
+        )
+        .await
+        .expect("Error loading mesh");
+        assert_eq!(loaded.vertices, sync.vertices);
+        assert_eq!(loaded.normals, sync.normals);
+        assert_eq!(loaded.indices, sync.indices);
+
+        assert!(Mesh::load_async(
+            "test/data/missing.stl",
+            None,
+            StlMode::Auto,
+            &ObjectSelector::All,
+            false,
+        )
+        .await
+        .is_err());
+    }
+
+    #[cfg(feature = "uri")]
+    #[test]
+    fn file_uri() {
+        let path = std::fs::canonicalize("test/data/cube.stl").expect("Error resolving path");
+        let uri = url::Url::from_file_path(path).expect("Error building URI");
+        let mesh = Mesh::from_uri(
+            uri.as_str(),
+            None,
+            StlMode::Auto,
+            &ObjectSelector::All,
+            false,
+        )
+        .expect("Error loading mesh");
+        let binary = load_stl("test/data/cube.stl", StlMode::Auto).expect("Error loading mesh");
+        assert_eq!(mesh.vertices, binary.vertices);
+        let mesh = Mesh::load(
+            uri.as_str(),
+            None,
+            StlMode::Auto,
+            &ObjectSelector::All,
+            false,
+        )
+        .expect("Error loading mesh");
+        assert_eq!(mesh.vertices, binary.vertices);
+
+        // Only explicit calls of `from_uri` download models
+        assert!(matches!(
+            Mesh::load(
+                "http://127.0.0.1:9/cube.stl",
+                None,
+                StlMode::Auto,
+                &ObjectSelector::All,
+                false,
+            ),
+            Err(MeshError::InvalidUri(_))
+        ));
+        assert!(matches!(
+            Mesh::from_uri(
+                "ftp://example.com/cube.stl",
+                None,
+                StlMode::Auto,
+                &ObjectSelector::All,
+                false,
+            ),
+            Err(MeshError::InvalidUri(_))
+        ));
+    }
+
+    #[test]
+    fn error_messages() {
+        let error = Mesh::from_obj(
+            Cursor::new("v 1 2 x\nf 1 2 3\n"),
+            &ObjectSelector::All,
+            false,
+        )
+        .expect_err("Corrupt OBJ should fail to load");
+        let message = error.to_string();
+        assert!(message.contains("OBJ"), "{message}");
+        assert!(!message.contains("STL"), "{message}");
+
+        let error = load_stl("test/data/missing.stl", StlMode::Auto)
+            .expect_err("Missing file should fail to load");
+        assert!(error.to_string().contains("test/data/missing.stl"));
+    }
+
+    #[test]
+    fn encrypted_3mf() {
+        // Copy of the cube with `extra` parts added
+        let archive = |extra: &[&str]| {
+            let mut source =
+                ZipArchive::new(File::open("test/data/cube.3mf").expect("Error opening file"))
+                    .expect("Error reading archive");
+            let mut archive = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+            for i in 0..source.len() {
+                archive
+                    .raw_copy_file(source.by_index(i).expect("Error reading archive"))
+                    .expect("Error writing archive");
+            }
+            for name in extra {
+                archive
+                    .start_file(*name, zip::write::SimpleFileOptions::default())
+                    .expect("Error writing archive");
+            }
+            archive
+                .finish()
+                .expect("Error writing archive")
+                .into_inner()
+        };
+        let load =
+            |data: Vec<u8>| Mesh::from_3mf(io::Cursor::new(data), &ObjectSelector::All, false);
+
+        load(archive(&[])).expect("Error loading mesh");
+        let error = load(archive(&["Secure/keystore.xml"]))
+            .expect_err("Secure content should fail to load");
+        assert!(matches!(error, MeshError::EncryptedThreemf), "{error}");
+
+        // Set the encrypted flag of every part, in the local and central directory headers
+        let mut data = archive(&[]);
+        for i in 0..data.len() - 4 {
+            match &data[i..i + 4] {
+                b"PK\x03\x04" => data[i + 6] |= 1,
+                b"PK\x01\x02" => data[i + 8] |= 1,
+                _ => {}
+            }
+        }
+        let error = load(data).expect_err("Encrypted parts should fail to load");
+        assert!(matches!(error, MeshError::EncryptedThreemf), "{error}");
+    }
+
+    #[test]
+    fn threemf_build_items() {
+        let meshes = Mesh::load_all(
+            "test/data/two_items.3mf",
+            None,
+            StlMode::Auto,
+            &ObjectSelector::All,
+            false,
+        )
+        .expect("Error loading meshes");
+
+        // A cube moved along X, then a pair of cubes scaled twice as large
+        assert_eq!(meshes.len(), 2);
+        assert_eq!(meshes[0].bounds.min, Vec3::new(-5.0, 0.0, 0.0));
+        assert_eq!(meshes[0].bounds.max, Vec3::new(-4.0, 1.0, 1.0));
+        assert_eq!(meshes[0].info().triangle_count, 12);
+        assert_eq!(meshes[1].bounds.min, Vec3::new(3.0, 0.0, 0.0));
+        assert_eq!(meshes[1].bounds.max, Vec3::new(5.0, 6.0, 2.0));
+        assert_eq!(meshes[1].info().triangle_count, 24);
+
+        let selected = Mesh::load_all(
+            "test/data/two_items.3mf",
+            None,
+            StlMode::Auto,
+            &ObjectSelector::ByName("Pair".to_string()),
+            false,
+        )
+        .expect("Error loading meshes");
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].bounds.max, meshes[1].bounds.max);
+
+        // Other formats come back as a single mesh
+        let stl = Mesh::load_all(
+            "test/data/cube.stl",
+            None,
+            StlMode::Auto,
+            &ObjectSelector::All,
+            false,
+        )
+        .expect("Error loading meshes");
+        assert_eq!(stl.len(), 1);
+    }
+
+    #[test]
+    fn threemf_build_item_far_from_origin() {
+        let load = |filename| {
+            Mesh::load_all(filename, None, StlMode::Auto, &ObjectSelector::All, false)
+                .expect("Error loading meshes")
+                .remove(0)
+        };
+        let near = load("test/data/cube.3mf");
+        // The same tiny cube, placed 1000 units away by its build item
+        let far = load("test/data/cube_far.3mf");
+
+        assert!(far.origin.abs().min_element() > 999.0);
+        let offset = far.origin - DVec3::splat(1000.0);
+        for (near, far) in near.vertices.iter().zip(&far.vertices) {
+            let far = (far.as_dvec3() + offset).as_vec3();
+            assert!(near.abs_diff_eq(far, 1e-6), "{near} != {far}");
+        }
+    }
+
+    #[test]
+    fn obj_materials() {
+        let obj =
+            |mtllib: &str| format!("{mtllib}v 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n");
+        let load = |obj: String| {
+            Mesh::from_obj_with_materials(
+                Cursor::new(obj),
+                Some(Path::new("test/data")),
+                &ObjectSelector::All,
+                false,
+            )
+        };
+
+        // Without a material library, or with one that doesn't exist, the faces are white
+        for mtllib in ["", "mtllib missing.mtl\n"] {
+            let mesh = load(obj(mtllib)).expect("Error loading mesh");
+            assert_eq!(mesh.colors, vec![Vec3::ONE; 3], "{mtllib}");
+        }
+
+        let mesh = load(obj("mtllib two_materials.mtl\n")).expect("Error loading mesh");
+        assert_eq!(mesh.colors, vec![Vec3::X; 3]);
+        let mesh = Mesh::load(
+            "test/data/two_materials.obj",
+            None,
+            StlMode::Auto,
+            &ObjectSelector::All,
+            false,
+        )
+        .expect("Error loading mesh");
+        assert!(mesh.colors.contains(&Vec3::X));
+        assert!(mesh.colors.contains(&Vec3::Z));
+        // Faces with another material are still part of the same object
+        assert_eq!(mesh.object_starts, vec![0]);
+
+        // A material library that exists but can't be parsed is reported
+        let error = load(obj("mtllib invalid.mtl\n")).expect_err("Invalid MTL should fail to load");
+        assert!(matches!(error, MeshError::InvalidObj(_)));
+        assert!(error.to_string().contains("invalid.mtl"), "{error}");
+    }
+
+    #[test]
+    fn obj_vertex_colors() {
+        let mesh = Mesh::load(
+            "test/data/vertex_colors.obj",
+            None,
+            StlMode::Auto,
+            &ObjectSelector::All,
+            false,
+        )
+        .expect("Error loading mesh");
+        assert_eq!(mesh.colors.len(), mesh.vertices.len());
+        for (vertex, color) in mesh.vertices.iter().zip(&mesh.colors) {
+            let expected = if vertex.x < 0.0 { Vec3::X } else { Vec3::Z };
+            assert_eq!(*color, expected, "{vertex}");
+        }
+
+        // Point clouds too, points without a color are white
+        let points = Mesh::from_obj(
+            Cursor::new("v 0 0 0 0 1 0\nv 1 0 0\nv 0 1 0 1 0 0\n"),
+            &ObjectSelector::All,
+            false,
+        )
+        .expect("Error loading mesh");
+        assert_eq!(points.colors, vec![Vec3::Y, Vec3::ONE, Vec3::X]);
+    }
+
+    #[test]
+    fn material_traversal() {
+        let load = |mtllib: &str| {
+            let obj = format!("mtllib {mtllib}\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n");
+            Mesh::from_obj_with_materials(
+                Cursor::new(obj),
+                Some(Path::new("test/data")),
+                &ObjectSelector::All,
+                false,
+            )
+            .expect("Error loading mesh")
+        };
+
+        assert_eq!(load("./two_materials.mtl").colors, vec![Vec3::X; 3]);
+
+        // Libraries outside of the directory are skipped, even when they exist
+        let absolute =
+            std::fs::canonicalize("test/data/two_materials.mtl").expect("Error resolving path");
+        for mtllib in [
+            "../data/two_materials.mtl",
+            "../../test/data/two_materials.mtl",
+            &absolute.to_string_lossy(),
+        ] {
+            assert_eq!(load(mtllib).colors, vec![Vec3::ONE; 3], "{mtllib}");
+        }
+    }
+
+    #[test]
+    fn sniff_headers() {
+        let sniff = |filename: &str| {
+            let mut file = File::open(filename).expect("Error opening model");
+            let mut header = Vec::new();
+            (&mut file)
+                .take(512)
+                .read_to_end(&mut header)
+                .expect("Error reading model");
+            InputFormat::sniff(&header, file.metadata().expect("Error reading model").len())
+        };
+
+        for (filename, format) in [
+            ("test/data/cube.stl", InputFormat::Stl),
+            ("test/data/cube_ascii.stl", InputFormat::Stl),
+            ("test/data/cube_solid_header.stl", InputFormat::Stl),
+            ("test/data/sphere.stl", InputFormat::Stl),
+            ("test/data/3DBenchy.stl", InputFormat::Stl),
+            ("test/data/cube.obj", InputFormat::Obj),
+            ("test/data/points.obj", InputFormat::Obj),
+            ("test/data/two_cubes.obj", InputFormat::Obj),
+            ("test/data/3DBenchy.obj", InputFormat::Obj),
+            ("test/data/cube.3mf", InputFormat::Threemf),
+            ("test/data/two_colors.3mf", InputFormat::Threemf),
+            ("test/data/cube_obj.zip", InputFormat::Zip),
+        ] {
+            assert_eq!(sniff(filename), Some(format), "{filename}");
+        }
+
+        assert_eq!(InputFormat::sniff(b"", 0), None);
+        assert_eq!(InputFormat::sniff(b"Just some notes\n", 16), None);
+        assert_eq!(InputFormat::sniff(&[0xff; 100], 100), None);
+    }
+
+    #[test]
+    fn non_finite_vertex() {
+        let load = || {
+            Mesh::from_obj(
+                Cursor::new(
+                    "v 0 0 0\nv nan 0 1\nv 1 0 0\nv 0 1 0\nv 5 5 5\nf 2 4 5\nf 1 3 4\nf 1 4 2\n",
+                ),
+                &ObjectSelector::All,
+                false,
+            )
+            .expect("Error loading mesh")
+        };
+
+        let error = load()
+            .sanitize(true)
+            .expect_err("NaN vertex should be rejected");
+        assert!(matches!(error, MeshError::NonFiniteVertex(0)));
+
+        // The vertices left unused are removed, so they don't count for centering the model
+        let mut mesh = load();
+        assert_eq!(2, mesh.sanitize(false).expect("Error sanitizing mesh"));
+        assert_eq!(3, mesh.vertices.len());
+        let triangle = mesh
+            .indices
+            .iter()
+            .map(|&i| mesh.vertices[i as usize])
+            .collect::<Vec<_>>();
+        assert_eq!(vec![Vec3::ZERO, Vec3::X, Vec3::Y], triangle);
+        assert_eq!(3, mesh.normals.len());
+        assert_eq!(3, mesh.colors.len());
+        assert_eq!(mesh.bounds.min, Vec3::ZERO);
+        assert_eq!(mesh.bounds.max, Vec3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn obj_normal_indices() {
+        // A square bent along its diagonal, the normals are listed in a different order than the
+        // vertices and each vertex keeps one normal in both triangles
+        let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 1\n\
+                   vn 0 -0.6 0.8\nvn 0 0 1\nvn 0.6 0 0.8\nvn 0 0.6 0.8\n\
+                   f 1//3 2//2 3//4\nf 1//3 3//4 4//1\n";
+        let mesh = Mesh::from_obj(Cursor::new(obj), &ObjectSelector::All, false)
+            .expect("Error loading mesh");
+        let expected = |v: Vec3| match v.to_array() {
+            [0.0, 0.0, 0.0] => Vec3::new(0.6, 0.0, 0.8),
+            [1.0, 0.0, 0.0] => Vec3::Z,
+            [1.0, 1.0, 0.0] => Vec3::new(0.0, 0.6, 0.8),
+            _ => Vec3::new(0.0, -0.6, 0.8),
+        };
+        assert_eq!(mesh.vertices.len(), 4);
+        for (v, n) in mesh.vertices.iter().zip(&mesh.normals) {
+            assert_eq!(*n, expected(*v), "{v}");
+        }
+
+        // Only the first face has normals, they can't be matched to the vertices
+        let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvn 1 0 0\nf 1//1 2//1 3//1\nf 1 3 4\n";
+        let mesh = Mesh::from_obj(Cursor::new(obj), &ObjectSelector::All, false)
+            .expect("Error loading mesh");
+        assert_eq!(mesh.normals.len(), mesh.vertices.len());
+        assert!(mesh.normals.iter().all(|n| *n == Vec3::Z));
+    }
+
+    #[test]
+    fn degenerate_normal() {
+        // The second triangle has three collinear vertices
+        let mesh = Mesh::from_obj(
+            Cursor::new("v 0 0 0\nv 1 0 0\nv 0 1 0\nv 2 0 0\nf 1 2 3\nf 1 2 4\n"),
+            &ObjectSelector::All,
+            false,
+        )
+        .expect("Error loading mesh");
+
+        let error = mesh
+            .check_normals()
+            .expect_err("Zero area triangle should be rejected");
+        assert!(matches!(error, MeshError::DegenerateNormal(1)));
+        // The shared vertices keep the normal of the first triangle
+        assert!(mesh.normals.iter().all(|n| n.is_finite()));
+        assert_eq!(mesh.normals[0], Vec3::Z);
+    }
+
+    #[test]
+    fn obj_smoothing_groups() {
+        let load = |groups: [&str; 2]| {
+            let obj = format!(
+                "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\ns {}\nf 1 2 3\ns {}\nf 1 4 2\n",
+                groups[0], groups[1]
+            );
+            Mesh::from_obj(Cursor::new(obj), &ObjectSelector::All, false)
+                .expect("Error loading mesh")
+        };
+        let face_normals = |mesh: &Mesh| {
+            mesh.indices
+                .chunks_exact(3)
+                .map(|face| {
+                    let [a, b, c] = [face[0], face[1], face[2]].map(|i| mesh.vertices[i as usize]);
+                    let normal = (b - a).cross(c - a).normalize();
+                    face.iter()
+                        .all(|&i| mesh.normals[i as usize].abs_diff_eq(normal, 1e-6))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // The faces share an edge, which is only smoothed when they are in the same group
+        assert_eq!(face_normals(&load(["1", "1"])), [false, false]);
+        assert_eq!(face_normals(&load(["1", "2"])), [true, true]);
+        assert_eq!(face_normals(&load(["off", "off"])), [true, true]);
+        assert_eq!(load(["1", "2"]).vertices.len(), 6);
+    }
+
+    #[test]
+    fn convex_hull() {
+        let cube = load_stl("test/data/cube.stl", StlMode::Auto).expect("Error loading mesh");
+        let hull = cube.convex_hull().expect("Error computing hull");
+
+        assert_eq!(hull.indices.len(), 12 * 3);
+        let corners = hull
+            .vertices
+            .iter()
+            .map(|v| v.to_array().map(f32::to_bits))
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(corners.len(), 8);
+        assert_eq!(hull.bounds.min, cube.bounds.min);
+        assert_eq!(hull.bounds.max, cube.bounds.max);
+        // Every triangle faces away from the center
+        let center = cube.bounds.center();
+        for t in hull.vertices.chunks_exact(3) {
+            let normal = (t[1] - t[0]).cross(t[2] - t[0]);
+            assert!(normal.dot(t[0] - center) > 0.0);
+        }
+
+        // Points inside the hull don't change it
+        let mut points = cube.vertices;
+        points.extend([Vec3::new(0.0, 0.0, 5.0), Vec3::new(1.0, -2.0, 3.0)]);
+        let (corners, triangles) = hull::convex_hull(&points).expect("Error computing hull");
+        assert_eq!((corners.len(), triangles.len()), (8, 12));
+
+        // A curved model has a closed hull holding all of its points
+        let sphere = load_stl("test/data/sphere.stl", StlMode::Auto).expect("Error loading mesh");
+        let (corners, triangles) =
+            hull::convex_hull(&sphere.vertices).expect("Error computing hull");
+        let mut edges = std::collections::HashSet::new();
+        for &[a, b, c] in &triangles {
+            for edge in [(a, b), (b, c), (c, a)] {
+                assert!(edges.insert(edge));
+            }
+        }
+        assert!(edges.iter().all(|&(a, b)| edges.contains(&(b, a))));
+        let epsilon = sphere.bounds.size().max_element() * 1e-4;
+        for &[a, b, c] in &triangles {
+            let (a, b, c) = (corners[a], corners[b], corners[c]);
+            let normal = (b - a).cross(c - a).normalize();
+            assert!(sphere
+                .vertices
+                .iter()
+                .all(|&v| normal.dot(v - a) <= epsilon));
+        }
+
+        let flat = Mesh::from_raw(
+            vec![Vec3::ZERO, Vec3::X, Vec3::Y, Vec3::ONE.with_z(0.0)],
+            vec![0, 1, 2],
+            None,
+        )
+        .expect("Error building mesh");
+        assert!(flat.convex_hull().is_err());
+    }
+
+    #[test]
+    fn fix_winding() {
+        let cube = load_stl("test/data/cube.stl", StlMode::Auto).expect("Error loading mesh");
+
+        let mut mesh = cube.clone();
+        for t in [0, 5, 9] {
+            mesh.indices.swap(t * 3 + 1, t * 3 + 2);
+        }
+        assert_eq!(mesh.fix_winding(), 3);
+        assert_eq!(mesh.indices, cube.indices);
+        assert_eq!(mesh.normals, cube.normals);
+
+        // Turned inside out, the faces are made to point outwards again
+        let mut mesh = cube.clone();
+        for face in mesh.indices.chunks_exact_mut(3) {
+            face.swap(1, 2);
+        }
+        assert_eq!(mesh.fix_winding(), 12);
+        assert_eq!(mesh.indices, cube.indices);
+
+        let mut mesh = cube;
+        assert_eq!(mesh.fix_winding(), 0);
+    }
+
+    #[test]
+    fn cube_edges() {
+        let mesh = load_stl("test/data/cube.stl", StlMode::Auto).expect("Error loading mesh");
+        let edges = mesh.edges(0.5);
+
+        // The diagonals splitting each side in two triangles are left out
+        assert_eq!(edges.len(), 12);
+        assert!(edges.iter().all(|edge| edge.sharp));
+        assert!(edges
+            .iter()
+            .all(|edge| edge.normals[0].dot(edge.normals[1]).abs() < 1e-6));
+    }
+
+    #[test]
+    fn object_selector() {
+        let load = |objects| {
+            Mesh::load(
+                "test/data/two_cubes.obj",
+                None,
+                StlMode::Auto,
+                &objects,
+                false,
+            )
+        };
+        let all = load(ObjectSelector::All).expect("Error loading mesh");
+        let large = load(ObjectSelector::ByIndex(1)).expect("Error loading mesh");
+        let small = load(ObjectSelector::ByName("small".to_string())).expect("Error loading mesh");
+
+        assert_eq!(all.bounds.size(), Vec3::splat(4.0));
+        assert_eq!(large.bounds.size(), Vec3::splat(2.0));
+        assert_eq!(large.bounds.center(), Vec3::splat(3.0));
+        assert_eq!(small.bounds.size(), Vec3::ONE);
+        assert_eq!(large.vertices.len(), all.vertices.len() / 2);
+
+        let error = load(ObjectSelector::ByIndex(2)).expect_err("Missing object should fail");
+        assert!(matches!(error, MeshError::ObjectNotFound(_)));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_smooth_normals() {
+        // OBJ vertices are shared by the triangles around them
+        let mesh = Mesh::load(
+            "test/data/3DBenchy.obj",
+            None,
+            StlMode::Auto,
+            &ObjectSelector::All,
+            true,
+        )
+        .expect("Error loading mesh");
+
+        let serial = sum_face_normals(&mesh.vertices, &mesh.indices);
+        // Exactly equal, the sums don't depend on how the work is split between threads
+        for threads in [1, 3, 8] {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .expect("Error building thread pool");
+            let parallel = pool.install(|| par_sum_face_normals(&mesh.vertices, &mesh.indices));
+            assert_eq!(serial, parallel, "{threads} threads");
+        }
+    }
+
+    #[test]
+    fn raw_quad() {
+        let vertices = vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(2.0, 1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        ];
+        let mesh = Mesh::from_raw(vertices.clone(), vec![0, 1, 2, 0, 2, 3], None)
+            .expect("Error building mesh");
+
+        assert_eq!(mesh.bounds.min, Vec3::ZERO);
+        assert_eq!(mesh.bounds.max, Vec3::new(2.0, 1.0, 0.0));
+        assert_eq!(mesh.normals, vec![Vec3::Z; 4]);
+        assert!(mesh.normals_recomputed);
+
+        // Quads far from the origin are moved next to it like loaded models
+        let offset = Vec3::new(1e6, -1e6, 0.0);
+        let far = Mesh::from_raw(
+            vertices.iter().map(|&v| v + offset).collect(),
+            vec![0, 1, 2, 0, 2, 3],
+            None,
+        )
+        .expect("Error building mesh");
+        assert_eq!(far.bounds.min, Vec3::new(-1.0, -0.5, 0.0));
+        assert_eq!(far.bounds.max, Vec3::new(1.0, 0.5, 0.0));
+        assert_eq!(far.origin, DVec3::new(1e6 + 1.0, -1e6 + 0.5, 0.0));
+
+        assert!(matches!(
+            Mesh::from_raw(vertices, vec![0, 1, 4], None),
+            Err(MeshError::InvalidMesh(_))
+        ));
+    }
+
+    #[test]
+    fn center_mode() {
+        // A large square with a long thin strip sticking out of it
+        let mesh = Mesh::from_raw(
+            vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(2.0, 2.0, 0.0),
+                Vec3::new(0.0, 2.0, 0.0),
+                Vec3::new(2.0, 0.9, 0.0),
+                Vec3::new(10.0, 0.9, 0.0),
+                Vec3::new(10.0, 1.1, 0.0),
+                Vec3::new(2.0, 1.1, 0.0),
+            ],
+            vec![0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7],
+            None,
+        )
+        .expect("Error building mesh");
+
+        let centroid = mesh.centroid();
+        assert!((centroid - Vec3::new(13.6 / 5.6, 1.0, 0.0)).length() < 1e-5);
+
+        let bbox = mesh.rotate_scale_and_center(Quat::IDENTITY, CenterMode::BoundingBox);
+        assert_eq!(bbox.transform_point3(mesh.bounds.center()), Vec3::ZERO);
+        let framed = mesh.rotate_scale_and_center(Quat::IDENTITY, CenterMode::Centroid);
+        assert!(framed.transform_point3(centroid).length() < 1e-5);
+        // The square is moved towards the middle of the image, while staying in the box
+        let square = Vec3::new(1.0, 1.0, 0.0);
+        assert!(framed.transform_point3(square).x > bbox.transform_point3(square).x);
+        let sphere = mesh.rotate_scale_and_center(Quat::IDENTITY, CenterMode::BoundingSphere);
+        for matrix in [bbox, framed, sphere] {
+            for &v in &mesh.vertices {
+                assert!(matrix.transform_point3(v).abs().max_element() <= 1.0 + 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn degenerate_bounds() {
+        let flat = Mesh::from_raw(vec![Vec3::ZERO, Vec3::X, Vec3::Y], vec![0, 1, 2], None)
+            .expect("Error building mesh");
+        let point = Mesh::from_raw(vec![Vec3::ONE; 3], vec![0, 1, 2], Some(vec![Vec3::Z; 3]))
+            .expect("Error building mesh");
+
+        for mesh in [flat, point] {
+            assert!(mesh.scale_and_center().is_finite());
+            for center_mode in [
+                CenterMode::BoundingBox,
+                CenterMode::Centroid,
+                CenterMode::BoundingSphere,
+            ] {
+                assert!(mesh
+                    .rotate_scale_and_center(Quat::from_rotation_z(1.0), center_mode)
+                    .is_finite());
+            }
+        }
     }
 }
@@ -0,0 +1,211 @@
+//! Convex hull of a point set, with the quickhull algorithm.
+
+use std::collections::{HashMap, HashSet};
+
+use glam::{DVec3, Vec3};
+
+/// A triangle of the hull, wound counter-clockwise seen from outside.
+struct Face {
+    corners: [usize; 3],
+    normal: DVec3,
+    /// Distance of the plane of the face from the origin, along the normal
+    offset: f64,
+    /// Points in front of the face, not yet known to be inside the hull
+    outside: Vec<usize>,
+    /// Whether the face is still part of the hull, rather than replaced by later ones
+    alive: bool,
+}
+
+impl Face {
+    fn new(points: &[DVec3], corners: [usize; 3]) -> Self {
+        let [a, b, c] = corners.map(|i| points[i]);
+        let normal = (b - a).cross(c - a).normalize_or_zero();
+        Self {
+            corners,
+            normal,
+            offset: normal.dot(a),
+            outside: Vec::new(),
+            alive: true,
+        }
+    }
+
+    fn distance(&self, point: DVec3) -> f64 {
+        self.normal.dot(point) - self.offset
+    }
+
+    const fn edges(&self) -> [(usize, usize); 3] {
+        let [a, b, c] = self.corners;
+        [(a, b), (b, c), (c, a)]
+    }
+}
+
+/// Computes the convex hull of `points`, returning the corners of the hull and its triangles as
+/// indices into them, wound counter-clockwise seen from outside.
+///
+/// Returns `None` if the points are all on one plane, which has no 3D hull.
+pub fn convex_hull(points: &[Vec3]) -> Option<(Vec<Vec3>, Vec<[usize; 3]>)> {
+    // Duplicates would make zero area faces
+    let mut seen = HashSet::new();
+    let points = points
+        .iter()
+        .filter(|p| seen.insert(p.to_array().map(f32::to_bits)))
+        .map(Vec3::as_dvec3)
+        .collect::<Vec<_>>();
+
+    // Points closer to a plane than this are taken to be on it, relative to the size of the set
+    let (min, max) = points
+        .iter()
+        .fold((DVec3::INFINITY, DVec3::NEG_INFINITY), |(min, max), &p| {
+            (min.min(p), max.max(p))
+        });
+    let epsilon = (max - min).max_element() * 1e-6;
+
+    let mut faces = initial_simplex(&points, epsilon)?
+        .into_iter()
+        .map(|corners| Face::new(&points, corners))
+        .collect::<Vec<_>>();
+    let simplex = faces
+        .iter()
+        .flat_map(|face| face.corners)
+        .collect::<HashSet<_>>();
+    assign(
+        &mut faces,
+        (0..points.len()).filter(|i| !simplex.contains(i)),
+        &points,
+        epsilon,
+    );
+
+    // Each directed edge belongs to one live face, whose neighbour across it owns the reversed
+    // edge
+    let mut edge_faces = HashMap::new();
+    for (i, face) in faces.iter().enumerate() {
+        edge_faces.extend(face.edges().map(|edge| (edge, i)));
+    }
+
+    let mut pending = (0..faces.len()).collect::<Vec<_>>();
+    while let Some(start) = pending.pop() {
+        if !faces[start].alive || faces[start].outside.is_empty() {
+            continue;
+        }
+
+        // The farthest point in front of the face is certainly a corner of the hull
+        let face = &faces[start];
+        let eye = face.outside.iter().copied().max_by(|&a, &b| {
+            face.distance(points[a])
+                .total_cmp(&face.distance(points[b]))
+        })?;
+
+        // Faces seen from the point are found by walking from the first one across edges, so
+        // they always make one connected patch even when rounding disagrees at its border
+        let mut visible = vec![start];
+        let mut seen = HashSet::from([start]);
+        let mut horizon = Vec::new();
+        let mut next = 0;
+        while let Some(&i) = visible.get(next) {
+            next += 1;
+            for (a, b) in faces[i].edges() {
+                let neighbour = edge_faces[&(b, a)];
+                if seen.contains(&neighbour) {
+                    continue;
+                }
+                if faces[neighbour].distance(points[eye]) > 0.0 {
+                    seen.insert(neighbour);
+                    visible.push(neighbour);
+                } else {
+                    horizon.push((a, b));
+                }
+            }
+        }
+
+        // The patch is replaced by a cone of faces from the point to its border
+        let mut orphans = Vec::new();
+        for &i in &visible {
+            faces[i].alive = false;
+            orphans.append(&mut faces[i].outside);
+            for edge in faces[i].edges() {
+                edge_faces.remove(&edge);
+            }
+        }
+        let first_new = faces.len();
+        for (a, b) in horizon {
+            edge_faces.extend([(a, b), (b, eye), (eye, a)].map(|edge| (edge, faces.len())));
+            faces.push(Face::new(&points, [a, b, eye]));
+        }
+        assign(
+            &mut faces[first_new..],
+            orphans.into_iter().filter(|&i| i != eye),
+            &points,
+            epsilon,
+        );
+        pending.extend(first_new..faces.len());
+    }
+
+    // Only the points used by the hull are kept
+    let mut corners = HashMap::new();
+    let mut hull_points = Vec::new();
+    let triangles = faces
+        .iter()
+        .filter(|face| face.alive)
+        .map(|face| {
+            face.corners.map(|i| {
+                *corners.entry(i).or_insert_with(|| {
+                    hull_points.push(points[i].as_vec3());
+                    hull_points.len() - 1
+                })
+            })
+        })
+        .collect();
+    Some((hull_points, triangles))
+}
+
+/// Gives each point to the first face it is in front of, dropping the points behind every face.
+fn assign(
+    faces: &mut [Face],
+    points_to_assign: impl Iterator<Item = usize>,
+    points: &[DVec3],
+    epsilon: f64,
+) {
+    for i in points_to_assign {
+        if let Some(face) = faces
+            .iter_mut()
+            .find(|face| face.distance(points[i]) > epsilon)
+        {
+            face.outside.push(i);
+        }
+    }
+}
+
+/// Finds a tetrahedron of points spanning the set as widely as possible, returning its faces
+/// wound counter-clockwise seen from outside.
+fn initial_simplex(points: &[DVec3], epsilon: f64) -> Option<[[usize; 3]; 4]> {
+    let farthest = |distance: &dyn Fn(DVec3) -> f64| {
+        (0..points.len())
+            .max_by(|&a, &b| distance(points[a]).total_cmp(&distance(points[b])))
+            .filter(|&i| distance(points[i]) > epsilon)
+    };
+
+    if points.is_empty() {
+        return None;
+    }
+    let a = 0;
+    let b = farthest(&|p| p.distance(points[a]))?;
+    let direction = (points[b] - points[a]).normalize();
+    let c = farthest(&|p| (p - points[a]).reject_from_normalized(direction).length())?;
+    let normal = (points[b] - points[a])
+        .cross(points[c] - points[a])
+        .normalize();
+    let d = farthest(&|p| normal.dot(p - points[a]).abs())?;
+
+    // Each face is turned away from the center of the tetrahedron
+    let center = (points[a] + points[b] + points[c] + points[d]) / 4.0;
+    Some(
+        [[a, b, c], [a, b, d], [b, c, d], [c, a, d]].map(|[i, j, k]| {
+            let normal = (points[j] - points[i]).cross(points[k] - points[i]);
+            if normal.dot(center - points[i]) > 0.0 {
+                [i, k, j]
+            } else {
+                [i, j, k]
+            }
+        }),
+    )
+}
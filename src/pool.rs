@@ -0,0 +1,74 @@
+use std::sync::{Mutex, PoisonError};
+
+use wgpu::{
+    Buffer, BufferAddress, BufferDescriptor, BufferUsages, Device, Queue, COPY_BUFFER_ALIGNMENT,
+};
+
+/// Buffers kept between renders, so renders of similarly sized meshes write into the buffers of
+/// the previous ones instead of allocating new ones.
+///
+/// Buffers are allocated with a power of two size, so one can be reused for any data that fits
+/// in it. The pool only holds the buffers given back to it, at most the ones of the largest
+/// render so far.
+#[derive(Default)]
+pub struct BufferPool(Mutex<Vec<Buffer>>);
+
+impl BufferPool {
+    /// Takes the smallest free buffer with exactly `usage` that holds at least `size` bytes, or
+    /// creates one if there is none.
+    pub fn take(&self, device: &Device, size: BufferAddress, usage: BufferUsages) -> Buffer {
+        let mut free = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let best = free
+            .iter()
+            .enumerate()
+            .filter(|(_, buffer)| buffer.usage() == usage && buffer.size() >= size)
+            .min_by_key(|(_, buffer)| buffer.size())
+            .map(|(i, _)| i);
+        if let Some(i) = best {
+            return free.swap_remove(i);
+        }
+        drop(free);
+
+        device.create_buffer(&BufferDescriptor {
+            label: None,
+            size: size.max(COPY_BUFFER_ALIGNMENT).next_power_of_two(),
+            usage,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Takes a buffer like [`Self::take`] and writes `data` at its start.
+    pub fn take_init(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        data: &[u8],
+        usage: BufferUsages,
+    ) -> Buffer {
+        let size = (data.len() as BufferAddress).next_multiple_of(COPY_BUFFER_ALIGNMENT);
+        let buffer = self.take(device, size, usage | BufferUsages::COPY_DST);
+        // Writes must be a multiple of 4 bytes, which 16-bit indices aren't always
+        if size == data.len() as BufferAddress {
+            queue.write_buffer(&buffer, 0, data);
+        } else {
+            let mut padded = data.to_vec();
+            padded.resize(data.len().next_multiple_of(4), 0);
+            queue.write_buffer(&buffer, 0, &padded);
+        }
+        buffer
+    }
+
+    /// Number of free buffers in the pool.
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    /// Gives buffers back to the pool once the GPU is done with them.
+    pub fn give_back(&self, buffers: impl IntoIterator<Item = Buffer>) {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend(buffers);
+    }
+}
@@ -1,37 +1,1048 @@
-use std::{cell::RefCell, mem::size_of, sync::Arc};
+use std::{
+    borrow::Cow,
+    mem::size_of,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    time::Duration,
+};
 
-use glam::{Mat4, Vec3};
-use tracing::debug;
+use glam::{BVec3, EulerRot, Mat3, Mat4, Quat, Vec2, Vec3, Vec4};
+use half::f16;
+use tracing::{debug, warn};
 use wgpu::{
-    util::{BufferInitDescriptor, DeviceExt},
-    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
-    BindGroupLayoutEntry, BindingType, BufferAddress, BufferBindingType, BufferDescriptor,
-    BufferUsages, Color, CommandEncoderDescriptor, CompareFunction, DepthBiasState,
-    DepthStencilState, Device, DeviceDescriptor, Extent3d, Face, Features, FragmentState,
-    FrontFace, ImageCopyBuffer, ImageCopyTexture, ImageDataLayout, IndexFormat, Instance, Limits,
-    LoadOp, Maintain, MapMode, MemoryHints, MultisampleState, Operations, Origin3d,
+    util::{BufferInitDescriptor, DeviceExt, TextureDataOrder},
+    Adapter, AdapterInfo, Backends, BindGroup, BindGroupDescriptor, BindGroupEntry,
+    BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType,
+    BlendComponent, BlendFactor, BlendOperation, BlendState, BufferAddress, BufferBindingType,
+    BufferUsages, Color, ColorTargetState, ColorWrites, CommandEncoderDescriptor, CompareFunction,
+    DepthBiasState, DepthStencilState, Device, DeviceDescriptor, DeviceLostReason, ErrorFilter,
+    Extent3d, Face, Features, FilterMode, FragmentState, FrontFace, ImageCopyBuffer,
+    ImageCopyTexture, ImageDataLayout, IndexFormat, Instance, InstanceDescriptor, Limits, LoadOp,
+    Maintain, MapMode, MemoryHints, MultisampleState, Operations, Origin3d,
     PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState, Queue,
     RenderPassColorAttachment, RenderPassDepthStencilAttachment, RenderPassDescriptor,
-    RenderPipeline, RenderPipelineDescriptor, RequestAdapterOptions, ShaderStages, StencilState,
-    StoreOp, Texture, TextureAspect, TextureDescriptor, TextureDimension, TextureFormat,
-    TextureUsages, TextureViewDescriptor, VertexAttribute, VertexBufferLayout, VertexFormat,
-    VertexState, VertexStepMode,
+    RenderPipeline, RenderPipelineDescriptor, RequestAdapterOptions, SamplerBindingType,
+    SamplerDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages, StencilState, StoreOp,
+    Texture, TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType,
+    TextureUsages, TextureViewDescriptor, TextureViewDimension, VertexAttribute,
+    VertexBufferLayout, VertexFormat, VertexState, VertexStepMode, COPY_BYTES_PER_ROW_ALIGNMENT,
 };
 
 use crate::{
+    callback::CallbackFuture,
     error::RenderError,
-    mesh::Mesh,
-    shader::{FragUniformBlock, VertUniformBlock, SHADER},
+    mesh::{BoundingBox, InputFormat, Mesh, MeshInfo, ObjectSelector, StlMode},
+    pool::BufferPool,
+    shader::{
+        AoUniformBlock, BackgroundUniformBlock, DofUniformBlock, EdgeInstance, EdgeUniformBlock,
+        FragUniformBlock, LineInstance, LineUniformBlock, VertUniformBlock, AO_SHADER,
+        BACKGROUND_SHADER, DOF_SHADER, EDGE_SHADER, FXAA_SHADER, LINE_SHADER, MAX_LIGHTS,
+        POINT_SHADER, SHADER,
+    },
     Config,
 };
 
+/// Color space of the rendered image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorSpace {
+    /// Colors are gamma encoded to sRGB when written, suitable for display.
+    #[default]
+    Srgb,
+    /// Colors are written as linear values, suitable for compositing in linear pipelines.
+    Linear,
+}
+
+impl ColorSpace {
+    const fn texture_format(self) -> TextureFormat {
+        match self {
+            Self::Srgb => TextureFormat::Rgba8UnormSrgb,
+            Self::Linear => TextureFormat::Rgba8Unorm,
+        }
+    }
+}
+
+/// Number of bits per channel of the rendered image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitDepth {
+    /// 8 bits per channel.
+    #[default]
+    Eight,
+    /// 16 bits per channel, rendered to a half-float target for higher precision.
+    Sixteen,
+    /// 32-bit floats per channel, linear and not clamped to 1, for HDR formats like EXR.
+    ///
+    /// Rendered to a half-float target, as 32-bit float targets can't be blended.
+    Float,
+}
+
+impl BitDepth {
+    /// Number of bytes used by each channel.
+    #[must_use]
+    pub const fn bytes_per_channel(self) -> usize {
+        match self {
+            Self::Eight => 1,
+            Self::Sixteen => 2,
+            Self::Float => 4,
+        }
+    }
+
+    /// Format of the render target, 16-bit targets are linear and encoded when read back.
+    const fn texture_format(self, color_space: ColorSpace) -> TextureFormat {
+        match self {
+            Self::Eight => color_space.texture_format(),
+            Self::Sixteen | Self::Float => TextureFormat::Rgba16Float,
+        }
+    }
+}
+
+/// Integer channel of a read back pixel.
+trait Channel: Copy + Into<u32> {
+    const MAX: u32;
+
+    /// Converts a value, clamping it to the channel range.
+    fn saturating_from(value: u32) -> Self;
+}
+
+impl Channel for u8 {
+    const MAX: u32 = Self::MAX as u32;
+
+    fn saturating_from(value: u32) -> Self {
+        Self::try_from(value).unwrap_or(Self::MAX)
+    }
+}
+
+impl Channel for u16 {
+    const MAX: u32 = Self::MAX as u32;
+
+    fn saturating_from(value: u32) -> Self {
+        Self::try_from(value).unwrap_or(Self::MAX)
+    }
+}
+
+/// Anti-aliasing quality presets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AntiAlias {
+    /// No anti-aliasing, a single sample per pixel.
+    Off,
+    /// 2x multisampling.
+    Msaa2,
+    /// 4x multisampling, supported by every adapter.
+    #[default]
+    Msaa4,
+    /// 8x multisampling.
+    Msaa8,
+}
+
+impl AntiAlias {
+    /// Number of samples per pixel, to be used as [`RenderOptions::sample_count`].
+    #[must_use]
+    pub const fn sample_count(self) -> u32 {
+        match self {
+            Self::Off => 1,
+            Self::Msaa2 => 2,
+            Self::Msaa4 => 4,
+            Self::Msaa8 => 8,
+        }
+    }
+}
+
+/// Pixel layout of the rendered image buffer.
+///
+/// The names refer to the default 8-bit depth, each channel takes 2 bytes with
+/// [`BitDepth::Sixteen`] and 4 bytes with [`BitDepth::Float`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PixelFormat {
+    /// 8-bit RGBA, 4 bytes per pixel.
+    #[default]
+    Rgba8,
+    /// 8-bit RGB, 3 bytes per pixel. The render is composited over the solid background color.
+    Rgb8,
+}
+
+impl PixelFormat {
+    /// Number of bytes used by each 8-bit pixel.
+    #[must_use]
+    pub const fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Rgba8 => 4,
+            Self::Rgb8 => 3,
+        }
+    }
+}
+
+/// How the model geometry is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Topology {
+    /// Triangles, or points if the model has vertices but no faces.
+    #[default]
+    Auto,
+    /// Filled triangles.
+    Triangles,
+    /// A square of `point_size` pixels at each vertex.
+    Points,
+}
+
+impl Topology {
+    /// Whether `mesh` is drawn as points.
+    const fn is_points(self, mesh: &Mesh) -> bool {
+        match self {
+            Self::Auto => mesh.indices.is_empty(),
+            Self::Triangles => false,
+            Self::Points => true,
+        }
+    }
+}
+
+/// How the faces of the model are shaded.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Shading {
+    /// Lit faces.
+    #[default]
+    Solid,
+    /// Lit faces with lines along the creases, boundaries and silhouette of the model, where
+    /// they aren't hidden by it.
+    SolidWithEdges {
+        edge_color: Vec3,
+        /// Width of the lines in pixels
+        thickness: f32,
+    },
+    /// Flat cel shading, with the light of each light source quantized into a few bands and hard
+    /// edged highlights. Lines are drawn like [`Shading::SolidWithEdges`] if `edge_color` is set,
+    /// for an outlined cartoon look.
+    Toon {
+        /// Number of light levels, from unlit to fully lit
+        bands: u8,
+        edge_color: Option<Vec3>,
+        /// Width of the lines in pixels
+        thickness: f32,
+    },
+    /// See-through faces whose light adds up where they overlap, showing shells and internal
+    /// cavities. Every face is drawn, from both sides.
+    XRay {
+        /// Fraction of the light of each face added to the image, from 0 to 1
+        opacity: f32,
+    },
+}
+
+impl Shading {
+    /// Color and width of the lines drawn along the creases and silhouette, if any.
+    const fn edges(self) -> Option<(Vec3, f32)> {
+        match self {
+            Self::Solid
+            | Self::XRay { .. }
+            | Self::Toon {
+                edge_color: None, ..
+            } => None,
+            Self::SolidWithEdges {
+                edge_color,
+                thickness,
+            }
+            | Self::Toon {
+                edge_color: Some(edge_color),
+                thickness,
+                ..
+            } => Some((edge_color, thickness)),
+        }
+    }
+
+    /// Opacity of the faces of [`Shading::XRay`].
+    const fn xray_opacity(self) -> Option<f32> {
+        match self {
+            Self::XRay { opacity } => Some(opacity),
+            _ => None,
+        }
+    }
+
+    /// Number of light levels of [`Shading::Toon`], 0 for smooth lighting.
+    const fn toon_bands(self) -> u8 {
+        match self {
+            Self::Toon { bands, .. } => bands,
+            _ => 0,
+        }
+    }
+}
+
+/// Which faces of the model are skipped, based on their winding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CullMode {
+    /// Faces pointing away from the camera are skipped.
+    #[default]
+    Back,
+    /// Faces pointing towards the camera are skipped, showing the inside of closed models.
+    Front,
+    /// Every face is drawn, for open surfaces and models with inconsistent winding.
+    None,
+}
+
+impl CullMode {
+    const fn face(self) -> Option<Face> {
+        match self {
+            Self::Back => Some(Face::Back),
+            Self::Front => Some(Face::Front),
+            Self::None => None,
+        }
+    }
+}
+
+/// Point of the model placed at the center of the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CenterMode {
+    /// Center of the bounding box of the model.
+    #[default]
+    BoundingBox,
+    /// Center of the surface of the model, weighted by the area of its triangles, so models with
+    /// a large thin part and a small dense one are framed on the dense one.
+    Centroid,
+    /// Center of a sphere around the model, which keeps the model the same size whichever way it
+    /// is turned.
+    BoundingSphere,
+}
+
+/// A coordinate axis of the model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    /// Unit vector pointing along the axis.
+    #[must_use]
+    pub const fn unit(self) -> Vec3 {
+        match self {
+            Self::X => Vec3::X,
+            Self::Y => Vec3::Y,
+            Self::Z => Vec3::Z,
+        }
+    }
+
+    /// Reflection of the centered model along the axis, after the model was rotated by
+    /// `rotation`.
+    fn reflection(self, rotation: Quat) -> Mat4 {
+        let normal = rotation * self.unit();
+        // Householder reflection through the plane perpendicular to the axis
+        Mat4::from_mat3(
+            Mat3::IDENTITY
+                - Mat3::from_cols(normal * normal.x, normal * normal.y, normal * normal.z) * 2.0,
+        )
+    }
+}
+
+/// Standard views of the model. Z points up and the front of the model faces -Y.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraPreset {
+    Front,
+    Back,
+    Left,
+    Right,
+    Top,
+    Bottom,
+    Isometric,
+    /// The isometric view with an orthographic projection, for sprites in games, where every
+    /// axis is foreshortened the same and parallel edges stay parallel.
+    GameIsometric,
+}
+
+impl CameraPreset {
+    /// Unit vector from the model towards the camera.
+    #[must_use]
+    pub fn direction(self) -> Vec3 {
+        match self {
+            Self::Front => Vec3::NEG_Y,
+            Self::Back => Vec3::Y,
+            Self::Left => Vec3::NEG_X,
+            Self::Right => Vec3::X,
+            Self::Top => Vec3::Z,
+            Self::Bottom => Vec3::NEG_Z,
+            // 45° around Z and about 35.264° above the XY plane
+            Self::Isometric | Self::GameIsometric => Vec3::new(1.0, -1.0, 1.0).normalize(),
+        }
+    }
+
+    /// Whether the view is meant to be rendered with an orthographic projection.
+    #[must_use]
+    pub const fn orthographic(self) -> bool {
+        matches!(self, Self::GameIsometric)
+    }
+
+    /// Camera position looking at the model from this view, at the same distance as
+    /// `cam_position`.
+    #[must_use]
+    pub fn position(self, cam_position: Vec3) -> Vec3 {
+        self.direction() * cam_position.length()
+    }
+
+    /// Name of the view, used to label view sheets.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Front => "front",
+            Self::Back => "back",
+            Self::Left => "left",
+            Self::Right => "right",
+            Self::Top => "top",
+            Self::Bottom => "bottom",
+            Self::Isometric => "isometric",
+            Self::GameIsometric => "game-iso",
+        }
+    }
+}
+
+/// Lines drawn over the model to help diagnose framing issues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DebugOverlay {
+    /// No overlay.
+    #[default]
+    None,
+    /// Yellow edges of the model's bounding box.
+    BoundingBox,
+    /// Red, green and blue X, Y and Z axes from the model's origin, as long as the largest side of
+    /// its bounding box.
+    Axes,
+    /// Both the bounding box and the axes.
+    Both,
+}
+
+impl DebugOverlay {
+    /// Ends of each line in model space and their colors.
+    fn lines(self, bounds: &BoundingBox) -> Vec<LineInstance> {
+        let mut lines = Vec::new();
+
+        if matches!(self, Self::BoundingBox | Self::Both) {
+            let corner = |i: usize| {
+                Vec3::select(
+                    BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0),
+                    bounds.max,
+                    bounds.min,
+                )
+            };
+            // Each edge joins two corners differing in a single axis
+            for i in 0..8 {
+                for axis in [1, 2, 4] {
+                    if i & axis == 0 {
+                        lines.push(LineInstance {
+                            start: corner(i),
+                            end: corner(i | axis),
+                            color: Vec3::new(1.0, 1.0, 0.0),
+                        });
+                    }
+                }
+            }
+        }
+
+        if matches!(self, Self::Axes | Self::Both) {
+            let length = bounds.size().max_element();
+            for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+                lines.push(LineInstance {
+                    start: Vec3::ZERO,
+                    end: axis * length,
+                    color: axis,
+                });
+            }
+        }
+
+        lines
+    }
+}
+
+/// A grid of lines on the floor beneath the model, as a reference for its scale and orientation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridOptions {
+    /// Distance between the lines, in model units
+    pub spacing: f32,
+    /// Color of the lines, encoded like the [`Background`] colors
+    pub color: Vec3,
+    /// How far the grid reaches past the footprint of the model on each side, as a fraction of
+    /// its longest side
+    pub extent: f32,
+}
+
+impl Default for GridOptions {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            spacing: 10.0,
+            color: Vec3::splat(0.5),
+            extent: 0.25,
+        }
+    }
+}
+
+impl GridOptions {
+    /// Ends of each line in model space, on the plane under the lowest point of the model once
+    /// `model_matrix` places it in the scene, where Z is up.
+    fn lines(&self, vertices: &[Vec3], model_matrix: Mat4, color: Vec3) -> Vec<LineInstance> {
+        let (min, max) = vertices
+            .iter()
+            .map(|&v| model_matrix.transform_point3(v))
+            .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), v| {
+                (min.min(v), max.max(v))
+            });
+        // The spacing is scaled with the model
+        let spacing = self.spacing * model_matrix.transform_vector3(Vec3::X).length();
+        if !spacing.is_finite() || spacing <= 0.0 || !min.is_finite() || !max.is_finite() {
+            return Vec::new();
+        }
+
+        let center = (min + max) * 0.5;
+        let half =
+            (max - min).truncate().max_element() * self.extent.max(0.0).mul_add(2.0, 1.0) * 0.5;
+        // Lines are spread out rather than drawing too many of them
+        let spacing = spacing * (half / spacing / MAX_GRID_LINES).ceil().max(1.0);
+        // At most MAX_GRID_LINES on each side of the center
+        #[allow(clippy::cast_possible_truncation)]
+        let count = (half / spacing).ceil() as i32;
+        #[allow(clippy::cast_precision_loss)]
+        let half = count as f32 * spacing;
+
+        let inverse = model_matrix.inverse();
+        let point =
+            |x: f32, y: f32| inverse.transform_point3(Vec3::new(center.x + x, center.y + y, min.z));
+        (-count..=count)
+            .flat_map(|i| {
+                #[allow(clippy::cast_precision_loss)]
+                let offset = i as f32 * spacing;
+                [
+                    (point(offset, -half), point(offset, half)),
+                    (point(-half, offset), point(half, offset)),
+                ]
+            })
+            .map(|(start, end)| LineInstance { start, end, color })
+            .collect()
+    }
+}
+
+/// A light along the silhouette of the model, setting it apart from the background.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RimLight {
+    /// Color of the light, scaled by its intensity
+    pub color: Vec3,
+    /// Falloff exponent, higher values give thinner rims
+    pub power: f32,
+}
+
+impl Default for RimLight {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            color: Vec3::ONE,
+            power: 3.0,
+        }
+    }
+}
+
+/// A light shining in a single direction, relative to the camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DirectionalLight {
+    /// Direction pointing towards the light
+    pub direction: Vec3,
+    /// Color of the light, scaled by its intensity
+    pub color: Vec3,
+}
+
+impl Default for DirectionalLight {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            direction: Vec3::new(-1.1, 0.4, 1.0),
+            color: Vec3::ONE,
+        }
+    }
+}
+
+impl DirectionalLight {
+    /// Classic three-point lighting: a key light, a dimmer fill light on the opposite side,
+    /// and a rim light from behind the model.
+    #[must_use]
+    pub fn three_point() -> Vec<Self> {
+        vec![
+            Self::default(),
+            Self {
+                direction: Vec3::new(1.0, 0.2, 0.6),
+                color: Vec3::splat(0.4),
+            },
+            Self {
+                direction: Vec3::new(0.0, 1.0, -1.0),
+                color: Vec3::splat(0.6),
+            },
+        ]
+    }
+}
+
+/// Depth of field, blurring the parts of the image away from the focal plane like a camera lens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DofOptions {
+    /// Distance from the camera to the plane in focus, the model is scaled to fit a 2 x 2 x 2 box
+    /// around the origin
+    pub focal_distance: f32,
+    /// Size of the blur, as the fraction of the image height the blur radius reaches far behind
+    /// the focal plane. The radius is at most 16 pixels
+    pub aperture: f32,
+}
+
+/// Offset added to the depth of the model's faces, pushing them away from the camera so lines
+/// drawn over them, like the wireframe, aren't hidden by the faces they lie on.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DepthBias {
+    /// Constant offset, in units of the smallest difference the depth buffer can hold
+    pub constant: i32,
+    /// Offset scaled by the slope of the face, for faces seen at a grazing angle
+    pub slope_scale: f32,
+    /// Largest offset, or 0 for no limit
+    pub clamp: f32,
+}
+
+impl From<DepthBias> for DepthBiasState {
+    fn from(bias: DepthBias) -> Self {
+        Self {
+            constant: bias.constant,
+            slope_scale: bias.slope_scale,
+            clamp: bias.clamp,
+        }
+    }
+}
+
+/// Background drawn behind the model.
+///
+/// Colors are given as they are written to the image, e.g. `1/255` for a channel of `01`: sRGB
+/// encoded in the sRGB color space and linear otherwise. Floating point images are always
+/// linear, so they get the decoded value of sRGB colors.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Background {
+    /// A single color (rgba).
+    Solid(Vec4),
+    /// A vertical gradient between two colors (rgba).
+    VerticalGradient { top: Vec4, bottom: Vec4 },
+    /// An 8-bit RGBA image with the same dimensions as the output.
+    Image(Vec<u8>),
+}
+
+impl Default for Background {
+    #[inline]
+    fn default() -> Self {
+        Self::Solid(Vec4::ZERO)
+    }
+}
+
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone)]
 pub struct RenderOptions {
     pub width: u16,
     pub height: u16,
+    /// Field of view of the camera in degrees, along the shorter side of the image. Must be
+    /// between 0 and 180.
     pub cam_fov_deg: f32,
     pub cam_position: Vec3,
+    /// Rotation applied to the model before it is centered and scaled to fit the frame
+    pub model_rotation: Quat,
+    /// Mirror the model along one of its axes, e.g. for models authored with the other
+    /// handedness. The winding of the faces is flipped with it so they are still visible
+    pub mirror: Option<Axis>,
+    /// Place the camera at a distance depending on the field of view, only the direction of
+    /// `cam_position` is used, and scale the model so it fills the width or the height of the
+    /// frame, whichever it reaches first
+    pub auto_frame: bool,
+    /// Turn models whose format is Y-up by convention, see [`InputFormat::up_axis`], so Y points
+    /// up like Z does for the other formats. Applied before `model_rotation`
+    pub auto_up: bool,
+    /// Use an orthographic projection, where the model doesn't shrink with the distance from
+    /// the camera and `cam_fov_deg` is ignored
+    pub orthographic: bool,
+    /// Distance from the camera to the near clip plane
+    pub near: f32,
+    /// Distance from the camera to the far clip plane
+    pub far: f32,
+    /// Number of samples for anti-aliasing, see [`AntiAlias`] for the common values
     pub sample_count: u32,
     pub recalc_normals: bool,
+    /// Fail to load models with NaN or infinite vertex coordinates, instead of removing the
+    /// triangles using them
+    pub reject_non_finite: bool,
+    /// Download models given as `http://` or `https://` URIs, see [`Mesh::from_uri`]. Only local
+    /// files and `file://` URIs are loaded otherwise. Needs the `uri` feature
+    pub allow_remote: bool,
+    /// Fail to load models with zero area triangles or zero normals, see [`Mesh::check_normals`]
+    pub strict_normals: bool,
+    /// Give every object of the model its own color, see [`Mesh::color_objects`]
+    pub auto_color_objects: bool,
+    /// Make the winding of the triangles consistent, so back-face culling doesn't drop the
+    /// inverted ones of malformed models, see [`Mesh::fix_winding`]
+    pub auto_fix_winding: bool,
+    /// Render the convex hull of the model instead of its triangles, for a simplified
+    /// silhouette, see [`Mesh::convex_hull`]
+    pub convex_hull: bool,
+    /// Store the size of the model in model units, before it is scaled to fit, in the PNG images
+    /// written by [`crate::render_to_file`] and [`crate::render_multi_size_to_files`] as a
+    /// `Model::Size` text chunk like `20 x 20 x 10`
+    pub embed_model_size: bool,
+    pub input_format: Option<InputFormat>,
+    /// Encoding of STL models, detected from the file if `Auto`
+    pub stl_mode: StlMode,
+    /// Objects to load from model files with several, like OBJ and 3MF
+    pub object_selector: ObjectSelector,
+    /// Directory the material libraries of OBJ models held in memory are read from, see
+    /// [`crate::render_bytes`]. Models loaded from files read them next to the file instead
+    pub obj_base_dir: Option<PathBuf>,
+    /// Fall back to software rendering if no GPU adapter is available
+    pub allow_software: bool,
+    /// Lights illuminating the model, up to 4 are used
+    pub lights: Vec<DirectionalLight>,
+    /// Light brightening the surfaces turning away from the camera along the silhouette, which
+    /// sets the model apart from the background
+    pub rim_light: Option<RimLight>,
+    /// Specular exponent, higher values give smaller highlights
+    pub shininess: f32,
+    /// Intensity of the specular highlights
+    pub specular_strength: f32,
+    pub color_space: ColorSpace,
+    pub background: Background,
+    /// Multiply the color channels by alpha in the output image, ignored when
+    /// `matte_background` is set
+    pub premultiply_alpha: bool,
+    /// Color the transparent parts of the image are composited over, so anti-aliased edges
+    /// blend into it while the alpha channel keeps the coverage of the model. Useful when the
+    /// image is shown over a known background color. Encoded like the [`Background`] colors
+    pub matte_background: Option<Vec3>,
+    /// Color RGB output is composited over, so it can be set apart from the background that
+    /// stays transparent in RGBA output. When `None`, RGB output is composited over the color of
+    /// a solid background, or black. Encoded like the [`Background`] colors
+    pub flatten_background: Option<Vec3>,
+    /// Stretch the colors of the opaque pixels so their luminance spans most of the range,
+    /// giving thumbnails of dark and bright models a similar brightness. The whole image counts
+    /// with an opaque background
+    pub auto_levels: bool,
+    /// Pixel layout of the output image
+    pub output_format: PixelFormat,
+    /// Bits per channel of the output image, 16-bit buffers hold native-endian `u16` values and
+    /// float buffers native-endian `f32` values
+    pub bit_depth: BitDepth,
+    /// Whether the model is drawn as triangles or points
+    pub topology: Topology,
+    /// How the faces of the model are shaded, point clouds are not affected
+    pub shading: Shading,
+    /// Faces skipped when drawing triangles
+    pub cull_mode: CullMode,
+    /// Point of the model placed at the center of the image
+    pub center_mode: CenterMode,
+    /// Light the back of faces like their front, for open and thin surfaces drawn without
+    /// culling
+    pub two_sided: bool,
+    /// Draw the back of faces in this flat color, so faces with inverted winding stand out from
+    /// shadows. Faces aren't culled when it is set. Encoded like the [`Background`] colors
+    pub highlight_backfaces: Option<Vec3>,
+    /// Size in pixels of the points drawn for point clouds
+    pub point_size: f32,
+    /// Darken creases and corners with a screen-space ambient occlusion pass, point clouds are
+    /// not affected
+    pub ambient_occlusion: bool,
+    /// How much fully occluded areas are darkened, from 0 to 1
+    pub ao_strength: f32,
+    /// Distance around each point searched for occluders, the model is scaled to fit a
+    /// 2 x 2 x 2 box
+    pub ao_radius: f32,
+    /// Lines drawn over the model for debugging
+    pub debug_overlay: DebugOverlay,
+    /// Draw the edges of the triangles in black over the model, where they aren't hidden by it.
+    /// Use with a `depth_bias` so the faces don't hide their own edges
+    pub wireframe: bool,
+    /// Thickness in pixels of the wireframe, grid and debug overlay lines, which are anti-aliased
+    pub line_width: f32,
+    /// Grid drawn on the floor beneath the model, where it isn't hidden by it. Off by default
+    pub grid: Option<GridOptions>,
+    /// Offset of the depth of the faces of the model
+    pub depth_bias: DepthBias,
+    /// Hide the faces of the model behind nearer ones. Without it faces are drawn over each other
+    /// in file order, for flat or stylized previews
+    pub depth_test: bool,
+    /// Record the depth of the faces of the model, so faces drawn later can be tested against it
+    pub depth_write: bool,
+    /// Smooth jagged edges with a post-processing pass (FXAA), which works without multisampling
+    pub fxaa: bool,
+    /// Number of frames averaged into the image, each sampling the pixels at another point
+    /// inside them. Smooths edges like multisampling, using more time instead of larger
+    /// textures. 0 and 1 render a single frame
+    pub taa_samples: u8,
+    /// Offset in pixels of the points the pixels are sampled at, from their center. Right and
+    /// down are positive
+    pub jitter: Vec2,
+    /// Blur the image away from the focal plane, point clouds are not affected. Off by default
+    /// as it takes two extra passes
+    pub depth_of_field: Option<DofOptions>,
+    /// Render images larger than the device texture limit in several tiles, instead of failing.
+    /// Ambient occlusion, FXAA and depth of field can leave faint seams between the tiles
+    pub tiled: bool,
+    /// WGSL source replacing the shader the model is drawn with, for custom shading. It must have
+    /// the `vert_main` and `frag_main` entry points, take the vertex position, normal and color
+    /// at locations 0 to 2, and can use the uniforms of the built-in shader at bindings 0 and 1 of
+    /// group 0, see `src/shaders/model.wgsl`
+    pub custom_shader: Option<String>,
+    /// Keep the mesh and image buffers of a render for the next ones, which write over them
+    /// instead of allocating new buffers when they fit. Speeds up rendering many images of the
+    /// same model with one renderer, like [`render_multi_size`](crate::render_multi_size)
+    pub reuse_buffers: bool,
+}
+
+impl Default for RenderOptions {
+    #[inline]
+    fn default() -> Self {
+        (&Config::default()).into()
+    }
+}
+
+/// Statistics about a completed render.
+#[derive(Debug, Clone)]
+pub struct RenderResult {
+    pub mesh: MeshInfo,
+    pub width: u16,
+    pub height: u16,
+    /// Time spent loading, rendering and writing the image
+    pub render_time: Duration,
+    /// Fraction of the pixels that aren't fully transparent, from 0 to 1. With a transparent
+    /// background, values near 0 or 1 show the model barely appears or fills the whole frame.
+    /// Opaque backgrounds cover every pixel
+    pub coverage: f32,
+}
+
+/// Progress of an operation producing several images, reported after each one is done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Number of images rendered so far
+    pub completed: usize,
+    /// Number of images the operation will render if not cancelled
+    pub total: usize,
+}
+
+/// Half the diagonal of the 2 x 2 x 2 box the model is scaled to fit in, so a sphere of this
+/// radius holds the model whatever its rotation.
+const FIT_RADIUS: f32 = 1.732_050_8;
+
+/// Portion of the width or height of the frame filled by models framed by `auto_frame`, leaving
+/// a margin around them.
+const FRAME_FILL: f32 = 0.95;
+
+/// Number of lines of a [`GridOptions`] grid on each side of its center, above which they are
+/// spread out.
+const MAX_GRID_LINES: f32 = 256.0;
+
+/// Angle in radians between the faces on either side of an edge above which it is drawn by
+/// [`Shading::SolidWithEdges`] from any view, not only on the silhouette.
+const CREASE_ANGLE: f32 = std::f32::consts::FRAC_PI_6;
+
+/// Depth units the edges of [`Shading::SolidWithEdges`] are pulled towards the camera by.
+const EDGE_DEPTH_BIAS: i32 = 4;
+
+impl RenderOptions {
+    /// Position of the camera, moved along the direction of `cam_position` when `auto_frame` is
+    /// set so the model fills the same portion of the frame regardless of the field of view.
+    #[must_use]
+    pub fn camera_position(&self) -> Vec3 {
+        if self.auto_frame {
+            self.cam_position.normalize_or(Vec3::NEG_Y)
+                * (FIT_RADIUS / (self.cam_fov_deg.to_radians() / 2.0).tan())
+        } else {
+            self.cam_position
+        }
+    }
+
+    /// Faces wound counter-clockwise are the front, unless mirroring the model reversed them.
+    const fn front_face(&self) -> FrontFace {
+        if self.mirror.is_some() {
+            FrontFace::Cw
+        } else {
+            FrontFace::Ccw
+        }
+    }
+
+    /// Primitive state of the model, shared by the pipeline drawing it and the depth pre-pass so
+    /// they draw the same faces.
+    fn model_primitive(&self) -> PrimitiveState {
+        PrimitiveState {
+            front_face: self.front_face(), // Ensure proper face winding
+            // Highlighted back faces have to be drawn
+            cull_mode: if self.shading.xray_opacity().is_some()
+                || self.highlight_backfaces.is_some()
+            {
+                None
+            } else {
+                self.cull_mode.face()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Depth state of the model, shared by the pipeline drawing it and the depth pre-pass so the
+    /// depth read by the post-processing matches what was drawn.
+    fn model_depth_stencil(&self) -> DepthStencilState {
+        // X-ray faces don't hide each other
+        let xray = self.shading.xray_opacity().is_some();
+        DepthStencilState {
+            format: TextureFormat::Depth32Float,
+            depth_write_enabled: self.depth_write && !xray,
+            depth_compare: if self.depth_test && !xray {
+                CompareFunction::Less
+            } else {
+                CompareFunction::Always
+            },
+            stencil: StencilState::default(),
+            bias: self.depth_bias.into(),
+        }
+    }
+
+    /// Decodes a color given as it is written to the image to the linear value rendered on the
+    /// GPU, which sRGB targets encode again when they are written.
+    fn linear_color(&self, color: Vec3) -> Vec3 {
+        match self.color_space {
+            ColorSpace::Srgb => color.map(srgb_to_linear),
+            ColorSpace::Linear => color,
+        }
+    }
+
+    /// Color RGB output is composited over, encoded as it is written to the image.
+    fn flatten_color(&self) -> Vec3 {
+        self.flatten_background
+            .unwrap_or_else(|| match self.background {
+                Background::Solid(color) => color.truncate(),
+                _ => Vec3::ZERO,
+            })
+    }
+
+    /// Color the render target is cleared to, the background color composited over the matte
+    /// if there is one.
+    fn clear_color(&self) -> Color {
+        let color = match self.background {
+            Background::Solid(color) => self.linear_color(color.truncate()).extend(color.w),
+            _ => Vec4::ZERO,
+        };
+        let color = self
+            .matte_background
+            .map(|matte| self.linear_color(matte))
+            .map_or(color, |matte| {
+                color
+                    .truncate()
+                    .mul_add(Vec3::splat(color.w), matte * (1.0 - color.w))
+                    .extend(color.w)
+            });
+        Color {
+            r: f64::from(color.x),
+            g: f64::from(color.y),
+            b: f64::from(color.z),
+            a: f64::from(color.w),
+        }
+    }
+
+    /// Rotation of the model, `model_rotation` after turning Y-up models upright with
+    /// `auto_up`.
+    fn rotation(&self, mesh: &Mesh) -> Quat {
+        if self.auto_up
+            && mesh
+                .format
+                .is_some_and(|format| format.up_axis() == Axis::Y)
+        {
+            self.model_rotation * Quat::from_rotation_x(std::f32::consts::FRAC_PI_2)
+        } else {
+            self.model_rotation
+        }
+    }
+
+    /// Model matrix, responsible for placing the model in the scene before `auto_frame` scales
+    /// it.
+    pub(crate) fn model_matrix(&self, mesh: &Mesh) -> Mat4 {
+        let rotation = self.rotation(mesh);
+        self.mirror
+            .map_or(Mat4::IDENTITY, |axis| axis.reflection(rotation))
+            * mesh.rotate_scale_and_center(rotation, self.center_mode)
+    }
+
+    /// View matrix, responsible for positioning the model relative to the camera.
+    fn view_matrix(&self) -> Mat4 {
+        let position = self.camera_position();
+        // Looking straight down or up, the Y axis points up in the image instead of Z
+        let up = if position.cross(Vec3::Z).length_squared() > 1e-6 * position.length_squared() {
+            Vec3::Z
+        } else {
+            Vec3::Y
+        };
+        Mat4::look_at_rh(position, Vec3::ZERO, up)
+    }
+
+    /// Half the width and height of the visible area, in view space units for orthographic
+    /// projections and as the tangents of half the field of view for perspective ones.
+    ///
+    /// The model is fit along the shorter side of the image, and the field of view spans it, so
+    /// the model fits in tall images as well as wide ones.
+    fn half_extent(&self) -> Vec2 {
+        let aspect = f32::from(self.width) / f32::from(self.height);
+        let short = if self.orthographic {
+            FIT_RADIUS
+        } else {
+            (self.cam_fov_deg.to_radians() / 2.0).tan()
+        };
+        if aspect >= 1.0 {
+            Vec2::new(short * aspect, short)
+        } else {
+            Vec2::new(short, short / aspect)
+        }
+    }
+
+    /// Projection matrix, responsible for adjusting the model according to the FOV and aspect
+    /// ratio.
+    fn projection(&self) -> Mat4 {
+        let aspect = f32::from(self.width) / f32::from(self.height);
+        let half = self.half_extent();
+        if self.orthographic {
+            // wgpu clips depth to 0..1 rather than the -1..1 of the GL convention
+            Mat4::orthographic_rh(-half.x, half.x, -half.y, half.y, self.near, self.far)
+        } else {
+            Mat4::perspective_rh_gl(half.y.atan() * 2.0, aspect, self.near, self.far)
+        }
+    }
+
+    /// Scale making the `vertices` placed by `model_matrix` fill the frame when `auto_frame` is
+    /// set, 1 otherwise.
+    ///
+    /// The scale is the largest one keeping every vertex within both the width and the height
+    /// of the frame, so models much wider than tall, or the other way around, aren't framed by
+    /// the extent along the other side.
+    fn frame_scale(&self, model_matrix: Mat4, vertices: &[Vec3]) -> f32 {
+        if !self.auto_frame {
+            return 1.0;
+        }
+
+        let half = self.half_extent() * FRAME_FILL;
+        let distance = self.camera_position().length();
+        let view_matrix = self.view_matrix() * model_matrix;
+        let scale = vertices
+            .iter()
+            .map(|&vertex| {
+                // Relative to the point the camera looks at, with Z towards the camera
+                let point = view_matrix.transform_point3(vertex) + Vec3::Z * distance;
+                let fit = |offset: f32, half: f32| {
+                    if self.orthographic {
+                        half / offset.abs()
+                    } else {
+                        // Points further from the camera may be further from the middle
+                        let room = point.z.mul_add(half, offset.abs());
+                        if room > 0.0 {
+                            distance * half / room
+                        } else {
+                            f32::INFINITY
+                        }
+                    }
+                };
+                fit(point.x, half.x).min(fit(point.y, half.y))
+            })
+            .fold(f32::INFINITY, f32::min);
+
+        if scale.is_finite() && scale > 0.0 {
+            scale
+        } else {
+            1.0
+        }
+    }
 }
 
 impl From<&Config> for RenderOptions {
@@ -41,20 +1052,189 @@ impl From<&Config> for RenderOptions {
             height: config.height,
             cam_fov_deg: config.cam_fov_deg,
             cam_position: config.cam_position.into(),
+            model_rotation: {
+                let (x, y, z) = config.model_rotation;
+                Quat::from_euler(
+                    EulerRot::XYZ,
+                    x.to_radians(),
+                    y.to_radians(),
+                    z.to_radians(),
+                )
+            },
+            mirror: config.mirror,
+            auto_frame: config.auto_frame,
+            auto_up: config.auto_up,
+            orthographic: config.orthographic,
+            near: config.near,
+            far: config.far,
             sample_count: config.sample_count,
             recalc_normals: config.recalc_normals,
+            reject_non_finite: config.reject_non_finite,
+            allow_remote: config.allow_remote,
+            strict_normals: config.strict_normals,
+            auto_color_objects: config.auto_color_objects,
+            auto_fix_winding: config.auto_fix_winding,
+            convex_hull: config.convex_hull,
+            embed_model_size: config.embed_model_size,
+            input_format: config.input_format,
+            stl_mode: config.stl_mode,
+            object_selector: config.object_selector.clone(),
+            obj_base_dir: None,
+            allow_software: config.allow_software,
+            lights: config.lights.clone(),
+            rim_light: config.rim_light,
+            shininess: config.shininess,
+            specular_strength: config.specular_strength,
+            color_space: config.color_space,
+            background: Background::Solid(config.background.into()),
+            premultiply_alpha: config.premultiply_alpha,
+            matte_background: config.matte_background.map(Into::into),
+            flatten_background: config.flatten_background.map(Into::into),
+            auto_levels: config.auto_levels,
+            output_format: config.output_format,
+            bit_depth: config.bit_depth,
+            topology: config.topology,
+            shading: match config.xray_opacity {
+                Some(opacity) => Shading::XRay { opacity },
+                None if config.toon_bands > 0 => Shading::Toon {
+                    bands: config.toon_bands,
+                    edge_color: config.edges.then(|| config.edge_color.into()),
+                    thickness: config.edge_thickness,
+                },
+                None if config.edges => Shading::SolidWithEdges {
+                    edge_color: config.edge_color.into(),
+                    thickness: config.edge_thickness,
+                },
+                None => Shading::Solid,
+            },
+            cull_mode: config.cull_mode,
+            center_mode: config.center_mode,
+            two_sided: config.two_sided,
+            highlight_backfaces: config.highlight_backfaces.map(Into::into),
+            point_size: config.point_size,
+            ambient_occlusion: config.ambient_occlusion,
+            ao_strength: config.ao_strength,
+            ao_radius: config.ao_radius,
+            debug_overlay: config.debug_overlay,
+            wireframe: config.wireframe,
+            grid: config.grid,
+            line_width: config.line_width,
+            depth_bias: config.depth_bias,
+            depth_test: config.depth_test,
+            depth_write: config.depth_write,
+            fxaa: config.fxaa,
+            taa_samples: config.taa_samples,
+            jitter: Vec2::ZERO,
+            depth_of_field: config.focal_distance.map(|focal_distance| DofOptions {
+                focal_distance,
+                aperture: config.aperture,
+            }),
+            tiled: config.tiled,
+            reuse_buffers: config.reuse_buffers,
+            custom_shader: config.custom_shader.clone(),
+        }
+    }
+}
+
+/// Chooses the smallest index format able to address every vertex of the mesh.
+fn index_format(mesh: &Mesh) -> IndexFormat {
+    // 0xFFFF is reserved as the primitive restart value
+    if mesh
+        .indices
+        .iter()
+        .all(|&i| u16::try_from(i).is_ok_and(|i| i < u16::MAX))
+    {
+        IndexFormat::Uint16
+    } else {
+        IndexFormat::Uint32
+    }
+}
+
+/// Part of the image rendered in a single pass, the whole image unless it is larger than the
+/// device texture limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Tile {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Tile {
+    fn full(opts: &RenderOptions) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width: u32::from(opts.width),
+            height: u32::from(opts.height),
         }
     }
+
+    /// Splits the image into a grid of tiles no larger than `max` pixels on each side.
+    fn grid(opts: &RenderOptions, max: u32) -> Vec<Self> {
+        let (width, height) = (u32::from(opts.width), u32::from(opts.height));
+        // Evenly sized tiles, with widths a multiple of 64 pixels so their rows need no padding
+        let tile_width = width
+            .div_ceil(width.div_ceil(max))
+            .next_multiple_of(64)
+            .min(max / 64 * 64);
+        let tile_height = height.div_ceil(height.div_ceil(max));
+        (0..height)
+            .step_by(tile_height as usize)
+            .flat_map(|y| {
+                (0..width).step_by(tile_width as usize).map(move |x| Self {
+                    x,
+                    y,
+                    width: tile_width.min(width - x),
+                    height: tile_height.min(height - y),
+                })
+            })
+            .collect()
+    }
+
+    /// Projection of the part of the view covered by the tile, scaled up to fill the render
+    /// target.
+    // Image sizes are at most u16::MAX, which f32 represents exactly
+    #[allow(clippy::cast_precision_loss)]
+    fn projection(self, opts: &RenderOptions) -> Mat4 {
+        let image = Vec2::new(f32::from(opts.width), f32::from(opts.height));
+        let origin = Vec2::new(self.x as f32, self.y as f32);
+        let size = Vec2::new(self.width as f32, self.height as f32);
+        // Center of the tile in normalized device coordinates, where y points up
+        let center = (origin * 2.0 + size) / image - 1.0;
+        // Moving the image by the jitter samples it at the opposite offset
+        let jitter = -opts.jitter * 2.0 / size;
+        Mat4::from_translation(Vec3::new(jitter.x, -jitter.y, 0.0))
+            * Mat4::from_scale((image / size).extend(1.0))
+            * Mat4::from_translation(Vec3::new(-center.x, center.y, 0.0))
+            * opts.projection()
+    }
 }
 
 struct Textures {
     main: Texture,
+    /// Has as many samples as `multisample`, or a single one when it isn't created
     depth: Texture,
     multisample: Option<Texture>,
+    /// Single-sampled depth read by the ambient occlusion and depth of field passes when `depth`
+    /// is multisampled
+    sampled_depth: Option<Texture>,
+    /// Output of the horizontal depth of field pass, the vertical pass writes back to `main`
+    dof: Option<Texture>,
+    /// Output of the FXAA pass, which reads `main`
+    fxaa: Option<Texture>,
 }
 
 impl Textures {
-    fn new(device: &Device, size: Extent3d, sample_count: u32) -> Self {
+    fn new(
+        device: &Device,
+        size: Extent3d,
+        sample_count: u32,
+        format: TextureFormat,
+        sample_depth: bool,
+        dof: bool,
+        fxaa: bool,
+    ) -> Self {
         let create_texture = |format, usage, sample_count| {
             device.create_texture(&TextureDescriptor {
                 label: None,
@@ -70,60 +1250,260 @@ impl Textures {
 
         Self {
             main: create_texture(
-                TextureFormat::Rgba8UnormSrgb,
-                TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+                format,
+                TextureUsages::RENDER_ATTACHMENT
+                    | if dof || fxaa {
+                        TextureUsages::TEXTURE_BINDING
+                    } else {
+                        TextureUsages::empty()
+                    }
+                    | if fxaa {
+                        TextureUsages::empty()
+                    } else {
+                        TextureUsages::COPY_SRC
+                    },
                 1,
             ),
             depth: create_texture(
                 TextureFormat::Depth32Float,
-                TextureUsages::RENDER_ATTACHMENT,
+                if sample_depth && sample_count == 1 {
+                    TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING
+                } else {
+                    TextureUsages::RENDER_ATTACHMENT
+                },
                 sample_count,
             ),
             multisample: if sample_count > 1 {
                 Some(create_texture(
-                    TextureFormat::Rgba8UnormSrgb,
+                    format,
                     TextureUsages::RENDER_ATTACHMENT,
                     sample_count,
                 ))
             } else {
                 None
             },
+            // Multisampled depth textures can't be sampled on every backend, so the depth is
+            // rendered again without multisampling instead
+            sampled_depth: (sample_depth && sample_count > 1).then(|| {
+                create_texture(
+                    TextureFormat::Depth32Float,
+                    TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                    1,
+                )
+            }),
+            dof: dof.then(|| {
+                create_texture(
+                    format,
+                    TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                    1,
+                )
+            }),
+            fxaa: fxaa.then(|| {
+                create_texture(
+                    format,
+                    TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+                    1,
+                )
+            }),
         }
     }
 }
 
+/// Lists the adapters available on every backend, e.g. to check which GPU a render would use.
+///
+/// Software adapters are included when the platform provides one.
+#[cfg(not(target_arch = "wasm32"))]
+#[must_use]
+pub fn list_adapters() -> Vec<AdapterInfo> {
+    let instance = Instance::new(InstanceDescriptor {
+        backends: Backends::all(),
+        ..Default::default()
+    });
+    instance
+        .enumerate_adapters(Backends::all())
+        .iter()
+        .map(Adapter::get_info)
+        .collect()
+}
+
+/// Requests wgpu's software adapter.
+async fn request_fallback_adapter(instance: &Instance) -> Result<Adapter, RenderError> {
+    instance
+        .request_adapter(&RequestAdapterOptions {
+            force_fallback_adapter: true,
+            ..Default::default()
+        })
+        .await
+        .ok_or(RenderError::NoFallback)
+}
+
 pub struct ThumbRenderer {
     queue: Queue,
     layout: BindGroupLayout,
     device: Device,
+    /// Why the device was lost, set once it can't render anymore
+    lost: Arc<Mutex<Option<String>>>,
+    /// Mesh, uniform and output buffers reused between renders
+    buffer_pool: BufferPool,
     pipeline: RenderPipeline,
+    point_pipeline: RenderPipeline,
+    background_layout: BindGroupLayout,
+    background_pipeline: RenderPipeline,
+    /// Renders the depth of the model without multisampling, only created when a pass reads it
+    depth_pipeline: Option<RenderPipeline>,
+    /// Ambient occlusion pass, only created when it is enabled
+    ao: Option<AoPipelines>,
+    /// Depth of field passes, only created when it is enabled
+    dof: Option<DofPipeline>,
+    /// Draws the debug overlay and the wireframe, only created when either is enabled
+    lines: Option<LinePipelines>,
+    /// Draws the creases and silhouette of the model, only created for shadings with lines
+    edges: Option<EdgePipeline>,
+    /// Edge smoothing pass, only created when it is enabled
+    fxaa: Option<FxaaPipeline>,
+    /// Number of samples per pixel the pipelines were created with
+    sample_count: u32,
 }
 
-impl ThumbRenderer {
-    pub(crate) async fn new(sample_count: u32) -> Result<Self, RenderError> {
-        // Initialize wgpu
-        let instance = Instance::default();
-        let adapter = instance
-            .request_adapter(&RequestAdapterOptions::default())
-            .await
-            .ok_or_else(|| {
-                RenderError::RenderError("Failed to find a suitable GPU adapter".to_string())
-            })?;
+/// Resources of the ambient occlusion pass.
+struct AoPipelines {
+    layout: BindGroupLayout,
+    pipeline: RenderPipeline,
+}
+
+/// Resources of the overlay lines.
+struct LinePipelines {
+    layout: BindGroupLayout,
+    /// Draws the debug overlay, only created when it is enabled
+    overlay: Option<RenderPipeline>,
+    /// Draws the edges of the triangles, only created when the wireframe is enabled
+    wireframe: Option<RenderPipeline>,
+    /// Draws the grid beneath the model, only created when it is enabled
+    grid: Option<RenderPipeline>,
+}
+
+/// Resources of the edge pass.
+struct EdgePipeline {
+    layout: BindGroupLayout,
+    pipeline: RenderPipeline,
+}
+
+/// Resources of the depth of field passes.
+struct DofPipeline {
+    layout: BindGroupLayout,
+    pipeline: RenderPipeline,
+}
+
+/// Resources of the FXAA pass.
+struct FxaaPipeline {
+    layout: BindGroupLayout,
+    pipeline: RenderPipeline,
+}
+
+impl ThumbRenderer {
+    pub(crate) async fn new(opts: &RenderOptions) -> Result<Self, RenderError> {
+        // Initialize wgpu
+        #[cfg(not(feature = "wasm"))]
+        let instance = Instance::default();
+        // Browsers provide WebGPU, or WebGL2 through the GL backend
+        #[cfg(feature = "wasm")]
+        let instance = Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::BROWSER_WEBGPU | wgpu::Backends::GL,
+            ..Default::default()
+        });
+        let adapter = if let Some(adapter) = instance
+            .request_adapter(&RequestAdapterOptions::default())
+            .await
+        {
+            adapter
+        } else if opts.allow_software {
+            warn!("Failed to find a suitable GPU adapter, falling back to software rendering");
+            request_fallback_adapter(&instance).await?
+        } else {
+            return Err(RenderError::NoAdapter);
+        };
+
+        Self::with_adapter(&adapter, opts).await
+    }
+
+    async fn with_adapter(adapter: &Adapter, opts: &RenderOptions) -> Result<Self, RenderError> {
+        // Both render targets must support the sample count, wgpu panics otherwise
+        let supported = adapter
+            .get_texture_format_features(opts.bit_depth.texture_format(opts.color_space))
+            .flags
+            & adapter
+                .get_texture_format_features(TextureFormat::Depth32Float)
+                .flags;
+        if !supported.sample_count_supported(opts.sample_count) {
+            return Err(RenderError::UnsupportedSampleCount {
+                requested: opts.sample_count,
+                supported: supported.supported_sample_counts(),
+            });
+        }
 
         let (device, queue) = adapter
             .request_device(
                 &DeviceDescriptor {
                     label: None,
-                    required_features: Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
-                    required_limits: Limits::downlevel_defaults(),
+                    // Not available on WebGPU, where the sample counts are checked by wgpu instead
+                    required_features: adapter.features()
+                        & Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+                    required_limits: if cfg!(feature = "wasm") {
+                        Limits::downlevel_webgl2_defaults()
+                    } else {
+                        Limits::downlevel_defaults()
+                    },
                     memory_hints: MemoryHints::MemoryUsage,
                 },
                 None,
             )
             .await?;
 
-        // Load the shader responsible for rendering the model
-        let shader = device.create_shader_module(SHADER);
+        let lost = Arc::new(Mutex::new(None));
+        {
+            let lost = Arc::clone(&lost);
+            device.set_device_lost_callback(move |reason, message| {
+                // The callback is also called when the device is dropped
+                if matches!(
+                    reason,
+                    DeviceLostReason::Unknown
+                        | DeviceLostReason::Destroyed
+                        | DeviceLostReason::DeviceInvalid
+                ) {
+                    warn!("GPU device lost: {reason:?} {message}");
+                    *lost.lock().unwrap_or_else(PoisonError::into_inner) =
+                        Some(format!("{reason:?} {message}"));
+                }
+            });
+        }
+        // Every call on a lost device fails validation, which panics by default. The render
+        // fails with `RenderError::DeviceLost` instead
+        {
+            let lost = Arc::clone(&lost);
+            device.on_uncaptured_error(Box::new(move |error| {
+                if lost
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .is_none()
+                {
+                    panic!("wgpu error: {error}");
+                }
+                debug!("Error on the lost device: {error}");
+            }));
+        }
+
+        // Load the shader responsible for rendering the model. Errors in a custom one are caught
+        // until the pipeline using it is created, which checks it matches the bindings
+        let shader = opts.custom_shader.as_ref().map_or_else(
+            || device.create_shader_module(SHADER),
+            |source| {
+                device.push_error_scope(ErrorFilter::Validation);
+                device.create_shader_module(ShaderModuleDescriptor {
+                    label: Some("custom shader"),
+                    source: ShaderSource::Wgsl(source.into()),
+                })
+            },
+        );
 
         // Memory layout for the uniform buffer that will be passed to the shader
         let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
@@ -159,17 +1539,102 @@ impl ThumbRenderer {
             push_constant_ranges: &[],
         });
 
+        // Vertex, normal and color buffers of the model
+        let vertex_buffers = [
+            VertexBufferLayout {
+                array_stride: size_of::<Vec3>() as BufferAddress,
+                step_mode: VertexStepMode::Vertex,
+                attributes: &[VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: VertexFormat::Float32x3,
+                }],
+            },
+            VertexBufferLayout {
+                array_stride: size_of::<Vec3>() as BufferAddress,
+                step_mode: VertexStepMode::Vertex,
+                attributes: &[VertexAttribute {
+                    offset: 0,
+                    shader_location: 1,
+                    format: VertexFormat::Float32x3,
+                }],
+            },
+            VertexBufferLayout {
+                array_stride: size_of::<Vec3>() as BufferAddress,
+                step_mode: VertexStepMode::Vertex,
+                attributes: &[VertexAttribute {
+                    offset: 0,
+                    shader_location: 2,
+                    format: VertexFormat::Float32x3,
+                }],
+            },
+        ];
+
         // Render pipeline configuration
+        let xray = opts.shading.xray_opacity().is_some();
         let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
             label: None,
             layout: Some(&render_pipeline_layout),
             vertex: VertexState {
                 module: &shader,
                 entry_point: "vert_main",
+                buffers: &vertex_buffers,
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "frag_main",
+                // The shader outputs linear colors, the sRGB target format takes care of encoding,
+                // 16-bit renders are encoded after they are read back
+                targets: &[Some(ColorTargetState {
+                    format: opts.bit_depth.texture_format(opts.color_space),
+                    // X-ray faces add a fraction of their light, set as the blend constant
+                    blend: xray.then_some(BlendState {
+                        color: BlendComponent {
+                            src_factor: BlendFactor::Constant,
+                            dst_factor: BlendFactor::One,
+                            operation: BlendOperation::Add,
+                        },
+                        alpha: BlendComponent {
+                            src_factor: BlendFactor::Constant,
+                            dst_factor: BlendFactor::One,
+                            operation: BlendOperation::Add,
+                        },
+                    }),
+                    write_mask: ColorWrites::ALL,
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: opts.model_primitive(),
+            depth_stencil: Some(opts.model_depth_stencil()),
+            multisample: MultisampleState {
+                count: opts.sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+            cache: None,
+        });
+        if opts.custom_shader.is_some() {
+            if let Some(error) = device.pop_error_scope().await {
+                return Err(RenderError::ShaderCompile(error.to_string()));
+            }
+        }
+
+        // Load the shader responsible for rendering point clouds
+        let point_shader = device.create_shader_module(POINT_SHADER);
+
+        // Point clouds are drawn as one instance of a two-triangle square per vertex, as wgpu
+        // points are always a single pixel wide
+        let point_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&render_pipeline_layout),
+            vertex: VertexState {
+                module: &point_shader,
+                entry_point: "vert_main",
                 buffers: &[
                     VertexBufferLayout {
                         array_stride: size_of::<Vec3>() as BufferAddress,
-                        step_mode: VertexStepMode::Vertex,
+                        step_mode: VertexStepMode::Instance,
                         attributes: &[VertexAttribute {
                             offset: 0,
                             shader_location: 0,
@@ -178,7 +1643,7 @@ impl ThumbRenderer {
                     },
                     VertexBufferLayout {
                         array_stride: size_of::<Vec3>() as BufferAddress,
-                        step_mode: VertexStepMode::Vertex,
+                        step_mode: VertexStepMode::Instance,
                         attributes: &[VertexAttribute {
                             offset: 0,
                             shader_location: 1,
@@ -189,16 +1654,12 @@ impl ThumbRenderer {
                 compilation_options: PipelineCompilationOptions::default(),
             },
             fragment: Some(FragmentState {
-                module: &shader,
+                module: &point_shader,
                 entry_point: "frag_main",
-                targets: &[Some(TextureFormat::Rgba8UnormSrgb.into())],
+                targets: &[Some(opts.bit_depth.texture_format(opts.color_space).into())],
                 compilation_options: PipelineCompilationOptions::default(),
             }),
-            primitive: PrimitiveState {
-                front_face: FrontFace::Ccw,  // Ensure proper face winding
-                cull_mode: Some(Face::Back), // Backface culling
-                ..Default::default()
-            },
+            primitive: PrimitiveState::default(),
             depth_stencil: Some(DepthStencilState {
                 format: TextureFormat::Depth32Float,
                 depth_write_enabled: true,
@@ -207,146 +1668,1113 @@ impl ThumbRenderer {
                 bias: DepthBiasState::default(),
             }),
             multisample: MultisampleState {
-                count: sample_count,
+                count: opts.sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        // Load the shader responsible for rendering the background
+        let background_shader = device.create_shader_module(BACKGROUND_SHADER);
+
+        // Memory layout for the background uniform buffer and texture
+        let background_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        // The background is a full-screen triangle drawn before the model, without touching
+        // the depth buffer
+        let background_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&background_layout],
+                push_constant_ranges: &[],
+            })),
+            vertex: VertexState {
+                module: &background_shader,
+                entry_point: "vert_main",
+                buffers: &[],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &background_shader,
+                entry_point: "frag_main",
+                targets: &[Some(opts.bit_depth.texture_format(opts.color_space).into())],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: Some(DepthStencilState {
+                format: TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: CompareFunction::Always,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multisample: MultisampleState {
+                count: opts.sample_count,
                 ..Default::default()
             },
             multiview: None,
             cache: None,
         });
 
+        // Same as the model pipeline, without color output or multisampling
+        let depth_pipeline = (opts.ambient_occlusion || opts.depth_of_field.is_some()).then(|| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&render_pipeline_layout),
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point: "vert_main",
+                    buffers: &vertex_buffers,
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: None,
+                primitive: opts.model_primitive(),
+                depth_stencil: Some(opts.model_depth_stencil()),
+                multisample: MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        });
+
+        let ao = opts.ambient_occlusion.then(|| {
+            let (layout, pipeline) = Self::ao_pipeline(&device, opts);
+            AoPipelines { layout, pipeline }
+        });
+
+        let dof = opts
+            .depth_of_field
+            .is_some()
+            .then(|| Self::dof_pipeline(&device, opts));
+
+        let lines =
+            (opts.debug_overlay != DebugOverlay::None || opts.wireframe || opts.grid.is_some())
+                .then(|| Self::line_pipelines(&device, opts));
+
+        let edges = opts
+            .shading
+            .edges()
+            .map(|_| Self::edge_pipeline(&device, opts));
+
+        let fxaa = opts.fxaa.then(|| Self::fxaa_pipeline(&device, opts));
+
         Ok(Self {
             queue,
             device,
+            lost,
+            buffer_pool: BufferPool::default(),
             layout: bind_group_layout,
             pipeline,
+            point_pipeline,
+            background_layout,
+            background_pipeline,
+            depth_pipeline,
+            ao,
+            dof,
+            lines,
+            edges,
+            fxaa,
+            sample_count: opts.sample_count,
         })
     }
 
-    pub(crate) fn render(&self, mesh: &Mesh, opts: &RenderOptions) -> Result<Vec<u8>, RenderError> {
-        struct IsSync(RefCell<Option<Result<(), wgpu::BufferAsyncError>>>);
+    /// Creates the pipeline darkening the rendered image where the depth buffer shows the model
+    /// is occluded.
+    fn ao_pipeline(device: &Device, opts: &RenderOptions) -> (BindGroupLayout, RenderPipeline) {
+        let shader = device.create_shader_module(AO_SHADER);
 
-        // SAFETY: This is only used here and it's safe
-        // because we are only using it
-        // to communicate back the result of the map_async call
-        // and we are not using it for anything else
-        #[allow(unsafe_code)]
-        unsafe impl Sync for IsSync {}
+        // Memory layout for the ambient occlusion uniform buffer and the depth texture
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        // Depth textures can't be loaded from in GLSL, but can be bound as float
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
 
-        let device = &self.device;
+        // The occlusion is a full-screen triangle drawn over the resolved image, multiplying its
+        // color and leaving the alpha untouched
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            })),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vert_main",
+                buffers: &[],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "frag_main",
+                targets: &[Some(ColorTargetState {
+                    format: opts.bit_depth.texture_format(opts.color_space),
+                    blend: Some(BlendState {
+                        color: BlendComponent {
+                            src_factor: BlendFactor::Zero,
+                            dst_factor: BlendFactor::Src,
+                            operation: BlendOperation::Add,
+                        },
+                        alpha: BlendComponent {
+                            src_factor: BlendFactor::Zero,
+                            dst_factor: BlendFactor::One,
+                            operation: BlendOperation::Add,
+                        },
+                    }),
+                    write_mask: ColorWrites::ALL,
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
 
-        // Textures size
-        let size = Extent3d {
-            width: u32::from(opts.width),
-            height: u32::from(opts.height),
-            depth_or_array_layers: 1,
-        };
+        (layout, pipeline)
+    }
 
-        let textures = Textures::new(device, size, opts.sample_count);
-        let mut texture_data =
-            Vec::<u8>::with_capacity(opts.width as usize * opts.height as usize * 4);
+    /// Creates the pipeline blurring the rendered image away from the focal plane, drawn once
+    /// for each axis.
+    fn dof_pipeline(device: &Device, opts: &RenderOptions) -> DofPipeline {
+        let shader = device.create_shader_module(DOF_SHADER);
 
-        // Buffer which will hold the final image data
-        let output_buffer = device.create_buffer(&BufferDescriptor {
+        // Memory layout for the depth of field uniform buffer, the image and the depth texture
+        let texture_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                // Depth textures can't be loaded from in GLSL, but can be bound as float
+                sample_type: TextureSampleType::Float { filterable: false },
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture_entry(1),
+                texture_entry(2),
+            ],
+        });
+
+        // A full-screen triangle copying the blurred image to the output texture
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
             label: None,
-            size: texture_data.capacity() as u64,
-            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
-            mapped_at_creation: false,
+            layout: Some(&device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            })),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vert_main",
+                buffers: &[],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "frag_main",
+                targets: &[Some(opts.bit_depth.texture_format(opts.color_space).into())],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
         });
 
-        let mut command_encoder =
-            device.create_command_encoder(&CommandEncoderDescriptor::default());
+        DofPipeline { layout, pipeline }
+    }
 
-        // Render pass block, required to drop the render pass before submitting the command encoder
-        {
-            let create_buffer = |data: &[u8], usage| {
-                device.create_buffer_init(&BufferInitDescriptor {
-                    label: None,
-                    contents: data,
-                    usage,
-                })
-            };
+    /// Creates the pipelines drawing the wireframe, grid and debug overlay lines as anti-aliased
+    /// quads, each only when its option is enabled.
+    fn line_pipelines(device: &Device, opts: &RenderOptions) -> LinePipelines {
+        let shader = device.create_shader_module(LINE_SHADER);
 
-            // Copy the model vertex data into a buffer to be sent to the GPU
-            let vertex_buffer =
-                create_buffer(bytemuck::cast_slice(&mesh.vertices), BufferUsages::VERTEX);
-            // Copy the model normal data into a buffer to be sent to the GPU
-            let normal_buffer =
-                create_buffer(bytemuck::cast_slice(&mesh.normals), BufferUsages::VERTEX);
-            // Copy the model index data into a buffer to be sent to the GPU
-            let index_buffer =
-                create_buffer(bytemuck::cast_slice(&mesh.indices), BufferUsages::INDEX);
-
-            // View matrix (responsible for correctly positioning the model relative to the camera)
-            let view_matrix = Mat4::look_at_rh(opts.cam_position, Vec3::ZERO, Vec3::Z);
-
-            // Perspective matrix (responsible for adjusting the model according to the FOV and aspect ratio)
-            let perspective_matrix = Mat4::perspective_rh_gl(
-                opts.cam_fov_deg.to_radians(),
-                f32::from(opts.width) / f32::from(opts.height),
-                0.1,
-                1024.0,
-            );
+        // Memory layout for the line uniform buffer
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
 
-            // Model matrix (responsible for scaling, rotating and translating the model)
-            let model_matrix = mesh.scale_and_center();
+        let vec3_attribute = |i: u32| VertexAttribute {
+            offset: BufferAddress::from(i) * size_of::<Vec3>() as BufferAddress,
+            shader_location: i,
+            format: VertexFormat::Float32x3,
+        };
 
-            // Vertex uniform data (Input data for the vertex shader)
-            let vert_uniform_data = VertUniformBlock {
-                perspective: perspective_matrix,
-                modelview: view_matrix * model_matrix,
-            };
+        // Each line is an instance of a quad, blended over the model by its coverage
+        let create_pipeline = |depth_compare| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point: "vert_main",
+                    buffers: &[VertexBufferLayout {
+                        array_stride: size_of::<LineInstance>() as BufferAddress,
+                        step_mode: VertexStepMode::Instance,
+                        attributes: &[vec3_attribute(0), vec3_attribute(1), vec3_attribute(2)],
+                    }],
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &shader,
+                    entry_point: "frag_main",
+                    targets: &[Some(ColorTargetState {
+                        format: opts.bit_depth.texture_format(opts.color_space),
+                        blend: Some(BlendState::ALPHA_BLENDING),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive: PrimitiveState::default(),
+                depth_stencil: Some(DepthStencilState {
+                    format: TextureFormat::Depth32Float,
+                    depth_write_enabled: false,
+                    depth_compare,
+                    stencil: StencilState::default(),
+                    bias: DepthBiasState::default(),
+                }),
+                multisample: MultisampleState {
+                    count: opts.sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+                cache: None,
+            })
+        };
 
-            let vert_uniform_buffer = create_buffer(
-                bytemuck::cast_slice(&[vert_uniform_data]),
-                BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-            );
+        LinePipelines {
+            layout,
+            // Debug lines are drawn over the model, whether they are behind it or not
+            overlay: (opts.debug_overlay != DebugOverlay::None)
+                .then(|| create_pipeline(CompareFunction::Always)),
+            // The wireframe is hidden behind the model, edges are drawn where they are level
+            // with it
+            wireframe: opts
+                .wireframe
+                .then(|| create_pipeline(CompareFunction::LessEqual)),
+            // The grid is hidden behind the model like the wireframe
+            grid: opts
+                .grid
+                .is_some()
+                .then(|| create_pipeline(CompareFunction::LessEqual)),
+        }
+    }
 
-            // Fragment uniform data (Input data for the fragment shader)
-            let frag_uniform_data = FragUniformBlock::new(
-                [-1.1, 0.4, 1.0],
-                [0.0, 0.13, 0.26],
-                [0.38, 0.63, 1.0],
-                [1.0, 1.0, 1.0],
-            );
+    /// Creates the pipeline drawing the creases and silhouette of the model as thick lines.
+    fn edge_pipeline(device: &Device, opts: &RenderOptions) -> EdgePipeline {
+        let shader = device.create_shader_module(EDGE_SHADER);
 
-            // Copy the fragment uniform data into a buffer to be sent to the GPU
-            let frag_uniform_buffer = create_buffer(
-                bytemuck::cast_slice(&[frag_uniform_data]),
-                BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-            );
+        // Memory layout for the edge uniform buffer
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
 
-            // Bind group to hold the uniform data buffers that will be passed to the shader
-            let bind_group = device.create_bind_group(&BindGroupDescriptor {
-                label: None,
-                layout: &self.layout,
-                entries: &[
-                    BindGroupEntry {
-                        binding: 0,
-                        resource: vert_uniform_buffer.as_entire_binding(),
-                    },
-                    BindGroupEntry {
-                        binding: 1,
-                        resource: frag_uniform_buffer.as_entire_binding(),
-                    },
-                ],
-            });
+        let vec3_attribute = |i: u32| VertexAttribute {
+            offset: BufferAddress::from(i) * size_of::<Vec3>() as BufferAddress,
+            shader_location: i,
+            format: VertexFormat::Float32x3,
+        };
 
-            // Configure the render pass
-            let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+        // Each edge is an instance of a quad, drawn over the model where it isn't hidden
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            })),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vert_main",
+                buffers: &[VertexBufferLayout {
+                    array_stride: size_of::<EdgeInstance>() as BufferAddress,
+                    step_mode: VertexStepMode::Instance,
+                    attributes: &[
+                        vec3_attribute(0),
+                        vec3_attribute(1),
+                        vec3_attribute(2),
+                        vec3_attribute(3),
+                        VertexAttribute {
+                            offset: 4 * size_of::<Vec3>() as BufferAddress,
+                            shader_location: 4,
+                            format: VertexFormat::Float32,
+                        },
+                    ],
+                }],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "frag_main",
+                targets: &[Some(opts.bit_depth.texture_format(opts.color_space).into())],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: Some(DepthStencilState {
+                format: TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: CompareFunction::LessEqual,
+                stencil: StencilState::default(),
+                // Pull the edges towards the camera, so the faces they lie on don't hide them
+                bias: DepthBiasState {
+                    constant: -EDGE_DEPTH_BIAS,
+                    slope_scale: -1.0,
+                    clamp: 0.0,
+                },
+            }),
+            multisample: MultisampleState {
+                count: opts.sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        EdgePipeline { layout, pipeline }
+    }
+
+    /// Creates the pipeline smoothing the edges of the rendered image.
+    fn fxaa_pipeline(device: &Device, opts: &RenderOptions) -> FxaaPipeline {
+        let shader = device.create_shader_module(FXAA_SHADER);
+
+        // Memory layout for the rendered image and its sampler
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        // A full-screen triangle copying the rendered image to the output texture
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            })),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vert_main",
+                buffers: &[],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "frag_main",
+                targets: &[Some(opts.bit_depth.texture_format(opts.color_space).into())],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        FxaaPipeline { layout, pipeline }
+    }
+
+    /// Creates the resources to draw the background, or `None` if it is only a clear color.
+    fn background_bind_group(
+        &self,
+        opts: &RenderOptions,
+        tile: Tile,
+    ) -> Result<Option<BindGroup>, RenderError> {
+        let device = &self.device;
+
+        let (uniform_data, texture_size, texture_data) = match &opts.background {
+            Background::Solid(_) => return Ok(None),
+            Background::VerticalGradient { top, bottom } => {
+                let top = opts.linear_color(top.truncate()).extend(top.w);
+                let bottom = opts.linear_color(bottom.truncate()).extend(bottom.w);
+                // The part of the gradient behind the tile
+                // Image sizes are at most u16::MAX, which f32 represents exactly
+                #[allow(clippy::cast_precision_loss)]
+                let at = |y: u32| top.lerp(bottom, y as f32 / f32::from(opts.height));
+                (
+                    BackgroundUniformBlock {
+                        top_color: at(tile.y),
+                        bottom_color: at(tile.y + tile.height),
+                    },
+                    Extent3d::default(),
+                    Cow::Borrowed(&[u8::MAX; 4][..]),
+                )
+            }
+            Background::Image(data) => {
+                let size = Extent3d {
+                    width: tile.width,
+                    height: tile.height,
+                    depth_or_array_layers: 1,
+                };
+                let expected = opts.width as usize * opts.height as usize * 4;
+                if data.len() != expected {
+                    return Err(RenderError::RenderError(format!(
+                        "Background image has {} bytes, expected {expected}",
+                        data.len()
+                    )));
+                }
+                let data = if tile == Tile::full(opts) {
+                    Cow::Borrowed(data.as_slice())
+                } else {
+                    let row = opts.width as usize * 4;
+                    let (x, width) = (tile.x as usize * 4, tile.width as usize * 4);
+                    Cow::Owned(
+                        data.chunks_exact(row)
+                            .skip(tile.y as usize)
+                            .take(tile.height as usize)
+                            .flat_map(|row| &row[x..x + width])
+                            .copied()
+                            .collect(),
+                    )
+                };
+                (
+                    BackgroundUniformBlock {
+                        top_color: Vec4::ONE,
+                        bottom_color: Vec4::ONE,
+                    },
+                    size,
+                    data,
+                )
+            }
+        };
+
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&[uniform_data]),
+            usage: BufferUsages::UNIFORM,
+        });
+
+        // Image data is stored in the same color space as the output, so it is copied unchanged
+        let format = opts.color_space.texture_format();
+        let texture = device.create_texture_with_data(
+            &self.queue,
+            &TextureDescriptor {
+                label: None,
+                size: texture_size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format,
+                usage: TextureUsages::TEXTURE_BINDING,
+                view_formats: &[format],
+            },
+            TextureDataOrder::LayerMajor,
+            &texture_data,
+        );
+
+        Ok(Some(device.create_bind_group(&BindGroupDescriptor {
+            label: None,
+            layout: &self.background_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::Sampler(
+                        &device.create_sampler(&SamplerDescriptor::default()),
+                    ),
+                },
+            ],
+        })))
+    }
+
+    /// Fails with `RenderError::DeviceLost` if the device was lost.
+    fn check_lost(&self) -> Result<(), RenderError> {
+        self.lost
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .map_or(Ok(()), |reason| Err(RenderError::DeviceLost(reason)))
+    }
+
+    /// Renders like [`Self::render`], replacing the renderer with a new one and trying once more
+    /// if the device was lost, e.g. when the GPU was reset.
+    pub(crate) async fn render_or_recreate(
+        &mut self,
+        mesh: &Mesh,
+        opts: &RenderOptions,
+    ) -> Result<Vec<u8>, RenderError> {
+        self.render_or_recreate_cancellable(mesh, opts, &AtomicBool::new(false))
+            .await
+    }
+
+    /// Renders like [`Self::render_or_recreate`], failing with `RenderError::Cancelled` once
+    /// `cancel` is set.
+    pub(crate) async fn render_or_recreate_cancellable(
+        &mut self,
+        mesh: &Mesh,
+        opts: &RenderOptions,
+        cancel: &AtomicBool,
+    ) -> Result<Vec<u8>, RenderError> {
+        let texture_data = self.render_texture_or_recreate(mesh, opts, cancel).await?;
+        Ok(finish_image(texture_data, opts))
+    }
+
+    /// Renders like [`Self::render_or_recreate`], also returning the fraction of the pixels that
+    /// aren't fully transparent, see [`RenderResult::coverage`].
+    #[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+    pub(crate) async fn render_with_coverage(
+        &mut self,
+        mesh: &Mesh,
+        opts: &RenderOptions,
+    ) -> Result<(Vec<u8>, f32), RenderError> {
+        let texture_data = self
+            .render_texture_or_recreate(mesh, opts, &AtomicBool::new(false))
+            .await?;
+        let coverage = coverage(&texture_data, opts.bit_depth);
+        Ok((finish_image(texture_data, opts), coverage))
+    }
+
+    /// Renders the pixels in the format of the texture like [`Self::render_texture`], with a new
+    /// device if the current one was lost.
+    async fn render_texture_or_recreate(
+        &mut self,
+        mesh: &Mesh,
+        opts: &RenderOptions,
+        cancel: &AtomicBool,
+    ) -> Result<Vec<u8>, RenderError> {
+        match self.render_texture(mesh, opts, cancel).await {
+            Err(RenderError::DeviceLost(reason)) => {
+                warn!("Recreating the renderer after the GPU device was lost: {reason}");
+                *self = Self::new(opts).await?;
+                self.render_texture(mesh, opts, cancel).await
+            }
+            result => result,
+        }
+    }
+
+    pub(crate) async fn render(
+        &self,
+        mesh: &Mesh,
+        opts: &RenderOptions,
+    ) -> Result<Vec<u8>, RenderError> {
+        self.render_cancellable(mesh, opts, &AtomicBool::new(false))
+            .await
+    }
+
+    /// Renders like [`Self::render`], failing with `RenderError::Cancelled` once `cancel` is set.
+    ///
+    /// Cancellation is checked before the commands of each frame are submitted and once the GPU
+    /// is done with them, the resources of the frame are released instead of going back to the
+    /// buffer pool.
+    pub(crate) async fn render_cancellable(
+        &self,
+        mesh: &Mesh,
+        opts: &RenderOptions,
+        cancel: &AtomicBool,
+    ) -> Result<Vec<u8>, RenderError> {
+        let texture_data = self.render_texture(mesh, opts, cancel).await?;
+        Ok(finish_image(texture_data, opts))
+    }
+
+    /// Renders the whole image, stitching tiles together if needed, returning the pixels in the
+    /// format of the texture.
+    async fn render_texture(
+        &self,
+        mesh: &Mesh,
+        opts: &RenderOptions,
+        cancel: &AtomicBool,
+    ) -> Result<Vec<u8>, RenderError> {
+        check_cancelled(cancel)?;
+        self.check_lost()?;
+        // Negated comparisons so NaN is rejected as well
+        if !(opts.near > 0.0 && opts.far > opts.near) {
+            return Err(RenderError::InvalidClipPlanes {
+                near: opts.near,
+                far: opts.far,
+            });
+        }
+        if !(opts.cam_fov_deg > 0.0 && opts.cam_fov_deg < 180.0) {
+            return Err(RenderError::InvalidFov(opts.cam_fov_deg));
+        }
+
+        // wgpu panics when creating textures over the limit
+        let max = self.device.limits().max_texture_dimension_2d;
+        let requested = u32::from(opts.width.max(opts.height));
+        if requested <= max {
+            return self.render_tile(mesh, opts, Tile::full(opts), cancel).await;
+        }
+        if !opts.tiled {
+            return Err(RenderError::DimensionsTooLarge { requested, max });
+        }
+
+        // Stitch the tiles together row by row, in the format of the texture so the whole image
+        // is post-processed at once, with the same levels across tiles
+        let bytes_per_pixel = if opts.bit_depth == BitDepth::Eight {
+            4
+        } else {
+            4 * size_of::<f16>()
+        };
+        let row = opts.width as usize * bytes_per_pixel;
+        let mut image = vec![0; row * opts.height as usize];
+        let tiles = Tile::grid(opts, max);
+        debug!("Rendering in {} tiles.", tiles.len());
+        for tile in tiles {
+            let data = self.render_tile(mesh, opts, tile, cancel).await?;
+            let tile_row = tile.width as usize * bytes_per_pixel;
+            let x = tile.x as usize * bytes_per_pixel;
+            for (y, tile_data) in data.chunks_exact(tile_row).enumerate() {
+                let start = (tile.y as usize + y) * row + x;
+                image[start..start + tile_row].copy_from_slice(tile_data);
+            }
+        }
+        Ok(image)
+    }
+
+    /// Renders the part of the image covered by `tile`, returning the pixels in the format of the
+    /// texture.
+    async fn render_tile(
+        &self,
+        mesh: &Mesh,
+        opts: &RenderOptions,
+        tile: Tile,
+        cancel: &AtomicBool,
+    ) -> Result<Vec<u8>, RenderError> {
+        if opts.taa_samples > 1 {
+            self.render_jittered(mesh, opts, tile, cancel).await
+        } else {
+            self.render_frame(mesh, opts, tile, cancel).await
+        }
+    }
+
+    /// Renders `opts.taa_samples` frames of the tile, each with the pixels sampled at another
+    /// point inside them, and averages them in linear color like a multisample resolve.
+    async fn render_jittered(
+        &self,
+        mesh: &Mesh,
+        opts: &RenderOptions,
+        tile: Tile,
+        cancel: &AtomicBool,
+    ) -> Result<Vec<u8>, RenderError> {
+        let srgb = opts.bit_depth == BitDepth::Eight && opts.color_space == ColorSpace::Srgb;
+        let mut sum = Vec::<f32>::new();
+        for index in 0..opts.taa_samples {
+            let jittered = RenderOptions {
+                jitter: opts.jitter + sample_offset(index),
+                ..opts.clone()
+            };
+            let frame = self.render_frame(mesh, &jittered, tile, cancel).await?;
+
+            // The frame is in the format of the texture, 8-bit values are sRGB encoded with it
+            let values: Vec<f32> = match opts.bit_depth {
+                BitDepth::Eight => frame
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &value)| {
+                        let value = f32::from(value) / 255.0;
+                        if srgb && i % 4 != 3 {
+                            srgb_to_linear(value)
+                        } else {
+                            value
+                        }
+                    })
+                    .collect(),
+                BitDepth::Sixteen | BitDepth::Float => frame
+                    .chunks_exact(2)
+                    .map(|bytes| f16::from_le_bytes([bytes[0], bytes[1]]).to_f32())
+                    .collect(),
+            };
+            if sum.is_empty() {
+                sum = values;
+            } else {
+                sum.iter_mut()
+                    .zip(values)
+                    .for_each(|(sum, value)| *sum += value);
+            }
+        }
+
+        let samples = f32::from(opts.taa_samples);
+        Ok(match opts.bit_depth {
+            BitDepth::Eight => sum
+                .into_iter()
+                .enumerate()
+                .map(|(i, sum)| {
+                    let value = sum / samples;
+                    let value = if srgb && i % 4 != 3 {
+                        linear_to_srgb(value)
+                    } else {
+                        value
+                    };
+                    // Values are clamped to the u8 range
+                    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                    {
+                        (value.clamp(0.0, 1.0) * 255.0).round() as u8
+                    }
+                })
+                .collect(),
+            BitDepth::Sixteen | BitDepth::Float => sum
+                .into_iter()
+                .flat_map(|sum| f16::from_f32(sum / samples).to_le_bytes())
+                .collect(),
+        })
+    }
+
+    /// Renders a single frame of the tile, returning the pixels in the format of the texture.
+    async fn render_frame(
+        &self,
+        mesh: &Mesh,
+        opts: &RenderOptions,
+        tile: Tile,
+        cancel: &AtomicBool,
+    ) -> Result<Vec<u8>, RenderError> {
+        let device = &self.device;
+
+        // Textures size
+        let size = Extent3d {
+            width: tile.width,
+            height: tile.height,
+            depth_or_array_layers: 1,
+        };
+
+        // Point clouds don't occlude themselves, and their depth isn't rendered again without
+        // multisampling
+        let points = opts.topology.is_points(mesh);
+        let ao = self.ao.as_ref().filter(|_| !points);
+        let dof = self
+            .dof
+            .as_ref()
+            .zip(opts.depth_of_field)
+            .filter(|_| !points);
+
+        // The color and depth attachments must have as many samples as the pipelines drawing to
+        // them, even when the renderer is reused with other options
+        let textures = Textures::new(
+            device,
+            size,
+            self.sample_count,
+            opts.bit_depth.texture_format(opts.color_space),
+            ao.is_some() || dof.is_some(),
+            dof.is_some(),
+            self.fxaa.is_some(),
+        );
+        let bytes_per_pixel = match opts.bit_depth {
+            BitDepth::Eight => 4,
+            BitDepth::Sixteen | BitDepth::Float => 8,
+        };
+        // Rows are copied into the output buffer at a multiple of 256 bytes apart
+        let row_bytes = tile.width * bytes_per_pixel;
+        let padded_row_bytes = row_bytes.next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+        let mut texture_data = Vec::<u8>::with_capacity(row_bytes as usize * tile.height as usize);
+
+        // Buffer which will hold the final image data
+        let output_buffer = self.buffer_pool.take(
+            device,
+            u64::from(padded_row_bytes) * u64::from(tile.height),
+            BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        );
+
+        let mut command_encoder =
+            device.create_command_encoder(&CommandEncoderDescriptor::default());
+
+        let create_buffer = |data: &[u8], usage| {
+            device.create_buffer_init(&BufferInitDescriptor {
+                label: None,
+                contents: data,
+                usage,
+            })
+        };
+        // The mesh and uniform buffers are written to buffers of the pool
+        let pooled_buffer =
+            |data: &[u8], usage| self.buffer_pool.take_init(device, &self.queue, data, usage);
+
+        // Copy the model vertex data into a buffer to be sent to the GPU
+        let vertex_buffer =
+            pooled_buffer(bytemuck::cast_slice(&mesh.vertices), BufferUsages::VERTEX);
+        // Copy the model normal data into a buffer to be sent to the GPU
+        let normal_buffer =
+            pooled_buffer(bytemuck::cast_slice(&mesh.normals), BufferUsages::VERTEX);
+        // Copy the model color data into a buffer to be sent to the GPU, meshes without a
+        // color for every vertex are drawn white
+        let color_buffer = if mesh.colors.len() == mesh.vertices.len() {
+            pooled_buffer(bytemuck::cast_slice(&mesh.colors), BufferUsages::VERTEX)
+        } else {
+            pooled_buffer(
+                bytemuck::cast_slice(&vec![Vec3::ONE; mesh.vertices.len()]),
+                BufferUsages::VERTEX,
+            )
+        };
+        // Copy the model index data into a buffer to be sent to the GPU
+        let index_format = index_format(mesh);
+        let index_buffer = match index_format {
+            IndexFormat::Uint16 => {
+                // Every index was checked to fit in a u16 by index_format
+                #[allow(clippy::cast_possible_truncation)]
+                let indices = mesh.indices.iter().map(|&i| i as u16).collect::<Vec<_>>();
+                pooled_buffer(bytemuck::cast_slice(&indices), BufferUsages::INDEX)
+            }
+            IndexFormat::Uint32 => {
+                pooled_buffer(bytemuck::cast_slice(&mesh.indices), BufferUsages::INDEX)
+            }
+        };
+        debug!("Using {index_format:?} indices.");
+        let index_count = u32::try_from(mesh.indices.len())
+            .map_err(|_| RenderError::RenderError("Index count exceeds u32::MAX".to_string()))?;
+
+        // View matrix (responsible for correctly positioning the model relative to the camera)
+        let view_matrix = opts.view_matrix();
+
+        // Perspective matrix (responsible for adjusting the model according to the FOV and aspect ratio)
+        let perspective_matrix = tile.projection(opts);
+
+        // Model matrix (responsible for scaling, rotating and translating the model)
+        let model_matrix = opts.model_matrix(mesh);
+        let model_matrix =
+            Mat4::from_scale(Vec3::splat(opts.frame_scale(model_matrix, &mesh.vertices)))
+                * model_matrix;
+
+        // Vertex uniform data (Input data for the vertex shader)
+        // Tile sizes are at most u16::MAX, which f32 represents exactly
+        #[allow(clippy::cast_precision_loss)]
+        let vert_uniform_data = VertUniformBlock {
+            perspective: perspective_matrix,
+            modelview: view_matrix * model_matrix,
+            // Half extent of a point in clip space, a pixel is 2 / size units wide
+            point_size: opts.point_size / Vec2::new(size.width as f32, size.height as f32),
+            _padding: Vec2::ZERO,
+        };
+
+        let vert_uniform_buffer = pooled_buffer(
+            bytemuck::cast_slice(&[vert_uniform_data]),
+            BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        );
+
+        // Fragment uniform data (Input data for the fragment shader)
+        if opts.lights.len() > MAX_LIGHTS {
+            warn!(
+                "Only the first {MAX_LIGHTS} of {} lights are used",
+                opts.lights.len()
+            );
+        }
+        let mut frag_uniform_data = FragUniformBlock::new(
+            &opts.lights,
+            [0.0, 0.13, 0.26],
+            [0.38, 0.63, 1.0],
+            [1.0, 1.0, 1.0],
+            opts.shininess,
+            opts.specular_strength,
+            // The back of x-ray faces is seen through the front
+            opts.two_sided || opts.shading.xray_opacity().is_some(),
+        );
+        frag_uniform_data.toon_bands = opts.shading.toon_bands().into();
+        if let Some(rim_light) = opts.rim_light {
+            frag_uniform_data.rim_color = rim_light.color.into();
+            frag_uniform_data.rim_power = rim_light.power;
+        }
+        if let Some(backface_color) = opts.highlight_backfaces {
+            frag_uniform_data.backface_color = opts.linear_color(backface_color).into();
+            frag_uniform_data.highlight_backfaces = 1;
+        }
+
+        // Copy the fragment uniform data into a buffer to be sent to the GPU
+        let frag_uniform_buffer = pooled_buffer(
+            bytemuck::cast_slice(&[frag_uniform_data]),
+            BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        );
+
+        // Bind group to hold the uniform data buffers that will be passed to the shader
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: None,
+            layout: &self.layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: vert_uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: frag_uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let line_bind_group = self.lines.as_ref().map(|lines| {
+            let line_uniform_buffer = create_buffer(
+                bytemuck::cast_slice(&[LineUniformBlock {
+                    perspective: vert_uniform_data.perspective,
+                    modelview: vert_uniform_data.modelview,
+                    // A pixel is 2 / size units wide
+                    #[allow(clippy::cast_precision_loss)]
+                    pixel_size: 2.0 / Vec2::new(size.width as f32, size.height as f32),
+                    width: opts.line_width,
+                    _padding: 0.0,
+                }]),
+                BufferUsages::UNIFORM,
+            );
+            device.create_bind_group(&BindGroupDescriptor {
+                label: None,
+                layout: &lines.layout,
+                entries: &[BindGroupEntry {
+                    binding: 0,
+                    resource: line_uniform_buffer.as_entire_binding(),
+                }],
+            })
+        });
+
+        // Render pass block, required to drop the render pass before submitting the command encoder
+        {
+            let background_bind_group = self.background_bind_group(opts, tile)?;
+
+            // Render the depth the ambient occlusion and depth of field passes read, if they
+            // can't read the multisampled one
+            if let (Some(depth_pipeline), Some(sampled_depth)) =
+                (&self.depth_pipeline, &textures.sampled_depth)
+            {
+                let mut depth_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[],
+                    depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                        view: &sampled_depth.create_view(&TextureViewDescriptor::default()),
+                        depth_ops: Some(Operations {
+                            load: LoadOp::Clear(1.0),
+                            store: StoreOp::Store,
+                        }),
+                        stencil_ops: None,
+                    }),
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                });
+                depth_pass.set_pipeline(depth_pipeline);
+                depth_pass.set_bind_group(0, &bind_group, &[]);
+                depth_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                depth_pass.set_vertex_buffer(1, normal_buffer.slice(..));
+                depth_pass.set_vertex_buffer(2, color_buffer.slice(..));
+                depth_pass.set_index_buffer(index_buffer.slice(..), index_format);
+                depth_pass.draw_indexed(0..index_count, 0, 0..1);
+            }
+
+            // Configure the render pass
+            let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
                 label: None,
                 color_attachments: &[Some(RenderPassColorAttachment {
                     view: &textures
                         .multisample
                         .as_ref()
-                        .map_or_else(|| &textures.main, |multisample| multisample)
+                        .unwrap_or(&textures.main)
                         .create_view(&TextureViewDescriptor::default()),
                     resolve_target: textures
                         .multisample
+                        .as_ref()
                         .map(|_| textures.main.create_view(&TextureViewDescriptor::default()))
                         .as_ref(),
                     ops: Operations {
-                        // TODO: Use the background color provided by the user
-                        load: LoadOp::Clear(Color::TRANSPARENT),
+                        load: LoadOp::Clear(opts.clear_color()),
                         store: StoreOp::Store,
                     },
                 })],
@@ -364,23 +2792,315 @@ impl ThumbRenderer {
                 timestamp_writes: None,
             });
 
-            render_pass.set_pipeline(&self.pipeline);
-            render_pass.set_bind_group(0, &bind_group, &[]);
-            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-            render_pass.set_vertex_buffer(1, normal_buffer.slice(..));
-            render_pass.set_index_buffer(index_buffer.slice(..), IndexFormat::Uint32);
-
-            // Render the model vertices
-            let index_count = u32::try_from(mesh.indices.len()).map_err(|_| {
-                RenderError::RenderError("Index count exceeds u32::MAX".to_string())
-            })?;
-            render_pass.draw_indexed(0..index_count, 0, 0..1);
+            // Draw the background behind everything else
+            if let Some(background_bind_group) = &background_bind_group {
+                render_pass.set_pipeline(&self.background_pipeline);
+                render_pass.set_bind_group(0, background_bind_group, &[]);
+                render_pass.draw(0..3, 0..1);
+            }
+
+            if opts.topology.is_points(mesh) {
+                render_pass.set_pipeline(&self.point_pipeline);
+                render_pass.set_bind_group(0, &bind_group, &[]);
+                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                render_pass.set_vertex_buffer(1, color_buffer.slice(..));
+
+                // Render a square for every vertex
+                let vertex_count = u32::try_from(mesh.vertices.len()).map_err(|_| {
+                    RenderError::RenderError("Vertex count exceeds u32::MAX".to_string())
+                })?;
+                render_pass.draw(0..6, 0..vertex_count);
+            } else {
+                render_pass.set_pipeline(&self.pipeline);
+                if let Some(opacity) = opts.shading.xray_opacity() {
+                    let opacity = f64::from(opacity);
+                    render_pass.set_blend_constant(Color {
+                        r: opacity,
+                        g: opacity,
+                        b: opacity,
+                        a: opacity,
+                    });
+                }
+                render_pass.set_bind_group(0, &bind_group, &[]);
+                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                render_pass.set_vertex_buffer(1, normal_buffer.slice(..));
+                render_pass.set_vertex_buffer(2, color_buffer.slice(..));
+                render_pass.set_index_buffer(index_buffer.slice(..), index_format);
+
+                // Render the model vertices
+                render_pass.draw_indexed(0..index_count, 0, 0..1);
+
+                if let (Some(wireframe), Some(line_bind_group)) = (
+                    self.lines
+                        .as_ref()
+                        .and_then(|lines| lines.wireframe.as_ref()),
+                    &line_bind_group,
+                ) {
+                    // Every side of every triangle, sides shared by two triangles are drawn twice
+                    let instances = mesh
+                        .indices
+                        .chunks_exact(3)
+                        .flat_map(|t| [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])])
+                        .map(|(start, end)| LineInstance {
+                            start: mesh.vertices[start as usize],
+                            end: mesh.vertices[end as usize],
+                            color: Vec3::ZERO,
+                        })
+                        .collect::<Vec<_>>();
+                    let instance_count = u32::try_from(instances.len()).map_err(|_| {
+                        RenderError::RenderError("Edge count exceeds u32::MAX".to_string())
+                    })?;
+                    let instance_buffer =
+                        create_buffer(bytemuck::cast_slice(&instances), BufferUsages::VERTEX);
+                    render_pass.set_pipeline(wireframe);
+                    render_pass.set_bind_group(0, line_bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, instance_buffer.slice(..));
+                    render_pass.draw(0..6, 0..instance_count);
+                }
+
+                if let (Some(edges), Some((edge_color, thickness))) =
+                    (&self.edges, opts.shading.edges())
+                {
+                    let instances = mesh
+                        .edges(CREASE_ANGLE)
+                        .iter()
+                        .map(EdgeInstance::from)
+                        .collect::<Vec<_>>();
+                    let instance_count = u32::try_from(instances.len()).map_err(|_| {
+                        RenderError::RenderError("Edge count exceeds u32::MAX".to_string())
+                    })?;
+                    let instance_buffer =
+                        create_buffer(bytemuck::cast_slice(&instances), BufferUsages::VERTEX);
+                    // Half the thickness in clip space, like the point size
+                    #[allow(clippy::cast_precision_loss)]
+                    let half_width = thickness / Vec2::new(size.width as f32, size.height as f32);
+                    let edge_uniform_buffer = create_buffer(
+                        bytemuck::cast_slice(&[EdgeUniformBlock {
+                            perspective: vert_uniform_data.perspective,
+                            modelview: vert_uniform_data.modelview,
+                            color: edge_color.extend(1.0),
+                            half_width,
+                            _padding: Vec2::ZERO,
+                        }]),
+                        BufferUsages::UNIFORM,
+                    );
+                    let edge_bind_group = device.create_bind_group(&BindGroupDescriptor {
+                        label: None,
+                        layout: &edges.layout,
+                        entries: &[BindGroupEntry {
+                            binding: 0,
+                            resource: edge_uniform_buffer.as_entire_binding(),
+                        }],
+                    });
+                    render_pass.set_pipeline(&edges.pipeline);
+                    render_pass.set_bind_group(0, &edge_bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, instance_buffer.slice(..));
+                    render_pass.draw(0..6, 0..instance_count);
+                }
+            }
+
+            if let (Some(grid_pipeline), Some(line_bind_group), Some(grid)) = (
+                self.lines.as_ref().and_then(|lines| lines.grid.as_ref()),
+                &line_bind_group,
+                opts.grid,
+            ) {
+                let instances =
+                    grid.lines(&mesh.vertices, model_matrix, opts.linear_color(grid.color));
+                let instance_buffer =
+                    create_buffer(bytemuck::cast_slice(&instances), BufferUsages::VERTEX);
+                render_pass.set_pipeline(grid_pipeline);
+                render_pass.set_bind_group(0, line_bind_group, &[]);
+                render_pass.set_vertex_buffer(0, instance_buffer.slice(..));
+                // At most 2 * (2 * MAX_GRID_LINES + 1) lines
+                #[allow(clippy::cast_possible_truncation)]
+                render_pass.draw(0..6, 0..instances.len() as u32);
+            }
+
+            if let (Some(overlay), Some(line_bind_group)) = (
+                self.lines.as_ref().and_then(|lines| lines.overlay.as_ref()),
+                &line_bind_group,
+            ) {
+                let instances = opts.debug_overlay.lines(&mesh.bounds);
+                let instance_buffer =
+                    create_buffer(bytemuck::cast_slice(&instances), BufferUsages::VERTEX);
+                render_pass.set_pipeline(overlay);
+                render_pass.set_bind_group(0, line_bind_group, &[]);
+                render_pass.set_vertex_buffer(0, instance_buffer.slice(..));
+                // At most 15 lines
+                #[allow(clippy::cast_possible_truncation)]
+                render_pass.draw(0..6, 0..instances.len() as u32);
+            }
         };
 
+        // Darken the resolved image where the model occludes itself
+        if let Some(ao) = ao {
+            let projection = tile.projection(opts);
+            let ao_uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&[AoUniformBlock {
+                    projection,
+                    inverse_projection: projection.inverse(),
+                    radius: opts.ao_radius,
+                    strength: opts.ao_strength.clamp(0.0, 1.0),
+                    _padding: Vec2::ZERO,
+                }]),
+                usage: BufferUsages::UNIFORM,
+            });
+            let ao_bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: None,
+                layout: &ao.layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: ao_uniform_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(
+                            &textures
+                                .sampled_depth
+                                .as_ref()
+                                .unwrap_or(&textures.depth)
+                                .create_view(&TextureViewDescriptor::default()),
+                        ),
+                    },
+                ],
+            });
+
+            let mut ao_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &textures.main.create_view(&TextureViewDescriptor::default()),
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Load,
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            ao_pass.set_pipeline(&ao.pipeline);
+            ao_pass.set_bind_group(0, &ao_bind_group, &[]);
+            ao_pass.draw(0..3, 0..1);
+        }
+
+        // Blur the image away from the focal plane, horizontally into the depth of field texture
+        // then vertically back into the main texture
+        if let (Some((dof, dof_opts)), Some(dof_texture)) = (dof, &textures.dof) {
+            let inverse_projection = tile.projection(opts).inverse();
+            let depth_view = textures
+                .sampled_depth
+                .as_ref()
+                .unwrap_or(&textures.depth)
+                .create_view(&TextureViewDescriptor::default());
+            // Image sizes are at most u16::MAX, which f32 represents exactly
+            #[allow(clippy::cast_precision_loss)]
+            let blur_scale = dof_opts.aperture.max(0.0) * f32::from(opts.height);
+            for (direction, source, target) in [
+                (Vec2::X, &textures.main, dof_texture),
+                (Vec2::Y, dof_texture, &textures.main),
+            ] {
+                let dof_uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+                    label: None,
+                    contents: bytemuck::cast_slice(&[DofUniformBlock {
+                        inverse_projection,
+                        direction,
+                        focal_distance: dof_opts.focal_distance,
+                        blur_scale,
+                    }]),
+                    usage: BufferUsages::UNIFORM,
+                });
+                let dof_bind_group = device.create_bind_group(&BindGroupDescriptor {
+                    label: None,
+                    layout: &dof.layout,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: dof_uniform_buffer.as_entire_binding(),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: BindingResource::TextureView(
+                                &source.create_view(&TextureViewDescriptor::default()),
+                            ),
+                        },
+                        BindGroupEntry {
+                            binding: 2,
+                            resource: BindingResource::TextureView(&depth_view),
+                        },
+                    ],
+                });
+
+                let mut dof_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(RenderPassColorAttachment {
+                        view: &target.create_view(&TextureViewDescriptor::default()),
+                        resolve_target: None,
+                        ops: Operations {
+                            load: LoadOp::Clear(Color::TRANSPARENT),
+                            store: StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                });
+                dof_pass.set_pipeline(&dof.pipeline);
+                dof_pass.set_bind_group(0, &dof_bind_group, &[]);
+                dof_pass.draw(0..3, 0..1);
+            }
+        }
+
+        // Smooth the edges of the final image into the texture copied to the output buffer
+        if let (Some(fxaa), Some(fxaa_texture)) = (&self.fxaa, &textures.fxaa) {
+            let fxaa_bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: None,
+                layout: &fxaa.layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(
+                            &textures.main.create_view(&TextureViewDescriptor::default()),
+                        ),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::Sampler(&device.create_sampler(
+                            &SamplerDescriptor {
+                                mag_filter: FilterMode::Linear,
+                                min_filter: FilterMode::Linear,
+                                ..Default::default()
+                            },
+                        )),
+                    },
+                ],
+            });
+
+            let mut fxaa_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &fxaa_texture.create_view(&TextureViewDescriptor::default()),
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::TRANSPARENT),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            fxaa_pass.set_pipeline(&fxaa.pipeline);
+            fxaa_pass.set_bind_group(0, &fxaa_bind_group, &[]);
+            fxaa_pass.draw(0..3, 0..1);
+        }
+
         // Queue copy of the texture data (containing the rendered image) to the output buffer
         command_encoder.copy_texture_to_buffer(
             ImageCopyTexture {
-                texture: &textures.main,
+                texture: textures.fxaa.as_ref().unwrap_or(&textures.main),
                 mip_level: 0,
                 origin: Origin3d::ZERO,
                 aspect: TextureAspect::All,
@@ -389,15 +3109,15 @@ impl ThumbRenderer {
                 buffer: &output_buffer,
                 layout: ImageDataLayout {
                     offset: 0,
-                    // Ensure bytes_per_row is a multiple of 256
-                    bytes_per_row: Some((u32::from(opts.width) * 4 + 255) / 256 * 256),
-                    rows_per_image: Some(u32::from(opts.height)),
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: Some(tile.height),
                 },
             },
             size,
         );
 
         // Submit all queued command to be executed
+        check_cancelled(cancel)?;
         self.queue.submit(Some(command_encoder.finish()));
         debug!("Commands submitted.");
 
@@ -406,32 +3126,677 @@ impl ThumbRenderer {
         // Wait for model to be rendered then retrieve image data from the output buffer
         let buffer_slice = output_buffer.slice(..);
 
-        let res = Arc::new(IsSync(RefCell::new(None)));
-        buffer_slice.map_async(MapMode::Read, {
-            let res = Arc::clone(&res);
-            move |r| {
-                res.0.borrow_mut().replace(r);
-            }
-        });
+        let mapped = CallbackFuture::default();
+        buffer_slice.map_async(MapMode::Read, mapped.callback());
+        // Native backends call the callback while polling, browsers do it once the page yields
         device.poll(Maintain::wait()).panic_on_timeout();
-        res.0
-            .borrow_mut()
-            .take()
-            .ok_or_else(|| RenderError::RenderError("Failed to receive render result".to_string()))?
-            .map_err(|e| RenderError::RenderError(format!("Failed to map buffer: {e:?}")))?;
+        mapped.await.map_err(|e| {
+            self.check_lost()
+                .err()
+                .unwrap_or_else(|| RenderError::RenderError(format!("Failed to map buffer: {e:?}")))
+        })?;
 
         debug!("Output buffer mapped successfully.");
+        if cancel.load(Ordering::Relaxed) {
+            output_buffer.unmap();
+            return Err(RenderError::Cancelled);
+        }
 
-        // Copy the mapped buffer's contents to texture_data
+        // Copy the rows of the image to texture_data, without their padding or the rest of a
+        // larger reused buffer
         {
             let view = buffer_slice.get_mapped_range();
-            texture_data.extend_from_slice(&view);
+            for row in view
+                .chunks(padded_row_bytes as usize)
+                .take(tile.height as usize)
+            {
+                texture_data.extend_from_slice(&row[..row_bytes as usize]);
+            }
         };
         debug!("Image data copied to local.");
 
         // Flushes any pending write operations and unmaps the buffer from host memory.
         output_buffer.unmap();
 
+        // The GPU is done with the buffers, the next render can write over them
+        if opts.reuse_buffers {
+            self.buffer_pool.give_back([
+                output_buffer,
+                vertex_buffer,
+                normal_buffer,
+                color_buffer,
+                index_buffer,
+                vert_uniform_buffer,
+                frag_uniform_buffer,
+            ]);
+        }
+
         Ok(texture_data)
     }
 }
+
+/// Fails with `RenderError::Cancelled` if `cancel` is set.
+pub fn check_cancelled(cancel: &AtomicBool) -> Result<(), RenderError> {
+    if cancel.load(Ordering::Relaxed) {
+        Err(RenderError::Cancelled)
+    } else {
+        Ok(())
+    }
+}
+
+/// Offset from the center of a pixel of the point sampled by the `index`th of several frames.
+///
+/// Points follow the Halton sequence in bases 2 and 3, which spreads any number of them evenly
+/// over the pixel.
+fn sample_offset(index: u8) -> Vec2 {
+    let halton = |base: u16| {
+        let (mut i, mut scale, mut value) = (u16::from(index) + 1, 1.0, 0.0);
+        while i > 0 {
+            scale /= f32::from(base);
+            value += scale * f32::from(i % base);
+            i /= base;
+        }
+        value
+    };
+    Vec2::new(halton(2), halton(3)) - 0.5
+}
+
+/// Decodes a color value encoded with the sRGB transfer function.
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.040_45 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encodes a linear color value with the sRGB transfer function.
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055f32.mul_add(value.powf(1.0 / 2.4), -0.055)
+    }
+}
+
+/// Fraction of the pixels read back from the texture that aren't fully transparent.
+#[cfg(all(feature = "image", not(target_arch = "wasm32")))]
+fn coverage(texture_data: &[u8], bit_depth: BitDepth) -> f32 {
+    let (pixels, covered) = if bit_depth == BitDepth::Eight {
+        let pixels = texture_data.chunks_exact(4);
+        (pixels.len(), pixels.filter(|pixel| pixel[3] > 0).count())
+    } else {
+        let pixels = texture_data.chunks_exact(4 * size_of::<f16>());
+        (
+            pixels.len(),
+            pixels
+                .filter(|pixel| f16::from_le_bytes([pixel[6], pixel[7]]).to_f32() > 0.0)
+                .count(),
+        )
+    };
+    if pixels == 0 {
+        return 0.0;
+    }
+    // Pixel counts are represented closely enough
+    #[allow(clippy::cast_precision_loss)]
+    {
+        covered as f32 / pixels as f32
+    }
+}
+
+/// Converts the pixels read back from the texture to the output format of `opts`.
+fn finish_image(texture_data: Vec<u8>, opts: &RenderOptions) -> Vec<u8> {
+    match opts.bit_depth {
+        BitDepth::Eight => {
+            let mut pixels = texture_data;
+            if opts.auto_levels {
+                auto_levels(&mut pixels);
+            }
+            post_process(pixels, opts)
+        }
+        BitDepth::Sixteen => {
+            // Encode the half-float values, as there is no sRGB target to do it on the GPU
+            let mut pixels = texture_data
+                .chunks_exact(2)
+                .enumerate()
+                .map(|(i, bytes)| {
+                    let value = f16::from_le_bytes([bytes[0], bytes[1]]).to_f32();
+                    let value = if i % 4 == 3 || opts.color_space == ColorSpace::Linear {
+                        value
+                    } else {
+                        linear_to_srgb(value)
+                    };
+                    // Values are clamped to the u16 range
+                    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                    {
+                        (value.clamp(0.0, 1.0) * 65535.0).round() as u16
+                    }
+                })
+                .collect::<Vec<_>>();
+            if opts.auto_levels {
+                auto_levels(&mut pixels);
+            }
+            post_process(pixels, opts)
+                .into_iter()
+                .flat_map(u16::to_ne_bytes)
+                .collect()
+        }
+        BitDepth::Float => {
+            let mut pixels = texture_data
+                .chunks_exact(2)
+                .map(|bytes| f16::from_le_bytes([bytes[0], bytes[1]]).to_f32())
+                .collect::<Vec<_>>();
+            if opts.auto_levels {
+                auto_levels_float(&mut pixels);
+            }
+            post_process_float(pixels, opts)
+                .into_iter()
+                .flat_map(f32::to_ne_bytes)
+                .collect()
+        }
+    }
+}
+
+/// Weights of the channels in the luminance of a color, from Rec. 709.
+const LUMINANCE: Vec3 = Vec3::new(0.2126, 0.7152, 0.0722);
+
+/// Range of luminance the levels of the model are stretched to by `auto_levels`.
+const LEVELS_RANGE: (f32, f32) = (0.05, 0.95);
+
+/// Lowest and highest luminance of the opaque pixels of the image, ignoring the darkest and
+/// brightest percent of them so a few specular highlights don't set the range.
+fn luminance_range(pixels: impl Iterator<Item = [f32; 4]>) -> Option<(f32, f32)> {
+    let mut luminances = pixels
+        .filter(|pixel| pixel[3] > 0.0)
+        .map(|pixel| Vec3::new(pixel[0], pixel[1], pixel[2]).dot(LUMINANCE))
+        .collect::<Vec<_>>();
+    if luminances.is_empty() {
+        return None;
+    }
+    luminances.sort_unstable_by(f32::total_cmp);
+    let last = luminances.len() - 1;
+    let (low, high) = (luminances[last / 100], luminances[last - last / 100]);
+    // Flat images have no levels to stretch
+    (high - low > 1e-3).then_some((low, high))
+}
+
+/// Maps `value` from the luminance range `low..high` to [`LEVELS_RANGE`].
+fn stretch_level(value: f32, (low, high): (f32, f32)) -> f32 {
+    let (target_low, target_high) = LEVELS_RANGE;
+    (value - low).mul_add((target_high - target_low) / (high - low), target_low)
+}
+
+/// Stretches the colors of the opaque pixels so their luminance spans [`LEVELS_RANGE`], giving
+/// thumbnails of dark and bright models a similar brightness.
+///
+/// Colors are adjusted as encoded in the image, so evenly in perceived brightness for sRGB.
+fn auto_levels<T: Channel>(pixels: &mut [T]) {
+    #[allow(clippy::cast_precision_loss)]
+    let max = T::MAX as f32;
+    #[allow(clippy::cast_precision_loss)]
+    let float = |value: T| value.into() as f32 / max;
+    let Some(range) = luminance_range(
+        pixels
+            .chunks_exact(4)
+            .map(|pixel| std::array::from_fn(|i| float(pixel[i]))),
+    ) else {
+        return;
+    };
+
+    for pixel in pixels
+        .chunks_exact_mut(4)
+        .filter(|pixel| pixel[3].into() > 0)
+    {
+        for channel in &mut pixel[..3] {
+            let value = stretch_level(float(*channel), range);
+            // Negative values are clamped to 0 by the conversion
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            {
+                *channel = T::saturating_from((value * max).round() as u32);
+            }
+        }
+    }
+    debug!("Image levels stretched from {range:?}.");
+}
+
+/// Stretches the levels of linear pixels like [`auto_levels`], without clamping bright values.
+fn auto_levels_float(pixels: &mut [f32]) {
+    let Some(range) = luminance_range(
+        pixels
+            .chunks_exact(4)
+            .map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]]),
+    ) else {
+        return;
+    };
+
+    for pixel in pixels.chunks_exact_mut(4).filter(|pixel| pixel[3] > 0.0) {
+        for channel in &mut pixel[..3] {
+            *channel = stretch_level(*channel, range).max(0.0);
+        }
+    }
+    debug!("Image levels stretched from {range:?}.");
+}
+
+/// Converts the read back RGBA pixels to the output pixel format.
+fn post_process<T: Channel>(mut texture_data: Vec<T>, opts: &RenderOptions) -> Vec<T> {
+    // Negative values are clamped to 0 by the conversion
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let channel = |value: f32| T::saturating_from(value.round() as u32);
+
+    if opts.output_format == PixelFormat::Rgb8 {
+        // Composite over the flatten color, dropping the alpha channel
+        #[allow(clippy::cast_precision_loss)]
+        let max = T::MAX as f32;
+        let background = opts.flatten_color() * max;
+        #[allow(clippy::cast_precision_loss)]
+        let float = |c: T| c.into() as f32;
+        texture_data = texture_data
+            .chunks_exact(4)
+            .flat_map(|pixel| {
+                // Matted colors are already composited
+                let alpha = if opts.matte_background.is_some() {
+                    1.0
+                } else {
+                    float(pixel[3]) / max
+                };
+                let color = Vec3::new(float(pixel[0]), float(pixel[1]), float(pixel[2]));
+                let color = color * alpha + background * (1.0 - alpha);
+                [channel(color.x), channel(color.y), channel(color.z)]
+            })
+            .collect();
+        debug!("Image data composited over background.");
+    } else if opts.premultiply_alpha && opts.matte_background.is_none() {
+        for pixel in texture_data.chunks_exact_mut(4) {
+            let alpha = pixel[3].into();
+            for channel in &mut pixel[..3] {
+                // Rounded integer division, the result always fits in the channel
+                *channel = T::saturating_from(((*channel).into() * alpha + T::MAX / 2) / T::MAX);
+            }
+        }
+        debug!("Image data premultiplied by alpha.");
+    }
+
+    texture_data
+}
+
+/// Converts the read back linear RGBA pixels to the output pixel format, without clamping them.
+fn post_process_float(mut texture_data: Vec<f32>, opts: &RenderOptions) -> Vec<f32> {
+    if opts.output_format == PixelFormat::Rgb8 {
+        let background = opts.linear_color(opts.flatten_color());
+        texture_data = texture_data
+            .chunks_exact(4)
+            .flat_map(|pixel| {
+                // Matted colors are already composited
+                let alpha = if opts.matte_background.is_some() {
+                    1.0
+                } else {
+                    pixel[3]
+                };
+                let color =
+                    Vec3::new(pixel[0], pixel[1], pixel[2]) * alpha + background * (1.0 - alpha);
+                color.to_array()
+            })
+            .collect();
+        debug!("Image data composited over background.");
+    } else if opts.premultiply_alpha && opts.matte_background.is_none() {
+        for pixel in texture_data.chunks_exact_mut(4) {
+            let alpha = pixel[3];
+            for channel in &mut pixel[..3] {
+                *channel *= alpha;
+            }
+        }
+        debug!("Image data premultiplied by alpha.");
+    }
+
+    texture_data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube() -> Mesh {
+        Mesh::load(
+            "test/data/cube.stl",
+            None,
+            StlMode::Auto,
+            &ObjectSelector::All,
+            false,
+        )
+        .expect("Error loading mesh")
+    }
+
+    #[test]
+    fn small_mesh_uses_16_bit_indices() {
+        let mesh = cube();
+
+        assert_eq!(index_format(&mesh), IndexFormat::Uint16);
+    }
+
+    #[test]
+    fn adapters_are_listed() {
+        let adapters = list_adapters();
+
+        assert!(!adapters.is_empty());
+        assert!(adapters.iter().all(|adapter| !adapter.name.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn buffers_are_reused() {
+        let mut opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+        let mesh = cube();
+        let renderer = ThumbRenderer::new(&opts)
+            .await
+            .expect("Error creating renderer");
+        let pooled = || renderer.buffer_pool.len();
+
+        let first = renderer
+            .render(&mesh, &opts)
+            .await
+            .expect("Error in render function");
+        let count = pooled();
+        assert!(count > 0);
+        let second = renderer
+            .render(&mesh, &opts)
+            .await
+            .expect("Error in render function");
+        // The second render took every buffer from the pool and gave them back
+        assert_eq!(pooled(), count);
+        assert_eq!(first, second);
+
+        opts.reuse_buffers = false;
+        renderer
+            .render(&mesh, &opts)
+            .await
+            .expect("Error in render function");
+        assert_eq!(pooled(), 0);
+    }
+
+    #[tokio::test]
+    async fn non_power_of_two_size() {
+        let opts = RenderOptions {
+            width: 100,
+            height: 60,
+            ..Default::default()
+        };
+        let mesh = cube();
+        let renderer = ThumbRenderer::new(&opts)
+            .await
+            .expect("Error creating renderer");
+
+        // Rows are padded and pooled buffers are larger than the image, neither is returned
+        let first = renderer
+            .render(&mesh, &opts)
+            .await
+            .expect("Error in render function");
+        assert_eq!(first.len(), 100 * 60 * 4);
+        let second = renderer
+            .render(&mesh, &opts)
+            .await
+            .expect("Error in render function");
+        assert_eq!(first, second);
+        // The model is in the middle of the image, the corners are background
+        assert_eq!(first[(30 * 100 + 50) * 4 + 3], u8::MAX);
+        assert_eq!(first[3], 0);
+
+        let rgb = renderer
+            .render(
+                &mesh,
+                &RenderOptions {
+                    output_format: PixelFormat::Rgb8,
+                    ..opts
+                },
+            )
+            .await
+            .expect("Error in render function");
+        assert_eq!(rgb.len(), 100 * 60 * 3);
+    }
+
+    #[tokio::test]
+    async fn cancelled_before_submission() {
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+        let mesh = cube();
+        let renderer = ThumbRenderer::new(&opts)
+            .await
+            .expect("Error creating renderer");
+
+        // Past the checks at the start of the render, up to the submission of the frame
+        let cancelled = renderer
+            .render_tile(&mesh, &opts, Tile::full(&opts), &AtomicBool::new(true))
+            .await;
+        assert!(matches!(cancelled, Err(RenderError::Cancelled)));
+        // The buffers of the cancelled frame are released rather than pooled
+        assert_eq!(renderer.buffer_pool.len(), 0);
+
+        renderer
+            .render(&mesh, &opts)
+            .await
+            .expect("Error in render function");
+    }
+
+    #[tokio::test]
+    async fn sample_counts() {
+        let mesh = cube();
+
+        for sample_count in [1, 4] {
+            // The depth of field pass also reads the depth, single-sampled either way
+            let opts = RenderOptions {
+                width: 64,
+                height: 64,
+                sample_count,
+                depth_of_field: Some(DofOptions {
+                    focal_distance: 2.0,
+                    aperture: 0.05,
+                }),
+                ..Default::default()
+            };
+            let renderer = ThumbRenderer::new(&opts)
+                .await
+                .expect("Error creating renderer");
+            renderer
+                .render(&mesh, &opts)
+                .await
+                .expect("Error in render function");
+
+            // The attachments follow the pipelines of the renderer rather than the options
+            let other = RenderOptions {
+                sample_count: 5 - sample_count,
+                ..opts
+            };
+            renderer
+                .render(&mesh, &other)
+                .await
+                .expect("Error in render function");
+        }
+    }
+
+    #[tokio::test]
+    async fn device_lost_recreates_renderer() {
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+        let mesh = cube();
+        let mut renderer = ThumbRenderer::new(&opts)
+            .await
+            .expect("Error creating renderer");
+
+        // Destroying the device loses it once the queue is polled, like a GPU reset
+        renderer.device.destroy();
+        renderer.device.poll(Maintain::Wait);
+        assert!(matches!(
+            renderer.render(&mesh, &opts).await,
+            Err(RenderError::DeviceLost(_))
+        ));
+
+        let buffer = renderer
+            .render_or_recreate(&mesh, &opts)
+            .await
+            .expect("Error rendering with the recreated renderer");
+        assert_eq!(buffer.len(), 64 * 64 * 4);
+        assert!(renderer.check_lost().is_ok());
+    }
+
+    #[tokio::test]
+    async fn software_fallback() {
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+        let adapter = request_fallback_adapter(&Instance::default())
+            .await
+            .expect("No software adapter available");
+        let mesh = cube();
+
+        let buffer = ThumbRenderer::with_adapter(&adapter, &opts)
+            .await
+            .expect("Error creating renderer")
+            .render(&mesh, &opts)
+            .await
+            .expect("Error in render function");
+
+        assert!(buffer.chunks_exact(4).any(|pixel| pixel[3] > 0));
+    }
+
+    #[tokio::test]
+    async fn dimensions_too_large() {
+        let opts = RenderOptions {
+            width: 8192,
+            height: 8192,
+            tiled: false,
+            ..Default::default()
+        };
+        let mesh = cube();
+
+        // Devices are requested with the downlevel limits, which allow 2048 pixels
+        let error = ThumbRenderer::new(&opts)
+            .await
+            .expect("Error creating renderer")
+            .render(&mesh, &opts)
+            .await
+            .expect_err("Rendering past the texture limit should fail");
+        assert!(matches!(
+            error,
+            RenderError::DimensionsTooLarge {
+                requested: 8192,
+                max: 2048
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn tiled_rendering() {
+        let opts = RenderOptions {
+            width: 4096,
+            height: 4096,
+            ..Default::default()
+        };
+        let mesh = cube();
+
+        // Devices are requested with the downlevel limits, so this is rendered in 2 x 2 tiles
+        let buffer = ThumbRenderer::new(&opts)
+            .await
+            .expect("Error creating renderer")
+            .render(&mesh, &opts)
+            .await
+            .expect("Error in render function");
+        assert_eq!(buffer.len(), 4096 * 4096 * 4);
+
+        // Pixels differing between neighbouring columns or rows, which should be no more common
+        // across the tile boundaries than next to them
+        let pixel = |x: usize, y: usize| &buffer[(y * 4096 + x) * 4..][..4];
+        let differs = |a: &[u8], b: &[u8]| a.iter().zip(b).any(|(a, b)| a.abs_diff(*b) > 8);
+        let column_changes = |x: usize| {
+            (0..4096)
+                .filter(|&y| differs(pixel(x, y), pixel(x + 1, y)))
+                .count()
+        };
+        let row_changes = |y: usize| {
+            (0..4096)
+                .filter(|&x| differs(pixel(x, y), pixel(x, y + 1)))
+                .count()
+        };
+        assert!(
+            column_changes(2047) <= column_changes(2046) + column_changes(2048) + 4,
+            "Seam between the left and right tiles"
+        );
+        assert!(
+            row_changes(2047) <= row_changes(2046) + row_changes(2048) + 4,
+            "Seam between the top and bottom tiles"
+        );
+
+        // The model is centered, so it covers the middle of the image across all four tiles
+        assert!([(2047, 2047), (2048, 2047), (2047, 2048), (2048, 2048)]
+            .iter()
+            .all(|&(x, y)| pixel(x, y)[3] == u8::MAX));
+    }
+
+    #[tokio::test]
+    async fn ambient_occlusion_darkens_creases() {
+        let mesh = Mesh::load(
+            "test/data/corner.obj",
+            None,
+            StlMode::Auto,
+            &ObjectSelector::All,
+            false,
+        )
+        .expect("Error loading mesh");
+        let adapter = request_fallback_adapter(&Instance::default())
+            .await
+            .expect("No software adapter available");
+        let render = |ambient_occlusion| {
+            let opts = RenderOptions {
+                width: 64,
+                height: 64,
+                ambient_occlusion,
+                ..Default::default()
+            };
+            let adapter = &adapter;
+            let mesh = &mesh;
+            async move {
+                ThumbRenderer::with_adapter(adapter, &opts)
+                    .await
+                    .expect("Error creating renderer")
+                    .render(mesh, &opts)
+                    .await
+                    .expect("Error in render function")
+            }
+        };
+        let plain = render(false).await;
+        let occluded = render(true).await;
+
+        // Brightness of the pixel showing a point of the floor
+        let opts = RenderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+        let view_projection = opts.projection() * opts.view_matrix();
+        let brightness = |buffer: &[u8], point: Vec3| {
+            let ndc = view_projection.project_point3(point);
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let (x, y) = (
+                ((ndc.x + 1.0) * 32.0) as usize,
+                ((1.0 - ndc.y) * 32.0) as usize,
+            );
+            let pixel = &buffer[(y * 64 + x) * 4..][..3];
+            pixel.iter().map(|&c| u32::from(c)).sum::<u32>()
+        };
+
+        // Next to the walls the floor is darkened, far from them it isn't
+        let crease = Vec3::new(-0.95, 0.0, -1.0);
+        let flat = Vec3::new(0.5, -0.5, -1.0);
+        assert!(brightness(&occluded, crease) * 10 < brightness(&plain, crease) * 9);
+        assert!(brightness(&occluded, flat) * 20 > brightness(&plain, flat) * 19);
+    }
+}
@@ -1,8 +1,18 @@
 use bytemuck::{Pod, Zeroable};
-use glam::Mat4;
+use glam::{Mat4, Vec2, Vec3, Vec4};
 use wgpu::include_wgsl;
 
+use crate::{mesh::Edge, render::DirectionalLight};
+
 pub const SHADER: wgpu::ShaderModuleDescriptor<'_> = include_wgsl!("shaders/model.wgsl");
+pub const POINT_SHADER: wgpu::ShaderModuleDescriptor<'_> = include_wgsl!("shaders/points.wgsl");
+pub const BACKGROUND_SHADER: wgpu::ShaderModuleDescriptor<'_> =
+    include_wgsl!("shaders/background.wgsl");
+pub const LINE_SHADER: wgpu::ShaderModuleDescriptor<'_> = include_wgsl!("shaders/lines.wgsl");
+pub const AO_SHADER: wgpu::ShaderModuleDescriptor<'_> = include_wgsl!("shaders/ao.wgsl");
+pub const FXAA_SHADER: wgpu::ShaderModuleDescriptor<'_> = include_wgsl!("shaders/fxaa.wgsl");
+pub const DOF_SHADER: wgpu::ShaderModuleDescriptor<'_> = include_wgsl!("shaders/dof.wgsl");
+pub const EDGE_SHADER: wgpu::ShaderModuleDescriptor<'_> = include_wgsl!("shaders/edges.wgsl");
 
 // Define the uniform data structure
 #[repr(C)]
@@ -10,38 +20,163 @@ pub const SHADER: wgpu::ShaderModuleDescriptor<'_> = include_wgsl!("shaders/mode
 pub struct VertUniformBlock {
     pub(crate) perspective: Mat4,
     pub(crate) modelview: Mat4,
+    /// Half extent of a point in clip space, only used by the point shader
+    pub(crate) point_size: Vec2,
+    pub(crate) _padding: Vec2,
 }
 
+/// Maximum number of lights supported by the shader
+pub const MAX_LIGHTS: usize = 4;
+
 #[repr(C, align(16))]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
-pub struct FragUniformBlock {
-    pub(crate) light_direction: [f32; 3],
+pub struct LightUniform {
+    pub(crate) direction: [f32; 3],
     _padding1: [u8; 4],
-    pub(crate) ambient_color: [f32; 3],
+    pub(crate) color: [f32; 3],
     _padding2: [u8; 4],
+}
+
+#[repr(C, align(16))]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct FragUniformBlock {
+    pub(crate) lights: [LightUniform; MAX_LIGHTS],
+    pub(crate) ambient_color: [f32; 3],
+    pub(crate) light_count: u32,
     pub(crate) diffuse_color: [f32; 3],
-    _padding3: [u8; 4],
+    pub(crate) shininess: f32,
     pub(crate) specular_color: [f32; 3],
-    _padding4: [u8; 4],
+    pub(crate) specular_strength: f32,
+    /// Non-zero to light back faces as if they faced the camera
+    pub(crate) two_sided: u32,
+    /// Number of light levels of toon shading, 0 for smooth lighting
+    pub(crate) toon_bands: u32,
+    _padding: [u8; 8],
+    /// Color of the rim light, black when there is none
+    pub(crate) rim_color: [f32; 3],
+    /// Falloff exponent of the rim light, higher values give thinner rims
+    pub(crate) rim_power: f32,
+    /// Linear color back faces are drawn with when `highlight_backfaces` is set
+    pub(crate) backface_color: [f32; 3],
+    /// Non-zero to draw back faces with `backface_color` instead of lighting them
+    pub(crate) highlight_backfaces: u32,
 }
 
 impl FragUniformBlock {
+    /// Only the first [`MAX_LIGHTS`] lights are used.
     #[inline]
-    pub const fn new(
-        light_direction: [f32; 3],
+    pub fn new(
+        lights: &[DirectionalLight],
         ambient_color: [f32; 3],
         diffuse_color: [f32; 3],
         specular_color: [f32; 3],
+        shininess: f32,
+        specular_strength: f32,
+        two_sided: bool,
     ) -> Self {
-        Self {
-            light_direction,
+        let mut block = Self {
+            lights: [LightUniform::zeroed(); MAX_LIGHTS],
             ambient_color,
+            light_count: 0,
             diffuse_color,
+            shininess,
             specular_color,
-            _padding1: [0; 4],
-            _padding2: [0; 4],
-            _padding3: [0; 4],
-            _padding4: [0; 4],
+            specular_strength,
+            two_sided: two_sided.into(),
+            toon_bands: 0,
+            _padding: [0; 8],
+            rim_color: [0.0; 3],
+            rim_power: 1.0,
+            backface_color: [0.0; 3],
+            highlight_backfaces: 0,
+        };
+
+        for (uniform, light) in block.lights.iter_mut().zip(lights) {
+            uniform.direction = light.direction.into();
+            uniform.color = light.color.into();
+            block.light_count += 1;
+        }
+
+        block
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct BackgroundUniformBlock {
+    pub(crate) top_color: Vec4,
+    pub(crate) bottom_color: Vec4,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct AoUniformBlock {
+    pub(crate) projection: Mat4,
+    pub(crate) inverse_projection: Mat4,
+    pub(crate) radius: f32,
+    pub(crate) strength: f32,
+    pub(crate) _padding: Vec2,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct DofUniformBlock {
+    pub(crate) inverse_projection: Mat4,
+    pub(crate) direction: Vec2,
+    pub(crate) focal_distance: f32,
+    pub(crate) blur_scale: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct LineUniformBlock {
+    pub(crate) perspective: Mat4,
+    pub(crate) modelview: Mat4,
+    /// Size of a pixel in clip space
+    pub(crate) pixel_size: Vec2,
+    /// Thickness of the lines in pixels
+    pub(crate) width: f32,
+    pub(crate) _padding: f32,
+}
+
+/// A line as read by the line shader, one per instance.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct LineInstance {
+    pub(crate) start: Vec3,
+    pub(crate) end: Vec3,
+    pub(crate) color: Vec3,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct EdgeUniformBlock {
+    pub(crate) perspective: Mat4,
+    pub(crate) modelview: Mat4,
+    pub(crate) color: Vec4,
+    /// Half the thickness of the edges in clip space
+    pub(crate) half_width: Vec2,
+    pub(crate) _padding: Vec2,
+}
+
+/// An edge as read by the edge shader, one per instance.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct EdgeInstance {
+    pub(crate) start: Vec3,
+    pub(crate) end: Vec3,
+    pub(crate) normals: [Vec3; 2],
+    /// 1 for sharp edges, 0 for edges only drawn on the silhouette
+    pub(crate) sharp: f32,
+}
+
+impl From<&Edge> for EdgeInstance {
+    fn from(edge: &Edge) -> Self {
+        Self {
+            start: edge.start,
+            end: edge.end,
+            normals: edge.normals,
+            sharp: if edge.sharp { 1.0 } else { 0.0 },
         }
     }
 }
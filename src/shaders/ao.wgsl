@@ -0,0 +1,88 @@
+struct AoBindings {
+    projection: mat4x4<f32>,
+    inverse_projection: mat4x4<f32>,
+    radius: f32, // Radius of the sampled hemisphere in view space units
+    strength: f32, // How much fully occluded pixels are darkened, from 0 to 1
+}
+
+@group(0) @binding(0) var<uniform> bindings: AoBindings;
+// Depth of the model, bound as a float texture as GLSL can't load from depth textures
+@group(0) @binding(1) var depth_texture: texture_2d<f32>;
+
+const SAMPLES: u32 = 32u;
+const GOLDEN_ANGLE: f32 = 2.39996323;
+
+// Draws a single triangle covering the whole screen
+@vertex
+fn vert_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+}
+
+// Reconstructs the view space position of the surface drawn at a pixel
+fn view_position(coords: vec2<i32>) -> vec3<f32> {
+    let size = vec2<f32>(textureDimensions(depth_texture));
+    let depth = textureLoad(depth_texture, coords, 0).x;
+    let uv = (vec2<f32>(coords) + 0.5) / size;
+    let position = bindings.inverse_projection * vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    return position.xyz / position.w;
+}
+
+// Outputs the fraction of ambient light reaching each pixel, which the blend state multiplies
+// with the rendered color
+@fragment
+fn frag_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let coords = vec2<i32>(position.xy);
+    let last = vec2<i32>(textureDimensions(depth_texture)) - 1;
+
+    // Nothing was drawn over the background
+    if textureLoad(depth_texture, coords, 0).x >= 1.0 {
+        return vec4<f32>(1.0);
+    }
+
+    // Normal from the neighbouring pixels, using the side with the smallest depth difference so
+    // it doesn't bend around the edges of the model
+    let center = view_position(coords);
+    let right = view_position(min(coords + vec2<i32>(1, 0), last)) - center;
+    let left = center - view_position(max(coords - vec2<i32>(1, 0), vec2<i32>(0)));
+    let down = view_position(min(coords + vec2<i32>(0, 1), last)) - center;
+    let up = center - view_position(max(coords - vec2<i32>(0, 1), vec2<i32>(0)));
+    let dx = select(right, left, abs(left.z) < abs(right.z));
+    let dy = select(down, up, abs(up.z) < abs(down.z));
+    var normal = normalize(cross(dx, dy));
+    if normal.z < 0.0 {
+        normal = -normal;
+    }
+
+    let helper = select(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 1.0, 0.0), abs(normal.x) > 0.9);
+    let tangent = normalize(cross(helper, normal));
+    let bitangent = cross(normal, tangent);
+
+    // Interleaved gradient noise rotating the samples of each pixel, which trades banding for
+    // fine grain
+    let rotation = fract(52.9829189 * fract(dot(position.xy, vec2<f32>(0.06711056, 0.00583715)))) * 6.28318531;
+
+    var occlusion = 0.0;
+    for (var i = 0u; i < SAMPLES; i++) {
+        // Spiral over the hemisphere, with the samples further from the center reaching further
+        let t = (f32(i) + 0.5) / f32(SAMPLES);
+        let angle = f32(i) * GOLDEN_ANGLE + rotation;
+        let elevation = 1.0 - t;
+        let spread = sqrt(1.0 - elevation * elevation);
+        let direction = vec3<f32>(cos(angle) * spread, sin(angle) * spread, elevation) * mix(0.2, 1.0, t);
+        let sample = center + (tangent * direction.x + bitangent * direction.y + normal * direction.z) * bindings.radius;
+
+        let clip = bindings.projection * vec4<f32>(sample, 1.0);
+        let ndc = clip.xy / clip.w;
+        let sample_coords = vec2<i32>(vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5) * vec2<f32>(last + 1));
+        let scene = view_position(clamp(sample_coords, vec2<i32>(0), last)).z;
+
+        // Surfaces far in front of the sample don't darken the edges of the model behind them
+        let in_range = smoothstep(0.0, 1.0, bindings.radius / abs(center.z - scene));
+        if scene >= sample.z + bindings.radius * 0.05 {
+            occlusion += in_range;
+        }
+    }
+
+    return vec4<f32>(vec3<f32>(1.0 - bindings.strength * occlusion / f32(SAMPLES)), 1.0);
+}
@@ -0,0 +1,31 @@
+struct BackgroundBindings {
+    top_color: vec4<f32>,
+    bottom_color: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> bindings: BackgroundBindings;
+@group(0) @binding(1) var background_texture: texture_2d<f32>;
+@group(0) @binding(2) var background_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>, // Clip-space position
+    @location(0) uv: vec2<f32>, // Texture coordinates, (0, 0) is the top left corner
+}
+
+// Draws a single triangle covering the whole screen
+@vertex
+fn vert_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var output: VertexOutput;
+
+    output.uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    output.position = vec4<f32>(output.uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+
+    return output;
+}
+
+// Vertical gradient multiplied by the background texture
+@fragment
+fn frag_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let gradient = mix(bindings.top_color, bindings.bottom_color, in.uv.y);
+    return gradient * textureSample(background_texture, background_sampler, in.uv);
+}
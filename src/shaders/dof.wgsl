@@ -0,0 +1,72 @@
+struct DofBindings {
+    inverse_projection: mat4x4<f32>,
+    direction: vec2<f32>, // One pixel along the axis blurred by this pass
+    focal_distance: f32, // Distance from the camera that is in focus
+    blur_scale: f32, // Blur radius in pixels of points infinitely far behind the focal plane
+}
+
+@group(0) @binding(0) var<uniform> bindings: DofBindings;
+@group(0) @binding(1) var color_texture: texture_2d<f32>;
+// Depth of the model, bound as a float texture as GLSL can't load from depth textures
+@group(0) @binding(2) var depth_texture: texture_2d<f32>;
+
+// Largest blur radius in pixels, which bounds the cost of each pixel
+const MAX_RADIUS: i32 = 16;
+
+// Draws a single triangle covering the whole screen
+@vertex
+fn vert_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+}
+
+// Distance from the camera to the surface drawn at a pixel, or to the far plane for the background
+fn view_distance(coords: vec2<i32>) -> f32 {
+    let size = vec2<f32>(textureDimensions(depth_texture));
+    let depth = textureLoad(depth_texture, coords, 0).x;
+    let uv = (vec2<f32>(coords) + 0.5) / size;
+    let position = bindings.inverse_projection * vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    return -position.z / position.w;
+}
+
+// Radius in pixels of the circle of confusion of a point, growing with its distance from the
+// focal plane like through a thin lens
+fn blur_radius(distance: f32) -> f32 {
+    return min(bindings.blur_scale * abs(distance - bindings.focal_distance) / distance, f32(MAX_RADIUS));
+}
+
+// Blurs the image along one axis, each pixel gathering the neighbours whose circle of confusion
+// reaches it
+@fragment
+fn frag_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let coords = vec2<i32>(position.xy);
+    let last = vec2<i32>(textureDimensions(color_texture)) - 1;
+
+    let distance = view_distance(coords);
+    let radius = blur_radius(distance);
+
+    // Colors are weighted by their alpha, so the transparent background doesn't darken the
+    // blurred edges of the model
+    var sum = vec4<f32>(0.0);
+    var total = 0.0;
+    for (var i = -MAX_RADIUS; i <= MAX_RADIUS; i++) {
+        let offset = abs(f32(i));
+        let sample_coords = clamp(coords + i * vec2<i32>(bindings.direction), vec2<i32>(0), last);
+        let sample_distance = view_distance(sample_coords);
+        let sample_radius = blur_radius(sample_distance);
+        // Blurry surfaces behind the pixel don't spread over it, so sharp edges in front of a
+        // blurry background stay sharp
+        if offset > sample_radius + 0.5 || (sample_distance > distance && offset > radius + 0.5) {
+            continue;
+        }
+        let color = textureLoad(color_texture, sample_coords, 0);
+        let weight = 1.0 - offset / (sample_radius + 1.0);
+        sum += vec4<f32>(color.rgb * color.a, color.a) * weight;
+        total += weight;
+    }
+
+    if sum.a <= 0.0 {
+        return vec4<f32>(0.0);
+    }
+    return vec4<f32>(sum.rgb / sum.a, sum.a / total);
+}
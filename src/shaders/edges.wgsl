@@ -0,0 +1,71 @@
+struct EdgeBindings {
+    perspective: mat4x4<f32>,
+    modelview: mat4x4<f32>,
+    color: vec4<f32>,
+    half_width: vec2<f32>, // Half the thickness of the edges in clip space
+}
+
+@group(0) @binding(0) var<uniform> bindings: EdgeBindings;
+
+struct EdgeInput {
+    @location(0) start: vec3<f32>,
+    @location(1) end: vec3<f32>,
+    @location(2) normal_a: vec3<f32>, // Normals of the triangles on either side of the edge
+    @location(3) normal_b: vec3<f32>,
+    @location(4) sharp: f32, // 1 for creases and boundaries, which are drawn from any view
+}
+
+// Whether a triangle with this normal faces the camera, seen from a point in view space
+fn faces_camera(normal: vec3<f32>, position: vec3<f32>) -> bool {
+    let normal_matrix = mat3x3<f32>(bindings.modelview[0].xyz, bindings.modelview[1].xyz, bindings.modelview[2].xyz);
+    // Orthographic projections look along -Z everywhere
+    var to_camera = -position;
+    if bindings.perspective[3][3] == 1.0 {
+        to_camera = vec3<f32>(0.0, 0.0, 1.0);
+    }
+    return dot(normal_matrix * normal, to_camera) > 0.0;
+}
+
+// Draws each edge as a quad of two triangles, `half_width` on either side of the line on screen
+@vertex
+fn vert_main(@builtin(vertex_index) index: u32, input: EdgeInput) -> @builtin(position) vec4<f32> {
+    let start = bindings.modelview * vec4<f32>(input.start, 1.0);
+    let end = bindings.modelview * vec4<f32>(input.end, 1.0);
+
+    // Smooth edges are only drawn on the silhouette, between a front and a back facing triangle
+    let middle = (start.xyz / start.w + end.xyz / end.w) * 0.5;
+    if input.sharp == 0.0 && faces_camera(input.normal_a, middle) == faces_camera(input.normal_b, middle) {
+        return vec4<f32>(0.0);
+    }
+
+    // Position along the edge and side of the line of each corner
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let corner = corners[index];
+
+    let clip_start = bindings.perspective * start;
+    let clip_end = bindings.perspective * end;
+    // Direction of the line on screen, in pixels so the quad is as wide in every direction
+    let pixel_direction = (clip_end.xy / clip_end.w - clip_start.xy / clip_start.w) / bindings.half_width;
+    var side = vec2<f32>(0.0, 0.0);
+    if length(pixel_direction) > 0.0 {
+        let direction = normalize(pixel_direction);
+        side = vec2<f32>(-direction.y, direction.x) * bindings.half_width;
+    }
+
+    let position = mix(clip_start, clip_end, corner.x);
+    // Offset after the projection so edges keep the same thickness at any depth
+    return position + vec4<f32>(side * corner.y * position.w, 0.0, 0.0);
+}
+
+// Edges are not lit
+@fragment
+fn frag_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(bindings.color.rgb, 1.0);
+}
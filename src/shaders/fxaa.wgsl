@@ -0,0 +1,59 @@
+@group(0) @binding(0) var color_texture: texture_2d<f32>;
+@group(0) @binding(1) var color_sampler: sampler;
+
+// Smallest blur applied along an edge, so nearly straight edges are still smoothed
+const REDUCE_MIN: f32 = 0.0078125;
+const REDUCE_MUL: f32 = 0.125;
+// Longest distance in pixels searched along an edge
+const SPAN_MAX: f32 = 8.0;
+
+// Draws a single triangle covering the whole screen
+@vertex
+fn vert_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+}
+
+// Perceived brightness, edges are found where it changes
+fn luma(color: vec4<f32>) -> f32 {
+    return sqrt(dot(color.rgb, vec3<f32>(0.299, 0.587, 0.114)));
+}
+
+fn sample(uv: vec2<f32>) -> vec4<f32> {
+    return textureSampleLevel(color_texture, color_sampler, uv, 0.0);
+}
+
+// Blends each pixel with its neighbours along the edge it lies on, if any
+@fragment
+fn frag_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(color_texture));
+    let uv = position.xy * texel;
+
+    let center = sample(uv);
+    let luma_m = luma(center);
+    let luma_nw = luma(sample(uv + vec2<f32>(-1.0, -1.0) * texel));
+    let luma_ne = luma(sample(uv + vec2<f32>(1.0, -1.0) * texel));
+    let luma_sw = luma(sample(uv + vec2<f32>(-1.0, 1.0) * texel));
+    let luma_se = luma(sample(uv + vec2<f32>(1.0, 1.0) * texel));
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // The edge runs perpendicular to the direction the brightness changes in
+    let direction = vec2<f32>(
+        (luma_sw + luma_se) - (luma_nw + luma_ne),
+        (luma_nw + luma_sw) - (luma_ne + luma_se),
+    );
+    let reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    let scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    let step = clamp(direction * scale, vec2<f32>(-SPAN_MAX), vec2<f32>(SPAN_MAX)) * texel;
+
+    let near = 0.5 * (sample(uv - step / 6.0) + sample(uv + step / 6.0));
+    let far = 0.5 * near + 0.25 * (sample(uv - step * 0.5) + sample(uv + step * 0.5));
+
+    // The wider blend crossed another edge, fall back to the narrower one
+    let luma_far = luma(far);
+    if luma_far < luma_min || luma_far > luma_max {
+        return near;
+    }
+    return far;
+}
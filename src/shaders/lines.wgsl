@@ -0,0 +1,65 @@
+struct LineBindings {
+    perspective: mat4x4<f32>,
+    modelview: mat4x4<f32>,
+    pixel_size: vec2<f32>, // Size of a pixel in clip space
+    width: f32, // Thickness of the lines in pixels
+}
+
+@group(0) @binding(0) var<uniform> bindings: LineBindings;
+
+struct LineInput {
+    @location(0) start: vec3<f32>,
+    @location(1) end: vec3<f32>,
+    @location(2) color: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>, // Clip-space position
+    @location(0) v_color: vec3<f32>, // Line color
+    @location(1) distance: f32, // Distance from the middle of the line in pixels
+}
+
+// Draws each line as a quad of two triangles, half a pixel wider than the line on either side
+// so its edges can be anti-aliased, and half the width longer at either end so lines meeting at
+// a corner join up
+@vertex
+fn vert_main(@builtin(vertex_index) index: u32, input: LineInput) -> VertexOutput {
+    // Position along the line and side of the line of each corner
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let corner = corners[index];
+
+    let clip_start = bindings.perspective * bindings.modelview * vec4<f32>(input.start, 1.0);
+    let clip_end = bindings.perspective * bindings.modelview * vec4<f32>(input.end, 1.0);
+    // Direction of the line on screen, in pixels so the quad is as wide in every direction
+    let pixel_direction = (clip_end.xy / clip_end.w - clip_start.xy / clip_start.w) / bindings.pixel_size;
+    var direction = vec2<f32>(1.0, 0.0);
+    if length(pixel_direction) > 0.0 {
+        direction = normalize(pixel_direction);
+    }
+    let side = vec2<f32>(-direction.y, direction.x);
+
+    let half_extent = bindings.width * 0.5 + 0.5;
+    let offset = side * corner.y * half_extent + direction * (corner.x * 2.0 - 1.0) * bindings.width * 0.5;
+
+    var output: VertexOutput;
+    output.position = mix(clip_start, clip_end, corner.x);
+    // Offset after the projection so lines keep the same thickness at any depth
+    output.position += vec4<f32>(offset * bindings.pixel_size * output.position.w, 0.0, 0.0);
+    output.v_color = input.color;
+    output.distance = corner.y * half_extent;
+    return output;
+}
+
+// Lines are not lit, their coverage fades out over the outermost pixel
+@fragment
+fn frag_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coverage = clamp(bindings.width * 0.5 + 0.5 - abs(in.distance), 0.0, 1.0);
+    return vec4<f32>(in.v_color, coverage);
+}
@@ -8,12 +8,14 @@ struct VertBindings {
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) position: vec4<f32>, // Clip-space position
     @location(0) v_normal: vec3<f32>, // Transformed normal
     @location(1) v_position: vec3<f32>, // World-space position
+    @location(2) v_color: vec3<f32>, // Vertex color
 }
 
 @vertex
@@ -28,46 +30,102 @@ fn vert_main(input: VertexInput) -> VertexOutput {
     let normal_matrix = mat3x3<f32>(v_bindings.modelview[0].xyz, v_bindings.modelview[1].xyz, v_bindings.modelview[2].xyz);
     output.v_normal = normalize(normal_matrix * input.normal);
 
+    output.v_color = input.color;
+
     // Calculate the final clip-space position
     output.position = v_bindings.perspective * world_position;
 
     return output;
 }
 
-struct FragBindings {
+const MAX_LIGHTS: u32 = 4u;
+
+struct Light {
     /* @offset(0) */
-    light_direction: vec3<f32>,
+    direction: vec3<f32>,
     /* @offset(16) */
+    color: vec3<f32>,
+}
+
+struct FragBindings {
+    /* @offset(0) */
+    lights: array<Light, MAX_LIGHTS>,
+    /* @offset(128) */
     ambient_color: vec3<f32>,
-    /* @offset(32) */
+    /* @offset(140) */
+    light_count: u32,
+    /* @offset(144) */
     diffuse_color: vec3<f32>,
-    /* @offset(48) */
+    /* @offset(156) */
+    shininess: f32,
+    /* @offset(160) */
     specular_color: vec3<f32>,
+    /* @offset(172) */
+    specular_strength: f32,
+    /* @offset(176) */
+    two_sided: u32,
+    /* @offset(180) */
+    toon_bands: u32,
+    /* @offset(192) */
+    rim_color: vec3<f32>,
+    /* @offset(204) */
+    rim_power: f32,
+    /* @offset(208) */
+    backface_color: vec3<f32>,
+    /* @offset(220) */
+    highlight_backfaces: u32,
 }
 
 @group(0) @binding(1) var<uniform> f_bindings: FragBindings;
 
 // Fragment shader main function
 @fragment
-fn frag_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    // Normalize the light direction vectors
-    let light_direction = normalize(f_bindings.light_direction);
-
-    // Diffuse lighting (Lambertian reflection)
-    let diffuse = max(dot(in.v_normal, light_direction), 0.0);
+fn frag_main(in: VertexOutput, @builtin(front_facing) front_facing: bool) -> @location(0) vec4<f32> {
+    // Back faces are flagged with a flat color, they show where the winding is inverted
+    if f_bindings.highlight_backfaces != 0u && !front_facing {
+        return vec4<f32>(f_bindings.backface_color, 1.0);
+    }
 
     // Camera direction (assuming camera is at the origin)
     let camera_dir = normalize(-in.v_position);
 
-    // Half-vector between the light and the camera directions
-    let half_direction = normalize(light_direction + camera_dir);
+    // Turn the normals of faces seen from behind towards the camera
+    var normal = in.v_normal;
+    if f_bindings.two_sided != 0u {
+        normal = faceForward(normal, -camera_dir, normal);
+    }
+
+    var color = f_bindings.ambient_color * in.v_color;
+    for (var i = 0u; i < min(f_bindings.light_count, MAX_LIGHTS); i++) {
+        let light = f_bindings.lights[i];
+
+        // Normalize the light direction vectors
+        let light_direction = normalize(light.direction);
+
+        // Diffuse lighting (Lambertian reflection)
+        var diffuse = max(dot(normal, light_direction), 0.0);
+
+        // Half-vector between the light and the camera directions
+        let half_direction = normalize(light_direction + camera_dir);
+
+        // Specular reflection (Blinn-Phong model)
+        var specular = f_bindings.specular_strength * pow(max(dot(half_direction, normal), 0.0), f_bindings.shininess);
+
+        // Toon shading snaps the light to evenly spaced levels, from unlit to fully lit, and
+        // turns the highlight into a hard edged spot
+        if f_bindings.toon_bands > 1u {
+            let steps = f32(f_bindings.toon_bands - 1u);
+            diffuse = min(floor(diffuse * f32(f_bindings.toon_bands)), steps) / steps;
+            specular = f_bindings.specular_strength * step(0.5, specular / max(f_bindings.specular_strength, 1e-6));
+        }
 
-    // Specular reflection (Blinn-Phong model)
-    let shininess = 128.0 * 32.0; // Adjust shininess for desired specular highlight
-    let specular = pow(max(dot(half_direction, in.v_normal), 0.0), shininess);
+        // Combine diffuse and specular lighting
+        color += light.color * (diffuse * f_bindings.diffuse_color * in.v_color + specular * f_bindings.specular_color);
+    }
 
-    // Combine ambient, diffuse, and specular lighting
-    let color = f_bindings.ambient_color + diffuse * f_bindings.diffuse_color + specular * f_bindings.specular_color;
+    // Rim light, brightest where the surface turns away from the camera along the silhouette
+    let rim = pow(max(1.0 - dot(normal, camera_dir), 1e-6), f_bindings.rim_power);
+    color += f_bindings.rim_color * rim;
 
     // Apply gamma correction
     let gamma = 0.5; // TODO: Metal requires this correction, test other backends
@@ -0,0 +1,71 @@
+struct VertBindings {
+    perspective: mat4x4<f32>,
+    modelview: mat4x4<f32>,
+    point_size: vec2<f32>,
+}
+
+@group(0) @binding(0) var<uniform> v_bindings: VertBindings;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>, // Clip-space position
+    @location(0) v_color: vec3<f32>, // Vertex color
+}
+
+@vertex
+fn vert_main(@builtin(vertex_index) index: u32, input: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+
+    // Two triangles making a square around the point
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+
+    // Offset the corners after the projection so points keep the same size at any depth
+    let position = v_bindings.perspective * v_bindings.modelview * vec4<f32>(input.position, 1.0);
+    output.position = position + vec4<f32>(corners[index] * v_bindings.point_size * position.w, 0.0, 0.0);
+    output.v_color = input.color;
+
+    return output;
+}
+
+const MAX_LIGHTS: u32 = 4u;
+
+struct Light {
+    direction: vec3<f32>,
+    color: vec3<f32>,
+}
+
+// Same layout as the model shader, points have no normal so only the diffuse color is used
+struct FragBindings {
+    /* @offset(0) */
+    lights: array<Light, MAX_LIGHTS>,
+    /* @offset(128) */
+    ambient_color: vec3<f32>,
+    /* @offset(140) */
+    light_count: u32,
+    /* @offset(144) */
+    diffuse_color: vec3<f32>,
+}
+
+@group(0) @binding(1) var<uniform> f_bindings: FragBindings;
+
+@fragment
+fn frag_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = f_bindings.diffuse_color * in.v_color;
+
+    // Apply gamma correction
+    let gamma = 0.5; // TODO: Metal requires this correction, test other backends
+    let corrected_color = pow(color, vec3<f32>(1.0 / gamma));
+
+    return vec4<f32>(clamp(corrected_color, vec3<f32>(0.0), vec3<f32>(1.0)), 1.0);
+}
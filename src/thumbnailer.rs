@@ -0,0 +1,149 @@
+//! Thumbnails following the freedesktop.org thumbnail managing standard.
+//! <https://specifications.freedesktop.org/thumbnail-spec/latest/>
+
+use std::{
+    env,
+    error::Error,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use crate::{load_mesh, render_mesh, Background, BitDepth, PixelFormat, RenderOptions};
+
+/// Sizes of the thumbnail cache, each stored in the directory of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThumbnailSize {
+    /// 128 x 128 pixels.
+    #[default]
+    Normal,
+    /// 256 x 256 pixels.
+    Large,
+}
+
+impl ThumbnailSize {
+    /// Width and height of the thumbnails in pixels.
+    #[must_use]
+    pub const fn pixels(self) -> u16 {
+        match self {
+            Self::Normal => 128,
+            Self::Large => 256,
+        }
+    }
+
+    /// Name of the cache directory holding thumbnails of this size.
+    #[must_use]
+    pub const fn dir_name(self) -> &'static str {
+        match self {
+            Self::Normal => "normal",
+            Self::Large => "large",
+        }
+    }
+}
+
+/// Path of the cached thumbnail for `uri`, named after the MD5 hash of the URI in
+/// `$XDG_CACHE_HOME/thumbnails`, or `~/.cache/thumbnails` if it isn't set.
+///
+/// Returns `None` if neither `XDG_CACHE_HOME` nor `HOME` is set.
+#[must_use]
+pub fn thumbnail_path(uri: &str, size: ThumbnailSize) -> Option<PathBuf> {
+    // Relative paths are invalid and must be ignored according to the base directory spec
+    let cache_dir = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+    Some(
+        cache_dir
+            .join("thumbnails")
+            .join(size.dir_name())
+            .join(format!("{:x}.png", md5::compute(uri))),
+    )
+}
+
+/// Renders the model at `uri` to a square PNG thumbnail with a transparent background.
+///
+/// The PNG holds the `Thumb::URI` text chunk, and `Thumb::MTime` and `Thumb::Size` for
+/// `file://` URIs, which thumbnail managers use to tell if it is out of date. The size and
+/// output format options of `opts` are ignored.
+///
+/// # Errors
+///
+/// This function will return an error if the model cannot be loaded, or if the rendering or
+/// encoding fails.
+pub async fn thumbnail_for_uri(
+    uri: &str,
+    size: ThumbnailSize,
+    opts: &RenderOptions,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let opts = RenderOptions {
+        width: size.pixels(),
+        height: size.pixels(),
+        background: Background::default(),
+        matte_background: None,
+        output_format: PixelFormat::Rgba8,
+        bit_depth: BitDepth::Eight,
+        ..opts.clone()
+    };
+    let mesh = load_mesh(Path::new(uri), &opts).await?;
+    let image = render_mesh(&mesh, &opts).await?;
+
+    let mut buff = Vec::new();
+    let mut encoder = png::Encoder::new(&mut buff, opts.width.into(), opts.height.into());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.add_text_chunk("Thumb::URI".to_owned(), uri.to_owned())?;
+    let metadata = url::Url::parse(uri)
+        .ok()
+        .filter(|url| url.scheme() == "file")
+        .and_then(|url| url.to_file_path().ok())
+        .and_then(|path| fs::metadata(path).ok());
+    if let Some(metadata) = metadata {
+        let mtime = metadata.modified()?.duration_since(UNIX_EPOCH)?.as_secs();
+        encoder.add_text_chunk("Thumb::MTime".to_owned(), mtime.to_string())?;
+        encoder.add_text_chunk("Thumb::Size".to_owned(), metadata.len().to_string())?;
+    }
+    encoder.add_text_chunk(
+        "Software".to_owned(),
+        concat!("stl-thumb ", env!("CARGO_PKG_VERSION")).to_owned(),
+    )?;
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&image)?;
+    writer.finish()?;
+
+    Ok(buff)
+}
+
+/// Renders the thumbnail for `uri` and stores it in the thumbnail cache, see
+/// [`thumbnail_for_uri`] and [`thumbnail_path`].
+///
+/// Returns the path the thumbnail was written to.
+///
+/// # Errors
+///
+/// This function will return an error if the thumbnail cannot be rendered, if there is no
+/// cache directory or if the file cannot be written.
+pub async fn save_thumbnail_for_uri(
+    uri: &str,
+    size: ThumbnailSize,
+    opts: &RenderOptions,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let path = thumbnail_path(uri, size).ok_or("Neither XDG_CACHE_HOME nor HOME is set")?;
+    let buff = thumbnail_for_uri(uri, size, opts).await?;
+
+    let dir = path.parent().ok_or("Invalid thumbnail path")?;
+    fs::create_dir_all(dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    }
+    // The spec asks for thumbnails only readable by the user, which temporary files are created
+    // as. Written to one of its own first, so other programs never read a partial thumbnail and
+    // concurrent saves don't write to the same file
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    tmp.write_all(&buff)?;
+    tmp.persist(&path)?;
+
+    Ok(path)
+}